@@ -0,0 +1,231 @@
+//! Plays seeded games with the scripted bot and reports how they went.
+//!
+//! `balance [--games N] [--seed FIRST] [--max-seconds S] [--threads T] [--mode MODE]
+//! [--difficulty LEVEL] [--adaptive] [--format csv|json] [--out FILE]`
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, Write},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use bevy_game::{resources::run_settings::RunSettings, AppState, Bot, Simulation};
+use serde::Serialize;
+
+const TICKS_PER_S: u64 = 60;
+const SURVIVED: &str = "survived";
+
+struct Options {
+    games: u64,
+    first_seed: u64,
+    max_seconds: u64,
+    threads: usize,
+    settings: RunSettings,
+    json: bool,
+    out: Option<String>,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self {
+            games: 100,
+            first_seed: 0,
+            max_seconds: 600,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            settings: RunSettings::default(),
+            json: false,
+            out: None,
+        };
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+            let number = |value: String| value.parse().map_err(|_| format!("bad number {}", value));
+            match arg.as_str() {
+                "--games" => options.games = number(value()?)?,
+                "--seed" => options.first_seed = number(value()?)?,
+                "--max-seconds" => options.max_seconds = number(value()?)?,
+                "--threads" => options.threads = number(value()?)?.max(1) as usize,
+                "--mode" => options.settings.mode = value()?.parse()?,
+                "--difficulty" => options.settings.difficulty = value()?.parse()?,
+                "--adaptive" => options.settings.adaptive = true,
+                "--format" => {
+                    options.json = match value()?.as_str() {
+                        "csv" => false,
+                        "json" => true,
+                        format => return Err(format!("unknown format {}", format)),
+                    }
+                }
+                "--out" => options.out = Some(value()?),
+                _ => return Err(format!("unknown argument {}", arg)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+#[derive(Serialize)]
+struct GameResult {
+    seed: u64,
+    survival_s: f32,
+    score: u32,
+    death_cause: String,
+}
+
+#[derive(Serialize)]
+struct Distribution {
+    mean: f32,
+    min: f32,
+    p10: f32,
+    median: f32,
+    p90: f32,
+    max: f32,
+}
+
+impl Distribution {
+    fn of(mut values: Vec<f32>) -> Self {
+        values.sort_by(f32::total_cmp);
+        let percentile = |p: f32| match values.is_empty() {
+            true => 0.,
+            false => values[((values.len() - 1) as f32 * p).round() as usize],
+        };
+        Self {
+            mean: values.iter().sum::<f32>() / values.len().max(1) as f32,
+            min: percentile(0.),
+            p10: percentile(0.1),
+            median: percentile(0.5),
+            p90: percentile(0.9),
+            max: percentile(1.),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Report {
+    games: usize,
+    survival_s: Distribution,
+    score: Distribution,
+    death_causes: BTreeMap<String, usize>,
+    results: Vec<GameResult>,
+}
+
+impl Report {
+    fn new(results: Vec<GameResult>) -> Self {
+        let mut death_causes = BTreeMap::new();
+        for result in results.iter() {
+            *death_causes.entry(result.death_cause.clone()).or_default() += 1;
+        }
+        Self {
+            games: results.len(),
+            survival_s: Distribution::of(results.iter().map(|r| r.survival_s).collect()),
+            score: Distribution::of(results.iter().map(|r| r.score as f32).collect()),
+            death_causes,
+            results,
+        }
+    }
+
+    fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "seed,survival_s,score,death_cause")?;
+        for r in self.results.iter() {
+            writeln!(
+                out,
+                "{},{:.2},{},{}",
+                r.seed, r.survival_s, r.score, r.death_cause
+            )?;
+        }
+        Ok(())
+    }
+
+    fn print_summary(&self) {
+        let line = |name: &str, d: &Distribution| {
+            eprintln!(
+                "{:<10} mean {:>8.1}  p10 {:>8.1}  median {:>8.1}  p90 {:>8.1}  max {:>8.1}",
+                name, d.mean, d.p10, d.median, d.p90, d.max
+            )
+        };
+        eprintln!("{} games", self.games);
+        line("survival", &self.survival_s);
+        line("score", &self.score);
+        for (cause, count) in self.death_causes.iter() {
+            eprintln!("{:<24} {:>6}", cause, count);
+        }
+    }
+}
+
+fn play(seed: u64, settings: RunSettings, max_ticks: u64) -> GameResult {
+    let mut sim = Simulation::with_settings(seed, settings);
+    let mut bot = Bot::default();
+    // the run ends with the last life, the game over screen follows a bit later
+    while sim.state() == AppState::Playing && sim.tick() < max_ticks {
+        let observation = sim.observe();
+        if observation.player.is_none() {
+            break;
+        }
+        sim.step(bot.act(&observation));
+    }
+    GameResult {
+        seed,
+        survival_s: sim.tick() as f32 / TICKS_PER_S as f32,
+        score: sim.score(),
+        death_cause: sim.death_cause().unwrap_or_else(|| SURVIVED.to_string()),
+    }
+}
+
+fn main() {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+
+    let next_game = Arc::new(AtomicU64::new(0));
+    let max_ticks = options.max_seconds * TICKS_PER_S;
+    let workers: Vec<_> = (0..options.threads)
+        .map(|_| {
+            let next_game = next_game.clone();
+            let (games, first_seed, settings) =
+                (options.games, options.first_seed, options.settings);
+            thread::spawn(move || {
+                let mut results = Vec::new();
+                loop {
+                    let game = next_game.fetch_add(1, Ordering::Relaxed);
+                    if game >= games {
+                        return results;
+                    }
+                    results.push(play(first_seed + game, settings, max_ticks));
+                }
+            })
+        })
+        .collect();
+    let mut results: Vec<GameResult> = workers
+        .into_iter()
+        .flat_map(|worker| worker.join().expect("simulation thread panicked"))
+        .collect();
+    results.sort_by_key(|result| result.seed);
+
+    let report = Report::new(results);
+    report.print_summary();
+    let mut out: Box<dyn Write> = match &options.out {
+        Some(path) => match File::create(path) {
+            Ok(file) => Box::new(file),
+            Err(err) => {
+                eprintln!("Failed to create {}: {}", path, err);
+                std::process::exit(1);
+            }
+        },
+        None => Box::new(io::stdout()),
+    };
+    let written = match options.json {
+        true => serde_json::to_writer_pretty(&mut out, &report).map_err(io::Error::from),
+        false => report.write_csv(&mut out),
+    };
+    if let Err(err) = written {
+        eprintln!("Failed to write the report: {}", err);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,251 @@
+//! Serves headless runs over a local socket for training agents, gym style.
+//!
+//! `gym_server [--tcp ADDR | --unix PATH] [--max-seconds S]`, listens on TCP 127.0.0.1:5555 by default.
+//!
+//! Every connection gets its own environment. Requests and responses are single
+//! lines of JSON:
+//! - `{"cmd": "spec"}` describes the action space.
+//! - `{"cmd": "reset", "seed": 42}` starts an episode, the seed is optional.
+//! - `{"cmd": "step", "action": 65}` plays one fixed step. The action is a byte
+//!   of held player actions, bit `i` being `spec.actions[i]`.
+//! - `{"cmd": "close"}` ends the connection.
+//!
+//! `reset` and `step` answer with the observation, the reward (score gained in
+//! the step, less a penalty on death), `done` once the player is dead and
+//! `truncated` once the episode hits its time limit. Failures answer with `error`.
+
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::TcpListener,
+    thread,
+};
+
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
+
+use bevy_game::{EntityState, InputActions, Observation, Simulation};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_ADDR: &str = "127.0.0.1:5555";
+const TICKS_PER_S: u64 = 60;
+const DEATH_PENALTY: f32 = 100.;
+
+#[derive(Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+enum Request {
+    Spec,
+    Reset { seed: Option<u64> },
+    Step { action: u8 },
+    Close,
+}
+
+#[derive(Serialize)]
+struct Spec {
+    actions: [&'static str; 8],
+    ticks_per_second: u64,
+    max_ticks: u64,
+}
+
+#[derive(Serialize)]
+struct EntityJson {
+    x: f32,
+    y: f32,
+    vx: f32,
+    vy: f32,
+    rotation: f32,
+}
+
+impl From<&EntityState> for EntityJson {
+    fn from(state: &EntityState) -> Self {
+        Self {
+            x: state.position.x,
+            y: state.position.y,
+            vx: state.velocity.x,
+            vy: state.velocity.y,
+            rotation: state.rotation,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ObservationJson {
+    tick: u64,
+    player: Option<EntityJson>,
+    enemies: Vec<EntityJson>,
+    player_projectiles: Vec<EntityJson>,
+    enemy_projectiles: Vec<EntityJson>,
+    pickups: Vec<EntityJson>,
+    score: u32,
+    lives: u32,
+}
+
+impl From<&Observation> for ObservationJson {
+    fn from(observation: &Observation) -> Self {
+        let entities = |states: &[EntityState]| states.iter().map(EntityJson::from).collect();
+        Self {
+            tick: observation.tick,
+            player: observation.player.as_ref().map(EntityJson::from),
+            enemies: entities(&observation.enemies),
+            player_projectiles: entities(&observation.player_projectiles),
+            enemy_projectiles: entities(&observation.enemy_projectiles),
+            pickups: entities(&observation.pickups),
+            score: observation.score,
+            lives: observation.lives,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct StepResponse {
+    observation: ObservationJson,
+    reward: f32,
+    done: bool,
+    truncated: bool,
+    death_cause: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+struct Environment {
+    sim: Option<Simulation>,
+    max_ticks: u64,
+}
+
+impl Environment {
+    fn reset(&mut self, seed: Option<u64>) -> StepResponse {
+        let mut sim = Simulation::new(seed.unwrap_or_else(rand::random));
+        let response = self.response(&mut sim, 0.);
+        self.sim = Some(sim);
+        response
+    }
+
+    fn step(&mut self, action: u8) -> Result<StepResponse, String> {
+        let mut sim = self.sim.take().ok_or("no episode, reset first")?;
+        if sim.death_cause().is_some() || sim.tick() >= self.max_ticks {
+            self.sim = Some(sim);
+            return Err("the episode is over, reset first".to_string());
+        }
+        let score = sim.score();
+        sim.step(InputActions::from_bits_truncate(action));
+        let mut reward = sim.score().saturating_sub(score) as f32;
+        if sim.death_cause().is_some() {
+            reward -= DEATH_PENALTY;
+        }
+        let response = self.response(&mut sim, reward);
+        self.sim = Some(sim);
+        Ok(response)
+    }
+
+    fn response(&self, sim: &mut Simulation, reward: f32) -> StepResponse {
+        let death_cause = sim.death_cause();
+        StepResponse {
+            observation: ObservationJson::from(&sim.observe()),
+            reward,
+            done: death_cause.is_some(),
+            truncated: death_cause.is_none() && sim.tick() >= self.max_ticks,
+            death_cause,
+        }
+    }
+}
+
+fn serve(reader: impl io::Read, mut writer: impl Write, max_ticks: u64) -> io::Result<()> {
+    let mut env = Environment {
+        sim: None,
+        max_ticks,
+    };
+    for line in BufReader::new(reader).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str(&line) {
+            Ok(Request::Spec) => serde_json::to_string(&Spec {
+                actions: InputActions::NAMES,
+                ticks_per_second: TICKS_PER_S,
+                max_ticks,
+            }),
+            Ok(Request::Reset { seed }) => serde_json::to_string(&env.reset(seed)),
+            Ok(Request::Step { action }) => match env.step(action) {
+                Ok(response) => serde_json::to_string(&response),
+                Err(error) => serde_json::to_string(&ErrorResponse { error }),
+            },
+            Ok(Request::Close) => return Ok(()),
+            Err(err) => serde_json::to_string(&ErrorResponse {
+                error: err.to_string(),
+            }),
+        }?;
+        writeln!(writer, "{}", response)?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+enum Listen {
+    Tcp(String),
+    #[cfg(unix)]
+    Unix(String),
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<(Listen, u64), String> {
+    let mut listen = Listen::Tcp(DEFAULT_ADDR.to_string());
+    let mut max_seconds = 600;
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--tcp" => listen = Listen::Tcp(value()?),
+            #[cfg(unix)]
+            "--unix" => listen = Listen::Unix(value()?),
+            "--max-seconds" => {
+                let value = value()?;
+                max_seconds = value.parse().map_err(|_| format!("bad number {}", value))?;
+            }
+            _ => return Err(format!("unknown argument {}", arg)),
+        }
+    }
+    Ok((listen, max_seconds * TICKS_PER_S))
+}
+
+fn main() -> io::Result<()> {
+    let (listen, max_ticks) = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+
+    let handle = move |result: io::Result<()>| {
+        if let Err(err) = result {
+            eprintln!("Connection closed: {}", err);
+        }
+    };
+    match listen {
+        Listen::Tcp(addr) => {
+            let listener = TcpListener::bind(&addr)?;
+            eprintln!("Listening on {}", addr);
+            for stream in listener.incoming() {
+                let stream = stream?;
+                let reader = stream.try_clone()?;
+                thread::spawn(move || handle(serve(reader, stream, max_ticks)));
+            }
+        }
+        #[cfg(unix)]
+        Listen::Unix(path) => {
+            // a socket left over by a previous server would fail the bind
+            if std::fs::metadata(&path).is_ok_and(|meta| meta.file_type().is_socket()) {
+                std::fs::remove_file(&path)?;
+            }
+            let listener = std::os::unix::net::UnixListener::bind(&path)?;
+            eprintln!("Listening on {}", path);
+            for stream in listener.incoming() {
+                let stream = stream?;
+                let reader = stream.try_clone()?;
+                thread::spawn(move || handle(serve(reader, stream, max_ticks)));
+            }
+        }
+    }
+    Ok(())
+}
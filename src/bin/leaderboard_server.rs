@@ -0,0 +1,286 @@
+//! Stand-in for the online leaderboard, keeping scores in memory to test the
+//! whole flow locally.
+//!
+//! `leaderboard_server [--addr ADDR]`, listens on 127.0.0.1:8787 by default and
+//! prints `listening on ADDR` once it's ready, port 0 picks a free one.
+//!
+//! - `POST /scores` takes a submission and answers with its `rank`.
+//! - `GET /scores/top?board=B&n=N` lists the best N entries of the board.
+//! - `GET /scores/around?board=B&player=P&n=N` lists the entries up to N ranks
+//!   above and below the player's best.
+//!
+//! Listings return at most 100 rows either way.
+//!
+//! Attached replays are played back headlessly in the background, the entry is
+//! verified once the replay scores what was submitted.
+
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use bevy_game::{
+    leaderboard::{
+        http::{self, Request},
+        LeaderboardEntry, Submission, SubmitResponse, Verification,
+    },
+    resources::{
+        game_tuning::{GameTuning, TuningPresets},
+        launch_options::LaunchOptions,
+        replay::Replay,
+        run_settings::{GameMode, RunSettings},
+    },
+    AppState, InputActions, Simulation,
+};
+use serde::Serialize;
+
+const DEFAULT_ADDR: &str = "127.0.0.1:8787";
+const DEFAULT_ROWS: usize = 10;
+/// Most rows a listing returns, larger counts are clamped to it.
+const MAX_ROWS: usize = 100;
+/// How long a connection may stall reading the request or taking the response.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+/// Steps played past the end of a replay before giving up on it ending the run.
+const REPLAY_GRACE_TICKS: usize = 10;
+/// Longest run accepted on boards without a time limit, in seconds.
+const MAX_RUN_S: f32 = 2. * 60. * 60.;
+
+struct Stored {
+    player: String,
+    score: u32,
+    verification: Verification,
+}
+
+/// Entries of every board, best score first and earlier submissions first among equals.
+#[derive(Default)]
+struct Boards(HashMap<String, Vec<Stored>>);
+
+impl Boards {
+    /// Ranks the entry, returning its index on the board.
+    fn insert(&mut self, board: &str, entry: Stored) -> usize {
+        let entries = self.0.entry(board.to_string()).or_default();
+        let index = entries.partition_point(|stored| stored.score >= entry.score);
+        entries.insert(index, entry);
+        index
+    }
+
+    /// Up to `len` entries from index `start` on.
+    fn entries(&self, board: &str, start: usize, len: usize) -> Vec<LeaderboardEntry> {
+        let entries = self.0.get(board).map_or(&[][..], |entries| entries);
+        entries
+            .iter()
+            .enumerate()
+            .skip(start)
+            .take(len)
+            .map(|(index, stored)| LeaderboardEntry {
+                rank: index as u32 + 1,
+                player: stored.player.clone(),
+                score: stored.score,
+                verification: stored.verification,
+            })
+            .collect()
+    }
+
+    fn best_of(&self, board: &str, player: &str) -> Option<usize> {
+        self.0
+            .get(board)?
+            .iter()
+            .position(|stored| stored.player == player)
+    }
+}
+
+type Shared = Arc<Mutex<Boards>>;
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+fn main() {
+    let mut addr = DEFAULT_ADDR.to_string();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--addr", Some(value)) => addr = value,
+            _ => {
+                eprintln!("usage: leaderboard_server [--addr ADDR]");
+                std::process::exit(2);
+            }
+        }
+    }
+    let listener = match TcpListener::bind(&addr) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("failed to listen on {}: {}", addr, err);
+            std::process::exit(1);
+        }
+    };
+    match listener.local_addr() {
+        Ok(local) => println!("listening on {}", local),
+        Err(_) => println!("listening on {}", addr),
+    }
+    let _ = io::stdout().flush();
+
+    let boards = Shared::default();
+    for stream in listener.incoming().flatten() {
+        let boards = boards.clone();
+        thread::spawn(move || {
+            if let Err(err) = serve(stream, &boards) {
+                eprintln!("connection failed: {}", err);
+            }
+        });
+    }
+}
+
+fn serve(mut stream: TcpStream, boards: &Shared) -> io::Result<()> {
+    stream.set_read_timeout(Some(CONNECTION_TIMEOUT))?;
+    stream.set_write_timeout(Some(CONNECTION_TIMEOUT))?;
+    let request = match http::read_request(&mut stream) {
+        Ok(request) => request,
+        Err(err) => return respond_error(&mut stream, 400, err.to_string()),
+    };
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/scores") => submit(&mut stream, boards, &request),
+        ("GET", "/scores/top") => {
+            let (board, n) = match board_and_rows(&request) {
+                Ok(params) => params,
+                Err(err) => return respond_error(&mut stream, 400, err),
+            };
+            let entries = boards.lock().unwrap().entries(board, 0, n);
+            respond(&mut stream, 200, &entries)
+        }
+        ("GET", "/scores/around") => {
+            let (board, n) = match board_and_rows(&request) {
+                Ok(params) => params,
+                Err(err) => return respond_error(&mut stream, 400, err),
+            };
+            let player = match request.param("player") {
+                Some(player) => player,
+                None => return respond_error(&mut stream, 400, "missing player".to_string()),
+            };
+            let boards = boards.lock().unwrap();
+            let entries = match boards.best_of(board, player) {
+                Some(index) => {
+                    let start = index.saturating_sub(n);
+                    boards.entries(board, start, (index - start).saturating_add(n + 1))
+                }
+                None => Vec::new(),
+            };
+            respond(&mut stream, 200, &entries)
+        }
+        (_, "/scores" | "/scores/top" | "/scores/around") => {
+            respond_error(&mut stream, 405, "method not allowed".to_string())
+        }
+        _ => respond_error(&mut stream, 404, "not found".to_string()),
+    }
+}
+
+fn submit(stream: &mut TcpStream, boards: &Shared, request: &Request) -> io::Result<()> {
+    let submission: Submission = match serde_json::from_str(&request.body) {
+        Ok(submission) => submission,
+        Err(err) => return respond_error(stream, 400, err.to_string()),
+    };
+    if submission.board.is_empty() || submission.player.trim().is_empty() {
+        return respond_error(stream, 400, "missing board or player".to_string());
+    }
+    let replay = match submission.decode_replay() {
+        Some(Ok(replay)) if replay.seed != submission.seed => {
+            return respond_error(stream, 400, "replay seed mismatch".to_string())
+        }
+        Some(Ok(replay)) => Some(replay),
+        Some(Err(err)) => return respond_error(stream, 400, err.to_string()),
+        None => None,
+    };
+    let presets = TuningPresets::load_or_default().unwrap_or_default();
+    if let Some(replay) = &replay {
+        let tuning = presets.tuning(replay.settings.difficulty);
+        if replay.ticks() > max_run_ticks(&replay.settings, &tuning) {
+            return respond_error(stream, 400, "replay runs longer than allowed".to_string());
+        }
+    }
+    let verification = match replay {
+        Some(_) => Verification::Pending,
+        None => Verification::Unverified,
+    };
+    let index = boards.lock().unwrap().insert(
+        &submission.board,
+        Stored {
+            player: submission.player.clone(),
+            score: submission.score,
+            verification,
+        },
+    );
+    if let Some(replay) = replay {
+        let boards = boards.clone();
+        thread::spawn(move || verify(&boards, &submission, replay, presets));
+    }
+    respond(
+        stream,
+        201,
+        &SubmitResponse {
+            rank: index as u32 + 1,
+        },
+    )
+}
+
+/// Plays the replay back and marks the submission with what it scored.
+fn verify(boards: &Shared, submission: &Submission, replay: Replay, presets: TuningPresets) {
+    let ticks = replay.ticks() + REPLAY_GRACE_TICKS;
+    let mut sim = Simulation::with_options(LaunchOptions {
+        replay: Some(replay),
+        tuning: presets,
+        ..Default::default()
+    });
+    for _ in 0..ticks {
+        if sim.state() != AppState::Playing {
+            break;
+        }
+        sim.step(InputActions::empty());
+    }
+    let verification = match sim.state() != AppState::Playing && sim.score() == submission.score {
+        true => Verification::Verified,
+        false => Verification::Mismatch,
+    };
+    let mut boards = boards.lock().unwrap();
+    // the entry still pending for the same player and score
+    if let Some(stored) = boards.0.get_mut(&submission.board).and_then(|entries| {
+        entries.iter_mut().find(|stored| {
+            stored.verification == Verification::Pending
+                && stored.player == submission.player
+                && stored.score == submission.score
+        })
+    }) {
+        stored.verification = verification;
+    }
+}
+
+/// Steps a run with the settings may last, time attack ends on its own before that.
+fn max_run_ticks(settings: &RunSettings, tuning: &GameTuning) -> usize {
+    let seconds = match settings.mode {
+        GameMode::TimeAttack => tuning.modes.time_attack_s.min(MAX_RUN_S),
+        _ => MAX_RUN_S,
+    };
+    (seconds / tuning.time_step).ceil() as usize
+}
+
+fn board_and_rows(request: &Request) -> Result<(&str, usize), String> {
+    let board = request.param("board").ok_or("missing board")?;
+    let rows = match request.param("n") {
+        Some(n) => n.parse().map_err(|_| format!("bad row count {}", n))?,
+        None => DEFAULT_ROWS,
+    };
+    Ok((board, rows.min(MAX_ROWS)))
+}
+
+fn respond(stream: &mut TcpStream, status: u16, body: &impl Serialize) -> io::Result<()> {
+    let body = serde_json::to_string(body).map_err(io::Error::other)?;
+    http::write_response(stream, status, &body)
+}
+
+fn respond_error(stream: &mut TcpStream, status: u16, error: String) -> io::Result<()> {
+    respond(stream, status, &ErrorResponse { error })
+}
@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+
+use crate::{EntityState, InputActions, Observation};
+
+/// Distance under which a projectile passing by is worth dodging.
+const PROJECTILE_DANGER_PX: f32 = 130.;
+/// How far ahead incoming projectiles are extrapolated.
+const LOOKAHEAD_S: f32 = 0.75;
+/// Distance kept from enemy ships to avoid ramming them.
+const ENEMY_DANGER_PX: f32 = 130.;
+/// Pickups further away than this are left alone.
+const PICKUP_RANGE_PX: f32 = 260.;
+const AIM_TOLERANCE_PX: f32 = 10.;
+/// Height above the starting row the bot drifts back from.
+const HOME_TOLERANCE_PX: f32 = 30.;
+/// Share of the wanted direction an axis needs before its key is held.
+const AXIS_DEADZONE: f32 = 0.3;
+
+/// Scripted player for balancing runs, it dodges incoming fire and enemy ships,
+/// collects nearby drops and otherwise lines up under the closest enemy.
+/// Firing is always held, ship rotation is never used.
+#[derive(Default)]
+pub struct Bot {
+    home: Option<Vec2>,
+}
+
+impl Bot {
+    pub fn act(&mut self, observation: &Observation) -> InputActions {
+        let player = match observation.player {
+            Some(player) => player,
+            None => return InputActions::empty(),
+        };
+        let home = *self.home.get_or_insert(player.position);
+
+        let dodge = dodge_direction(player.position, observation);
+        let direction = match dodge.length() > 0. {
+            true => dodge,
+            false => nearest(player.position, &observation.pickups)
+                .filter(|pickup| pickup.position.distance(player.position) < PICKUP_RANGE_PX)
+                .map(|pickup| pickup.position - player.position)
+                .unwrap_or_else(|| attack_direction(player.position, home, observation)),
+        };
+        to_actions(direction) | InputActions::FIRE
+    }
+}
+
+/// Sum of pushes away from everything about to hit the player, zero when it's safe.
+fn dodge_direction(position: Vec2, observation: &Observation) -> Vec2 {
+    let projectiles = observation
+        .enemy_projectiles
+        .iter()
+        .filter_map(|projectile| {
+            let offset = projectile.position - position;
+            let speed_sq = projectile.velocity.length_squared();
+            let time = match speed_sq > 0. {
+                true => (-offset.dot(projectile.velocity) / speed_sq).clamp(0., LOOKAHEAD_S),
+                false => 0.,
+            };
+            let closest = offset + projectile.velocity * time;
+            let distance = closest.length();
+            if distance >= PROJECTILE_DANGER_PX {
+                return None;
+            }
+            // a projectile heading straight in is sidestepped across its path
+            let away = match distance > 1. {
+                true => -closest / distance,
+                false => projectile.velocity.perp().normalize_or_zero(),
+            };
+            Some(away * (1. - distance / PROJECTILE_DANGER_PX))
+        });
+    let enemies = observation.enemies.iter().filter_map(|enemy| {
+        let offset = enemy.position - position;
+        let distance = offset.length();
+        match distance < ENEMY_DANGER_PX {
+            true => Some(-offset.normalize_or_zero() * (1. - distance / ENEMY_DANGER_PX)),
+            false => None,
+        }
+    });
+    projectiles.chain(enemies).sum()
+}
+
+fn attack_direction(position: Vec2, home: Vec2, observation: &Observation) -> Vec2 {
+    let x = match nearest(position, &observation.enemies) {
+        Some(enemy) if (enemy.position.x - position.x).abs() > AIM_TOLERANCE_PX => {
+            (enemy.position.x - position.x).signum()
+        }
+        _ => 0.,
+    };
+    let y = match position.y - home.y > HOME_TOLERANCE_PX {
+        true => -1.,
+        false => 0.,
+    };
+    Vec2::new(x, y)
+}
+
+fn nearest(position: Vec2, entities: &[EntityState]) -> Option<&EntityState> {
+    entities.iter().min_by(|a, b| {
+        a.position
+            .distance_squared(position)
+            .total_cmp(&b.position.distance_squared(position))
+    })
+}
+
+fn to_actions(direction: Vec2) -> InputActions {
+    let direction = direction.normalize_or_zero();
+    let mut actions = InputActions::empty();
+    actions.set(InputActions::RIGHT, direction.x > AXIS_DEADZONE);
+    actions.set(InputActions::LEFT, direction.x < -AXIS_DEADZONE);
+    actions.set(InputActions::UP, direction.y > AXIS_DEADZONE);
+    actions.set(InputActions::DOWN, direction.y < -AXIS_DEADZONE);
+    actions
+}
@@ -0,0 +1,4 @@
+use bevy::prelude::*;
+
+#[derive(Component, Deref, DerefMut, Clone, Copy)]
+pub struct Damage(pub f32);
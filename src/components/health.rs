@@ -0,0 +1,21 @@
+use bevy::prelude::*;
+
+#[derive(Component, Clone, Copy)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Self { current: max, max }
+    }
+
+    pub fn fraction(&self) -> f32 {
+        (self.current / self.max).clamp(0., 1.)
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.
+    }
+}
@@ -1,3 +1,5 @@
+pub mod damage;
+pub mod health;
 pub mod movable;
 pub mod root_node;
 pub mod sizeable;
@@ -1,21 +1,17 @@
 use bevy::prelude::*;
 
+#[derive(Clone, Copy, Default)]
 pub enum MovementViewportBehavior {
+    #[default]
     None,
     DespawnOnLeave,
     Contain,
 }
 
-impl Default for MovementViewportBehavior {
-    fn default() -> Self {
-        Self::None
-    }
-}
-
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct MovementOptions {
     pub viewport_behavior: MovementViewportBehavior,
 }
 
-#[derive(Component, Deref, DerefMut, Default)]
+#[derive(Component, Clone, Deref, DerefMut, Default)]
 pub struct Movable(pub MovementOptions);
@@ -1,4 +1,4 @@
 use bevy::prelude::*;
 
-#[derive(Component, Deref, DerefMut)]
+#[derive(Component, Clone, Deref, DerefMut)]
 pub struct Sizeable(pub Vec2);
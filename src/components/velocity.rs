@@ -2,7 +2,7 @@ use std::ops::Neg;
 
 use bevy::prelude::*;
 
-#[derive(Component, Deref, DerefMut)]
+#[derive(Component, Clone, Deref, DerefMut)]
 pub struct AngleVelocity(pub f32);
 
 #[derive(Component, Deref, DerefMut, Clone, Copy)]
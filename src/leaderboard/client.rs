@@ -0,0 +1,133 @@
+use std::{fmt, io, time::Duration};
+
+use serde::de::DeserializeOwned;
+
+use super::{
+    http::{self, percent_encode},
+    LeaderboardEntry, Submission, SubmitResponse,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+pub enum LeaderboardError {
+    /// The service couldn't be reached.
+    Io(io::Error),
+    /// The service answered with an error status.
+    Status(u16, String),
+    /// The answer wasn't the expected JSON.
+    Format(String),
+}
+
+impl LeaderboardError {
+    /// Whether the same request may succeed later, rejected submissions never do.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            LeaderboardError::Io(_) => true,
+            LeaderboardError::Status(status, _) => *status >= 500,
+            LeaderboardError::Format(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for LeaderboardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LeaderboardError::Io(err) => write!(f, "leaderboard unreachable: {}", err),
+            LeaderboardError::Status(status, body) => {
+                write!(f, "leaderboard answered {}: {}", status, body)
+            }
+            LeaderboardError::Format(err) => write!(f, "bad leaderboard answer: {}", err),
+        }
+    }
+}
+
+impl From<io::Error> for LeaderboardError {
+    fn from(err: io::Error) -> Self {
+        LeaderboardError::Io(err)
+    }
+}
+
+/// Talks to the leaderboard service at an `http://host:port[/prefix]` url.
+#[derive(Clone, Debug)]
+pub struct LeaderboardClient {
+    host: String,
+    prefix: String,
+    timeout: Duration,
+}
+
+impl LeaderboardClient {
+    pub fn new(url: &str) -> Result<Self, String> {
+        let rest = match url.split_once("://") {
+            Some(("http", rest)) => rest,
+            Some((scheme, _)) => return Err(format!("unsupported leaderboard scheme {}", scheme)),
+            None => url,
+        };
+        let (host, prefix) = match rest.find('/') {
+            Some(i) => rest.split_at(i),
+            None => (rest, ""),
+        };
+        if host.is_empty() {
+            return Err(format!("bad leaderboard url {}", url));
+        }
+        // the default http port when none is given
+        let host = match host.contains(':') {
+            true => host.to_string(),
+            false => format!("{}:80", host),
+        };
+        Ok(Self {
+            host,
+            prefix: prefix.trim_end_matches('/').to_string(),
+            timeout: TIMEOUT,
+        })
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn submit(&self, submission: &Submission) -> Result<SubmitResponse, LeaderboardError> {
+        let body = serde_json::to_string(submission)
+            .map_err(|err| LeaderboardError::Format(err.to_string()))?;
+        self.request("POST", "/scores", Some(&body))
+    }
+
+    /// The best `n` scores of the board.
+    pub fn top(&self, board: &str, n: usize) -> Result<Vec<LeaderboardEntry>, LeaderboardError> {
+        let target = format!("/scores/top?board={}&n={}", percent_encode(board), n);
+        self.request("GET", &target, None)
+    }
+
+    /// Scores up to `n` ranks above and below the player's best, empty if they
+    /// aren't on the board.
+    pub fn around(
+        &self,
+        board: &str,
+        player: &str,
+        n: usize,
+    ) -> Result<Vec<LeaderboardEntry>, LeaderboardError> {
+        let target = format!(
+            "/scores/around?board={}&player={}&n={}",
+            percent_encode(board),
+            percent_encode(player),
+            n
+        );
+        self.request("GET", &target, None)
+    }
+
+    fn request<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        target: &str,
+        body: Option<&str>,
+    ) -> Result<T, LeaderboardError> {
+        let target = format!("{}{}", self.prefix, target);
+        let response = http::send(&self.host, method, &target, body, self.timeout)?;
+        if !(200..300).contains(&response.status) {
+            return Err(LeaderboardError::Status(response.status, response.body));
+        }
+        serde_json::from_str(&response.body)
+            .map_err(|err| LeaderboardError::Format(err.to_string()))
+    }
+}
@@ -0,0 +1,207 @@
+//! Just enough HTTP/1.1 for the leaderboard client and its mock server, one
+//! request per connection with bodies sized by `Content-Length`.
+
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+/// Largest body either side accepts, replays included.
+const MAX_BODY_LEN: usize = 4 << 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    /// Path without the query string.
+    pub path: String,
+    /// Decoded query parameters, in order.
+    pub query: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Request {
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+/// Reads a request the way the mock server receives it.
+pub fn read_request(stream: impl Read) -> io::Result<Request> {
+    let mut reader = BufReader::new(stream);
+    let (start, content_length) = read_head(&mut reader)?;
+    let mut parts = start.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(method), Some(target)) => (method.to_string(), target),
+        _ => return Err(invalid("bad request line")),
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect();
+    let body = read_body(&mut reader, content_length.unwrap_or(0))?;
+    Ok(Request {
+        method,
+        path: path.to_string(),
+        query,
+        body,
+    })
+}
+
+pub fn write_response(mut stream: impl Write, status: u16, body: &str) -> io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// Sends a request to `host` (`name:port`) and waits for the whole response.
+pub fn send(
+    host: &str,
+    method: &str,
+    target: &str,
+    body: Option<&str>,
+    timeout: Duration,
+) -> io::Result<Response> {
+    let addr = host
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| invalid("host has no address"))?;
+    let mut stream = TcpStream::connect_timeout(&addr, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    let body = body.unwrap_or("");
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        method,
+        target,
+        host,
+        body.len(),
+        body
+    )?;
+    stream.flush()?;
+
+    let mut reader = BufReader::new(stream);
+    let (start, content_length) = read_head(&mut reader)?;
+    let status = start
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| invalid("bad status line"))?;
+    let body = match content_length {
+        Some(len) => read_body(&mut reader, len)?,
+        // without a length the body runs until the server closes the connection
+        None => {
+            let mut body = String::new();
+            reader.take(MAX_BODY_LEN as u64).read_to_string(&mut body)?;
+            body
+        }
+    };
+    Ok(Response { status, body })
+}
+
+/// Percent-encodes everything but the unreserved characters, for query values.
+pub fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn percent_decode(text: &str) -> String {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = match (byte, tail) {
+            (b'%', [high, low, ..]) => std::str::from_utf8(&[*high, *low])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+            _ => None,
+        };
+        match (escaped, byte) {
+            (Some(escaped), _) => {
+                bytes.push(escaped);
+                rest = &tail[2..];
+            }
+            (None, b'+') => {
+                bytes.push(b' ');
+                rest = tail;
+            }
+            (None, _) => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// The start line and the `Content-Length`, if any, of a request or response.
+fn read_head(reader: &mut impl BufRead) -> io::Result<(String, Option<usize>)> {
+    let mut start = String::new();
+    if reader.read_line(&mut start)? == 0 {
+        return Err(invalid("connection closed before the request"));
+    }
+    let mut content_length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                let len = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| invalid("bad content length"))?;
+                content_length = Some(len);
+            }
+        }
+    }
+    Ok((start.trim_end().to_string(), content_length))
+}
+
+fn read_body(reader: &mut impl Read, len: usize) -> io::Result<String> {
+    if len > MAX_BODY_LEN {
+        return Err(invalid("body too large"));
+    }
+    let mut body = vec![0; len];
+    reader.read_exact(&mut body)?;
+    String::from_utf8(body).map_err(|_| invalid("body is not utf-8"))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
@@ -0,0 +1,147 @@
+//! Online leaderboard: scores submitted over HTTP/JSON with their replay, and
+//! queued on disk while the service can't be reached.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::resources::{
+    daily_challenge::DailyChallenge,
+    replay::{Replay, ReplayError},
+    run_settings::GameMode,
+};
+
+pub use client::{LeaderboardClient, LeaderboardError};
+pub use queue::{Delivery, SubmissionQueue};
+
+mod client;
+pub mod http;
+mod queue;
+
+const DEFAULT_PLAYER: &str = "player";
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Where runs are submitted and under which name, set with `--leaderboard URL`.
+#[derive(Resource, Clone, Debug)]
+pub struct LeaderboardSettings {
+    pub client: LeaderboardClient,
+    pub player: String,
+}
+
+impl LeaderboardSettings {
+    /// The player defaults to the name of the user.
+    pub fn new(url: &str, player: Option<String>) -> Result<Self, String> {
+        let player = player
+            .or_else(|| std::env::var("USER").ok())
+            .or_else(|| std::env::var("USERNAME").ok())
+            .unwrap_or_else(|| DEFAULT_PLAYER.to_string());
+        if player.trim().is_empty() {
+            return Err("empty player name".to_string());
+        }
+        Ok(Self {
+            client: LeaderboardClient::new(url)?,
+            player,
+        })
+    }
+}
+
+/// A finished run sent to the leaderboard.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Submission {
+    pub board: String,
+    pub player: String,
+    pub score: u32,
+    pub seed: u64,
+    /// Base64 of the encoded replay, for the service to check the score with.
+    pub replay: Option<String>,
+}
+
+impl Submission {
+    pub fn with_replay(mut self, replay: &Replay) -> Self {
+        self.replay = Some(encode_base64(&replay.encode()));
+        self
+    }
+
+    /// The attached replay, `None` if there is none.
+    pub fn decode_replay(&self) -> Option<Result<Replay, ReplayError>> {
+        let text = self.replay.as_ref()?;
+        Some(
+            decode_base64(text)
+                .ok_or(ReplayError::Format("replay is not base64"))
+                .and_then(|bytes| Replay::decode(&bytes)),
+        )
+    }
+}
+
+/// How far the service got checking a score against its replay.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Verification {
+    /// Submitted without a replay.
+    Unverified,
+    Pending,
+    Verified,
+    /// The replay doesn't score what was submitted.
+    Mismatch,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LeaderboardEntry {
+    /// Starting at 1.
+    pub rank: u32,
+    pub player: String,
+    pub score: u32,
+    pub verification: Verification,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SubmitResponse {
+    pub rank: u32,
+}
+
+/// Board a run of the mode is ranked on, every day gets a board of its own.
+/// `None` for the daily board while the day's challenge isn't known.
+pub fn board_name(mode: GameMode, daily: Option<&DailyChallenge>) -> Option<String> {
+    match (mode, daily) {
+        (GameMode::DailyChallenge, Some(daily)) => {
+            Some(format!("{}-{}", mode.slug(), daily.date()))
+        }
+        (GameMode::DailyChallenge, None) => None,
+        _ => Some(mode.slug()),
+    }
+}
+
+fn encode_base64(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| {
+            group | (byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => text.push(BASE64_ALPHABET[(group >> (18 - 6 * i)) as usize & 0x3f] as char),
+                false => text.push('='),
+            }
+        }
+    }
+    text
+}
+
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim_end_matches('=').as_bytes();
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    for chunk in text.chunks(4) {
+        if chunk.len() == 1 {
+            return None;
+        }
+        let mut group = 0u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            let value = BASE64_ALPHABET.iter().position(|&a| a == c)? as u32;
+            group |= value << (18 - 6 * i);
+        }
+        for i in 0..chunk.len() - 1 {
+            bytes.push((group >> (16 - 8 * i)) as u8);
+        }
+    }
+    Some(bytes)
+}
@@ -0,0 +1,105 @@
+use std::{
+    collections::VecDeque,
+    fs,
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{LeaderboardClient, Submission, SubmitResponse};
+
+const QUEUE_PATH: &str = "leaderboard_queue.json";
+const RETRY_BASE: Duration = Duration::from_secs(2);
+const RETRY_MAX: Duration = Duration::from_secs(300);
+
+/// What became of a queued submission.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Delivery {
+    Submitted(Submission, SubmitResponse),
+    /// Refused by the service, it won't be sent again.
+    Rejected(Submission, String),
+}
+
+/// Submissions not yet taken by the service, sent in order and retried with
+/// exponential backoff while it can't be reached.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct SubmissionQueue {
+    pending: VecDeque<Submission>,
+    #[serde(skip)]
+    failures: u32,
+    #[serde(skip)]
+    retry_at: Option<Instant>,
+}
+
+impl SubmissionQueue {
+    /// Reads the queue left by earlier sessions, a missing or broken file starts empty.
+    pub fn load() -> Self {
+        fs::read_to_string(QUEUE_PATH)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        let data = match serde_json::to_string_pretty(self) {
+            Ok(data) => data,
+            Err(err) => return warn!("Failed to serialize the leaderboard queue: {}", err),
+        };
+        if let Err(err) = fs::write(QUEUE_PATH, data) {
+            warn!("Failed to save the leaderboard queue: {}", err);
+        }
+    }
+
+    pub fn push(&mut self, submission: Submission) {
+        self.pending.push_back(submission);
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Time left before the next attempt, `None` with nothing queued.
+    pub fn retry_in(&self, now: Instant) -> Option<Duration> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(
+            self.retry_at
+                .map_or(Duration::ZERO, |at| at.saturating_duration_since(now)),
+        )
+    }
+
+    /// Sends the queued submissions in order, stopping at the first one the
+    /// service can't take yet and backing off before the next attempt.
+    pub fn flush(&mut self, client: &LeaderboardClient, now: Instant) -> Vec<Delivery> {
+        let mut deliveries = Vec::new();
+        while let Some(submission) = self.pending.front() {
+            match client.submit(submission) {
+                Ok(response) => {
+                    let submission = self.pending.pop_front().unwrap();
+                    deliveries.push(Delivery::Submitted(submission, response));
+                }
+                Err(err) if err.is_retryable() => {
+                    self.failures += 1;
+                    let backoff = RETRY_BASE
+                        .saturating_mul(1 << (self.failures - 1).min(16))
+                        .min(RETRY_MAX);
+                    self.retry_at = Some(now + backoff);
+                    return deliveries;
+                }
+                Err(err) => {
+                    let submission = self.pending.pop_front().unwrap();
+                    deliveries.push(Delivery::Rejected(submission, err.to_string()));
+                }
+            }
+        }
+        self.failures = 0;
+        self.retry_at = None;
+        deliveries
+    }
+}
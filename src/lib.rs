@@ -1,37 +1,144 @@
-use bevy::prelude::*;
-use plugins::{
-    delayed_state_switch_plugin::DelayedStateSwitchPlugin, game::game_plugin::GamePlugin,
-    resources_plugin::ResourcePlugin, sprite_animation_plugin::SpriteAnimationPlugin,
-    start_menu::start_menu_plugin::StartMenuPlugin, ui_interaction_plugin::UiInteractionPlugin,
+use std::time::Duration;
+
+use bevy::{
+    asset::AssetPlugin, hierarchy::HierarchyPlugin, input::InputPlugin, prelude::*,
+    time::TimePlugin, transform::TransformPlugin, window::WindowMode,
+};
+use plugins::game::{movement_plugin::GameClock, replay_plugin::ReplayPlayback};
+use resources::{
+    game_rng::SeedSettings,
+    game_tuning::GameTuning,
+    launch_options::{LaunchOptions, USAGE},
+    score::Score,
+    viewport_size::ViewportSize,
 };
-use resources::viewport_size::ViewportSize;
 
-mod components;
-mod plugins;
-mod resources;
+pub use bot::Bot;
+pub use plugins::bevy_game_plugins::BevyGamePlugins;
+pub use plugins::game::simulation::{EntityState, Observation, Simulation};
+pub use resources::game_config::{GameConfig, Subsystems};
+pub use resources::player_input::InputActions;
+
+mod bot;
+pub mod components;
+pub mod leaderboard;
+pub mod net;
+pub mod plugins;
+pub mod resources;
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub enum AppState {
     MainMenu,
     Playing,
     Paused,
+    GameOver,
+    HighScores,
+    Lobby,
 }
 
-pub const SPRITE_SCALE: f32 = 0.5;
+/// Present in apps built without a window, which leave no files behind.
+#[derive(Resource)]
+pub struct Headless;
 
 pub fn run() {
-    build_app().run();
+    if std::env::args().any(|arg| arg == "--help" || arg == "-h") {
+        return println!("{}", USAGE);
+    }
+    let options = match LaunchOptions::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            std::process::exit(2);
+        }
+    };
+    match options.headless_ticks {
+        Some(ticks) => run_headless(options, ticks),
+        None => build_app(options).run(),
+    }
+}
+
+/// Simulates the run without a window and prints how it ended.
+fn run_headless(options: LaunchOptions, ticks: u64) {
+    let mut app = build_headless_app(options);
+    for _ in 0..ticks {
+        app.update();
+        if *app.world.resource::<State<AppState>>().current() != AppState::Playing {
+            break;
+        }
+    }
+    let world = &app.world;
+    println!(
+        "state {:?}, tick {}, score {}",
+        world.resource::<State<AppState>>().current(),
+        world.resource::<GameClock>().tick,
+        world.get_resource::<Score>().map_or(0, |score| **score),
+    );
+}
+
+fn build_app(options: LaunchOptions) -> App {
+    let mut app = build_game_app(options.apply_to(GameConfig::default()));
+    apply_launch_options(&mut app, options);
+    app
 }
 
-fn build_app() -> App {
+/// The game without a window or rendering, starting straight in a run and
+/// advancing the clock by one fixed step per update so runs are reproducible.
+pub fn build_headless_app(options: LaunchOptions) -> App {
+    let mut app = build_game_app(options.apply_to(GameConfig::headless()));
+    apply_launch_options(&mut app, options);
+    app
+}
+
+/// The engine plugins the config asks for followed by `BevyGamePlugins`,
+/// ready for more plugins and systems before it runs.
+pub fn build_game_app(config: GameConfig) -> App {
     let mut app = App::new();
-    app.add_plugins(DefaultPlugins)
-        .add_plugin(DelayedStateSwitchPlugin)
-        .add_plugin(SpriteAnimationPlugin)
-        .add_plugin(ResourcePlugin)
-        .add_plugin(UiInteractionPlugin)
-        .add_plugin(GamePlugin)
-        .add_plugin(StartMenuPlugin)
-        .add_state(AppState::MainMenu);
+    match config.subsystems.contains(Subsystems::WINDOW) {
+        true => app.add_plugins(DefaultPlugins.set(WindowPlugin {
+            window: WindowDescriptor {
+                width: config.viewport.w,
+                height: config.viewport.h,
+                mode: match config.fullscreen {
+                    true => WindowMode::BorderlessFullscreen,
+                    false => WindowMode::Windowed,
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })),
+        false => app
+            .add_plugins(MinimalPlugins.build().disable::<TimePlugin>())
+            .add_plugin(TransformPlugin)
+            .add_plugin(HierarchyPlugin)
+            .add_plugin(InputPlugin)
+            .add_plugin(AssetPlugin::default())
+            .add_asset::<Image>()
+            .add_asset::<TextureAtlas>()
+            .add_asset::<Font>()
+            .init_resource::<Time>()
+            .insert_resource(Headless)
+            .add_system_to_stage(CoreStage::First, fixed_time_system),
+    };
+    app.add_plugins(BevyGamePlugins::new(config));
     app
 }
+
+fn apply_launch_options(app: &mut App, mut options: LaunchOptions) {
+    let mut settings = options.run;
+    if let Some(replay) = options.replay.take() {
+        app.insert_resource(ReplayPlayback::new(replay, &mut settings));
+    }
+    if let Some(leaderboard) = options.leaderboard.clone() {
+        app.insert_resource(leaderboard);
+    }
+    app.insert_resource(SeedSettings {
+        fixed_seed: options.seed,
+    })
+    .insert_resource(settings)
+    .insert_resource(options);
+}
+
+fn fixed_time_system(mut time: ResMut<Time>, tuning: Res<GameTuning>) {
+    let last_update = time.last_update().unwrap_or_else(|| time.startup());
+    time.update_with_instant(last_update + Duration::from_secs_f32(tuning.time_step));
+}
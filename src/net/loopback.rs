@@ -0,0 +1,103 @@
+use std::{
+    collections::VecDeque,
+    io,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use super::Transport;
+
+/// How packets fare on a simulated link, the same both ways.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LinkConditions {
+    /// One way delay of every packet.
+    pub latency: Duration,
+    /// Extra delay of up to this much, which reorders packets too.
+    pub jitter: Duration,
+    /// Share of the packets dropped, from 0 to 1.
+    pub loss: f32,
+}
+
+struct Link {
+    now: Duration,
+    conditions: LinkConditions,
+    rng: ChaCha8Rng,
+    /// Packets on their way to either end, with the time they arrive.
+    in_flight: [VecDeque<(Duration, Vec<u8>)>; 2],
+}
+
+/// Two peers in the same process with a simulated link in between, to try
+/// out latency and packet loss locally. Time only passes with `advance`, and
+/// the losses follow from the seed, so the link behaves the same every time.
+#[derive(Clone)]
+pub struct LoopbackNetwork(Arc<Mutex<Link>>);
+
+impl LoopbackNetwork {
+    pub fn new(conditions: LinkConditions, seed: u64) -> Self {
+        Self(Arc::new(Mutex::new(Link {
+            now: Duration::ZERO,
+            conditions,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            in_flight: Default::default(),
+        })))
+    }
+
+    /// The two ends of the link.
+    pub fn pair(&self) -> (LoopbackTransport, LoopbackTransport) {
+        let end = |side| LoopbackTransport {
+            link: self.0.clone(),
+            side,
+        };
+        (end(0), end(1))
+    }
+
+    pub fn advance(&self, time: Duration) {
+        self.0.lock().unwrap().now += time;
+    }
+
+    pub fn set_conditions(&self, conditions: LinkConditions) {
+        self.0.lock().unwrap().conditions = conditions;
+    }
+}
+
+/// One end of a `LoopbackNetwork`.
+pub struct LoopbackTransport {
+    link: Arc<Mutex<Link>>,
+    side: usize,
+}
+
+impl Transport for LoopbackTransport {
+    fn send(&mut self, packet: &[u8]) -> io::Result<()> {
+        let mut link = self.link.lock().unwrap();
+        let LinkConditions {
+            latency,
+            jitter,
+            loss,
+        } = link.conditions;
+        if link.rng.gen::<f32>() < loss {
+            return Ok(());
+        }
+        let delay = latency + jitter.mul_f32(link.rng.gen());
+        let arrival = link.now + delay;
+        link.in_flight[1 - self.side].push_back((arrival, packet.to_vec()));
+        Ok(())
+    }
+
+    fn recv(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut link = self.link.lock().unwrap();
+        let now = link.now;
+        let queue = &mut link.in_flight[self.side];
+        let arrived = queue
+            .iter()
+            .enumerate()
+            .filter(|(_, (arrival, _))| *arrival <= now)
+            .min_by_key(|(_, (arrival, _))| *arrival)
+            .map(|(i, _)| i);
+        Ok(arrived
+            .and_then(|i| queue.remove(i))
+            .map(|(_, packet)| packet))
+    }
+}
@@ -0,0 +1,49 @@
+//! Online co-op: two peers simulate the same run from each other's inputs,
+//! sent over UDP. Inputs that are late get guessed, and the run is rolled
+//! back and simulated again when a guess turns out wrong.
+
+use std::{fmt, io};
+
+pub use loopback::{LinkConditions, LoopbackNetwork, LoopbackTransport};
+pub use protocol::{Message, PROTOCOL_VERSION};
+pub use session::{Handshake, RollbackSession, SessionStart, INPUT_DELAY, MAX_PREDICTION};
+pub use transport::{Transport, UdpTransport};
+
+mod loopback;
+mod protocol;
+mod session;
+mod transport;
+
+/// Port a host listens on unless told otherwise.
+pub const DEFAULT_PORT: u16 = 7777;
+
+#[derive(Debug)]
+pub enum NetError {
+    Io(io::Error),
+    Format(&'static str),
+    /// The peer runs another version of the game.
+    Version(u8),
+    /// The host didn't answer.
+    Timeout,
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetError::Io(err) => write!(f, "network error: {}", err),
+            NetError::Format(err) => write!(f, "bad packet: {}", err),
+            NetError::Version(version) => write!(
+                f,
+                "the other player runs protocol version {}, this game {}",
+                version, PROTOCOL_VERSION
+            ),
+            NetError::Timeout => write!(f, "the host didn't answer"),
+        }
+    }
+}
+
+impl From<io::Error> for NetError {
+    fn from(err: io::Error) -> Self {
+        NetError::Io(err)
+    }
+}
@@ -0,0 +1,127 @@
+use crate::resources::{player_input::InputActions, replay::Replay, run_settings::RunSettings};
+
+use super::NetError;
+
+const PACKET_MAGIC: &[u8; 4] = b"BGNP";
+/// Peers only play with the same version of the protocol, and so of the game.
+pub const PROTOCOL_VERSION: u8 = 1;
+const KIND_HELLO: u8 = 0;
+const KIND_WELCOME: u8 = 1;
+const KIND_INPUTS: u8 = 2;
+const KIND_LEAVE: u8 = 3;
+const FLAG_CHECKSUM: u8 = 1 << 0;
+
+/// A single datagram between the two peers.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    /// Asks the host to join its run, sent until the host answers.
+    Hello,
+    /// The host's answer, with the run both peers simulate.
+    Welcome { seed: u64, settings: RunSettings },
+    /// The sender's actions from the `start` step on. `ack` counts the steps
+    /// of the receiver's actions the sender has, `checksum` is the sender's
+    /// state hash after a step both peers have all the actions of.
+    Inputs {
+        ack: u64,
+        start: u64,
+        actions: Vec<InputActions>,
+        checksum: Option<(u64, u64)>,
+    },
+    /// The sender left the run.
+    Leave,
+}
+
+impl Message {
+    /// Magic, version and kind followed by the fields of the message.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = PACKET_MAGIC.to_vec();
+        bytes.push(PROTOCOL_VERSION);
+        match self {
+            Message::Hello => bytes.push(KIND_HELLO),
+            Message::Welcome { seed, settings } => {
+                bytes.push(KIND_WELCOME);
+                // the settings go the way replays store them
+                bytes.extend(Replay::new(*seed, *settings).encode());
+            }
+            Message::Inputs {
+                ack,
+                start,
+                actions,
+                checksum,
+            } => {
+                bytes.push(KIND_INPUTS);
+                bytes.extend_from_slice(&ack.to_le_bytes());
+                bytes.extend_from_slice(&start.to_le_bytes());
+                bytes.push(match checksum {
+                    Some(_) => FLAG_CHECKSUM,
+                    None => 0,
+                });
+                if let Some((tick, checksum)) = checksum {
+                    bytes.extend_from_slice(&tick.to_le_bytes());
+                    bytes.extend_from_slice(&checksum.to_le_bytes());
+                }
+                bytes.extend(actions.iter().map(|actions| actions.bits()));
+            }
+            Message::Leave => bytes.push(KIND_LEAVE),
+        }
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, NetError> {
+        let header_len = PACKET_MAGIC.len() + 2;
+        if bytes.len() < header_len || &bytes[..PACKET_MAGIC.len()] != PACKET_MAGIC {
+            return Err(NetError::Format("missing packet header"));
+        }
+        if bytes[PACKET_MAGIC.len()] != PROTOCOL_VERSION {
+            return Err(NetError::Version(bytes[PACKET_MAGIC.len()]));
+        }
+        let mut rest = &bytes[header_len..];
+        match bytes[header_len - 1] {
+            KIND_HELLO => Ok(Message::Hello),
+            KIND_WELCOME => {
+                let replay =
+                    Replay::decode(rest).map_err(|_| NetError::Format("bad run settings"))?;
+                Ok(Message::Welcome {
+                    seed: replay.seed,
+                    settings: replay.settings,
+                })
+            }
+            KIND_INPUTS => {
+                let ack = read_u64(&mut rest)?;
+                let start = read_u64(&mut rest)?;
+                let (flags, tail) = rest
+                    .split_first()
+                    .ok_or(NetError::Format("missing input flags"))?;
+                rest = tail;
+                let checksum = match flags & FLAG_CHECKSUM != 0 {
+                    true => Some((read_u64(&mut rest)?, read_u64(&mut rest)?)),
+                    false => None,
+                };
+                let actions = rest
+                    .iter()
+                    .map(|bits| {
+                        InputActions::from_bits(*bits)
+                            .ok_or(NetError::Format("unknown input actions"))
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(Message::Inputs {
+                    ack,
+                    start,
+                    actions,
+                    checksum,
+                })
+            }
+            KIND_LEAVE => Ok(Message::Leave),
+            _ => Err(NetError::Format("unknown message")),
+        }
+    }
+}
+
+fn read_u64(bytes: &mut &[u8]) -> Result<u64, NetError> {
+    if bytes.len() < 8 {
+        return Err(NetError::Format("truncated packet"));
+    }
+    let (value, rest) = bytes.split_at(8);
+    *bytes = rest;
+    Ok(u64::from_le_bytes(value.try_into().unwrap()))
+}
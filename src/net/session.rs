@@ -0,0 +1,306 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use instant::Instant;
+
+use crate::{
+    plugins::game::player_plugin::PlayerId,
+    resources::{player_input::InputActions, run_settings::RunSettings},
+};
+
+use super::{protocol::Message, NetError, Transport};
+
+/// Steps local actions are held back for, time for them to reach the peer
+/// before they're simulated.
+pub const INPUT_DELAY: u64 = 2;
+/// Steps simulated past the last one with the peer's actions before waiting for them.
+pub const MAX_PREDICTION: u64 = 8;
+/// Steps of actions repeated in every packet at most, until the peer has them.
+const MAX_PACKET_ACTIONS: usize = 64;
+/// Steps the state hashes are kept for, to compare with the peer's late ones.
+const CHECKSUM_HISTORY: u64 = 300;
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const HELLO_INTERVAL: Duration = Duration::from_millis(250);
+const JOIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Inputs of a two player run simulated on both peers. The peer's actions are
+/// guessed until they arrive, and a wrong guess rolls the run back to the
+/// step it was made for.
+pub struct RollbackSession {
+    player: PlayerId,
+    /// Local actions of every step, the first at index 0.
+    local: Vec<InputActions>,
+    /// The peer's actions as far as they arrived without gaps.
+    remote: Vec<InputActions>,
+    /// What the peer was guessed to do in simulated steps its actions are missing for.
+    predicted: BTreeMap<u64, InputActions>,
+    /// Steps of local actions the peer has.
+    acked: u64,
+    rollback: Option<u64>,
+    rollbacks: u64,
+    /// State hash after each recent step.
+    checksums: BTreeMap<u64, u64>,
+    remote_checksums: BTreeMap<u64, u64>,
+    desync: Option<u64>,
+    last_heard: Instant,
+    left: bool,
+}
+
+impl RollbackSession {
+    /// The host plays first, the joining peer second.
+    pub fn new(player: PlayerId, now: Instant) -> Self {
+        // neither peer acts in the first steps, which leaves time for the actions to arrive
+        let idle = vec![InputActions::empty(); INPUT_DELAY as usize];
+        Self {
+            player,
+            local: idle.clone(),
+            remote: idle,
+            predicted: BTreeMap::new(),
+            acked: INPUT_DELAY,
+            rollback: None,
+            rollbacks: 0,
+            checksums: BTreeMap::new(),
+            remote_checksums: BTreeMap::new(),
+            desync: None,
+            last_heard: now,
+            left: false,
+        }
+    }
+
+    pub fn player(&self) -> PlayerId {
+        self.player
+    }
+
+    /// Last step with the actions of both players.
+    pub fn confirmed_tick(&self) -> u64 {
+        self.remote.len() as u64
+    }
+
+    /// Whether the step after `tick` can be simulated without guessing too far ahead.
+    pub fn can_advance(&self, tick: u64) -> bool {
+        tick < self.confirmed_tick() + MAX_PREDICTION
+    }
+
+    /// Local actions of the step after `tick`, which are simulated once the input delay is over.
+    pub fn add_local(&mut self, tick: u64, actions: InputActions) {
+        while (self.local.len() as u64) < tick + 1 + INPUT_DELAY {
+            self.local.push(actions);
+        }
+    }
+
+    /// Actions of both players for the step, the peer's guessed while they haven't arrived.
+    pub fn inputs(&mut self, tick: u64) -> Vec<InputActions> {
+        let local = self.local.get(tick as usize - 1).copied();
+        let remote = match self.remote.get(tick as usize - 1) {
+            Some(actions) => *actions,
+            None => {
+                // the peer most likely keeps doing what it did last
+                let guess = match self.left {
+                    true => InputActions::empty(),
+                    false => self.remote.last().copied().unwrap_or_default(),
+                };
+                self.predicted.insert(tick, guess);
+                guess
+            }
+        };
+        let local = local.unwrap_or_default();
+        match self.player {
+            PlayerId(0) => vec![local, remote],
+            _ => vec![remote, local],
+        }
+    }
+
+    pub fn receive(&mut self, message: Message, now: Instant) {
+        self.last_heard = now;
+        match message {
+            Message::Inputs {
+                ack,
+                start,
+                actions,
+                checksum,
+            } => {
+                self.acked = self.acked.max(ack.min(self.local.len() as u64));
+                for (tick, actions) in (start..).zip(actions) {
+                    if tick != self.confirmed_tick() + 1 {
+                        continue;
+                    }
+                    self.remote.push(actions);
+                    match self.predicted.remove(&tick) {
+                        Some(guess) if guess != actions => self.mispredicted(tick),
+                        _ => (),
+                    }
+                }
+                if let Some((tick, checksum)) = checksum {
+                    self.remote_checksums.insert(tick, checksum);
+                }
+                self.compare_checksums();
+            }
+            Message::Leave => self.left = true,
+            Message::Hello | Message::Welcome { .. } => (),
+        }
+    }
+
+    fn mispredicted(&mut self, tick: u64) {
+        self.rollback = Some(self.rollback.map_or(tick, |from| from.min(tick)));
+        // the state from there on is simulated again
+        self.checksums.split_off(&tick);
+    }
+
+    /// First step simulated with a wrong guess, to roll the run back to the state before it.
+    pub fn take_rollback(&mut self) -> Option<u64> {
+        let from = self.rollback.take()?;
+        self.rollbacks += 1;
+        Some(from)
+    }
+
+    /// Rollbacks so far.
+    pub fn rollbacks(&self) -> u64 {
+        self.rollbacks
+    }
+
+    /// Records the state hash after the step, for the peer to compare with its own.
+    pub fn record_checksum(&mut self, tick: u64, checksum: u64) {
+        self.checksums.insert(tick, checksum);
+        self.checksums = self
+            .checksums
+            .split_off(&tick.saturating_sub(CHECKSUM_HISTORY));
+        self.compare_checksums();
+    }
+
+    /// Hashes of the states after steps both players' actions are known for.
+    pub fn confirmed_checksums(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.checksums
+            .range(..=self.confirmed_tick())
+            .map(|(tick, checksum)| (*tick, *checksum))
+    }
+
+    fn compare_checksums(&mut self) {
+        let oldest = self.checksums.keys().next().copied().unwrap_or(0);
+        let confirmed = self.confirmed_tick();
+        let ready: Vec<_> = self
+            .remote_checksums
+            .range(..=confirmed)
+            .map(|(tick, checksum)| (*tick, *checksum))
+            .collect();
+        for (tick, remote) in ready {
+            match self.checksums.get(&tick) {
+                Some(local) if *local != remote => {
+                    self.desync.get_or_insert(tick);
+                }
+                Some(_) => (),
+                // not simulated yet
+                None if tick >= oldest => continue,
+                None => (),
+            }
+            self.remote_checksums.remove(&tick);
+        }
+    }
+
+    /// First step after which the peers' states differed.
+    pub fn desync(&self) -> Option<u64> {
+        self.desync
+    }
+
+    /// The local actions the peer is missing, along with the latest state hash to compare.
+    pub fn message(&self) -> Message {
+        Message::Inputs {
+            ack: self.confirmed_tick(),
+            start: self.acked + 1,
+            actions: self
+                .local
+                .iter()
+                .skip(self.acked as usize)
+                .take(MAX_PACKET_ACTIONS)
+                .copied()
+                .collect(),
+            checksum: self.confirmed_checksums().last(),
+        }
+    }
+
+    /// Whether the peer left the run or hasn't been heard from for too long.
+    pub fn is_disconnected(&self, now: Instant) -> bool {
+        self.left || now.duration_since(self.last_heard) > DISCONNECT_TIMEOUT
+    }
+}
+
+/// The run both peers agreed to play.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SessionStart {
+    pub player: PlayerId,
+    pub seed: u64,
+    pub settings: RunSettings,
+}
+
+/// Getting both peers into the same run, before a `RollbackSession` takes over.
+pub enum Handshake {
+    /// Waiting for a peer to join the run.
+    Host { seed: u64, settings: RunSettings },
+    /// Asking the host to be let in.
+    Join {
+        started: Instant,
+        last_hello: Option<Instant>,
+    },
+}
+
+impl Handshake {
+    pub fn host(seed: u64, settings: RunSettings) -> Self {
+        Handshake::Host { seed, settings }
+    }
+
+    pub fn join(now: Instant) -> Self {
+        Handshake::Join {
+            started: now,
+            last_hello: None,
+        }
+    }
+
+    /// Answers or sends greetings, `Some` once the run can start.
+    pub fn poll(
+        &mut self,
+        transport: &mut dyn Transport,
+        now: Instant,
+    ) -> Result<Option<SessionStart>, NetError> {
+        while let Some(packet) = transport.recv()? {
+            let message = match Message::decode(&packet) {
+                Ok(message) => message,
+                Err(NetError::Format(_)) => continue,
+                Err(err) => return Err(err),
+            };
+            match (&*self, message) {
+                (Handshake::Host { seed, settings }, Message::Hello) => {
+                    let welcome = Message::Welcome {
+                        seed: *seed,
+                        settings: *settings,
+                    };
+                    transport.send(&welcome.encode())?;
+                    return Ok(Some(SessionStart {
+                        player: PlayerId(0),
+                        seed: *seed,
+                        settings: *settings,
+                    }));
+                }
+                (Handshake::Join { .. }, Message::Welcome { seed, settings }) => {
+                    return Ok(Some(SessionStart {
+                        player: PlayerId(1),
+                        seed,
+                        settings,
+                    }));
+                }
+                _ => (),
+            }
+        }
+        if let Handshake::Join {
+            started,
+            last_hello,
+        } = self
+        {
+            if now.duration_since(*started) > JOIN_TIMEOUT {
+                return Err(NetError::Timeout);
+            }
+            if last_hello.is_none_or(|last| now.duration_since(last) >= HELLO_INTERVAL) {
+                transport.send(&Message::Hello.encode())?;
+                *last_hello = Some(now);
+            }
+        }
+        Ok(None)
+    }
+}
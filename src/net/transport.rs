@@ -0,0 +1,87 @@
+use std::{
+    io,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+};
+
+use super::Message;
+
+/// Largest datagram either peer sends.
+const MAX_PACKET: usize = 1200;
+
+/// Unreliable, unordered delivery of packets to the other peer.
+pub trait Transport: Send + Sync {
+    /// Sends the packet if there is a peer to send it to, it may never arrive.
+    fn send(&mut self, packet: &[u8]) -> io::Result<()>;
+    /// The next packet received from the peer, `None` when there is none yet.
+    fn recv(&mut self) -> io::Result<Option<Vec<u8>>>;
+}
+
+/// Packets over a non-blocking UDP socket. A host learns its peer's address
+/// from the first valid `Hello` it receives and ignores anyone else.
+pub struct UdpTransport {
+    socket: UdpSocket,
+    peer: Option<SocketAddr>,
+}
+
+impl UdpTransport {
+    /// Waits for a peer on the port of every local address.
+    pub fn host(port: u16) -> io::Result<Self> {
+        Self::bind(SocketAddr::from(([0, 0, 0, 0], port)), None)
+    }
+
+    /// Talks to the host at `address`, a `host:port` pair.
+    pub fn join(address: &str) -> io::Result<Self> {
+        let peer = address.to_socket_addrs()?.next().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no address for {}", address),
+            )
+        })?;
+        let local = match peer {
+            SocketAddr::V4(_) => SocketAddr::from(([0, 0, 0, 0], 0)),
+            SocketAddr::V6(_) => SocketAddr::from(([0u16; 8], 0)),
+        };
+        Self::bind(local, Some(peer))
+    }
+
+    fn bind(local: SocketAddr, peer: Option<SocketAddr>) -> io::Result<Self> {
+        let socket = UdpSocket::bind(local)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self { socket, peer })
+    }
+
+    pub fn local_port(&self) -> io::Result<u16> {
+        Ok(self.socket.local_addr()?.port())
+    }
+}
+
+impl Transport for UdpTransport {
+    fn send(&mut self, packet: &[u8]) -> io::Result<()> {
+        match self.peer {
+            Some(peer) => self.socket.send_to(packet, peer).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    fn recv(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut buffer = [0; MAX_PACKET];
+        loop {
+            let (len, from) = match self.socket.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+                // an earlier packet bounced off a closed port, the peer may still come
+                Err(err) if err.kind() == io::ErrorKind::ConnectionReset => continue,
+                Err(err) => return Err(err),
+            };
+            match self.peer {
+                Some(peer) if peer != from => continue,
+                Some(_) => (),
+                None if matches!(Message::decode(&buffer[..len]), Ok(Message::Hello)) => {
+                    self.peer = Some(from)
+                }
+                None => continue,
+            }
+            return Ok(Some(buffer[..len].to_vec()));
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use bevy::{app::PluginGroupBuilder, prelude::*};
+
+use crate::resources::game_config::{GameConfig, Subsystems};
+
+use super::{
+    delayed_state_switch_plugin::DelayedStateSwitchPlugin,
+    game::{
+        beam_plugin::BeamPlugin, boss_plugin::BossPlugin,
+        bullet_emitter_plugin::BulletEmitterPlugin, daily_challenge_plugin::DailyChallengePlugin,
+        damage_feedback_plugin::DamageFeedbackPlugin, director_plugin::DirectorPlugin,
+        enemy_ai_plugin::EnemyAiPlugin, enemy_behavior_plugin::EnemyBehaviorPlugin,
+        enemy_plugin::EnemyPlugin, explosion_plugin::ExplosionPlugin,
+        formation_plugin::FormationPlugin, game_mode_plugin::GameModePlugin,
+        game_plugin::GamePlugin, homing_plugin::HomingPlugin, hud_plugin::HudPlugin,
+        movement_plugin::MovementPlugin, pause_menu_plugin::PauseMenuPlugin,
+        pickup_plugin::PickupPlugin, player_plugin::PlayerPlugin, replay_plugin::ReplayPlugin,
+        rollback_plugin::RollbackPlugin, spawn_plugin::SpawnPlugin, weapon_plugin::WeaponPlugin,
+    },
+    game_over::game_over_plugin::GameOverPlugin,
+    high_scores::high_scores_plugin::HighScoresPlugin,
+    leaderboard_plugin::LeaderboardPlugin,
+    lobby::lobby_plugin::LobbyPlugin,
+    net_plugin::NetPlugin,
+    resources_plugin::ResourcePlugin,
+    sprite_animation_plugin::SpriteAnimationPlugin,
+    start_menu::start_menu_plugin::StartMenuPlugin,
+    ui_interaction_plugin::UiInteractionPlugin,
+};
+
+/// Every plugin of the game, without the engine ones. Subsystems the config
+/// leaves out are disabled, single plugins can be swapped with `set` or
+/// `disable` on the built group.
+#[derive(Default)]
+pub struct BevyGamePlugins {
+    pub config: GameConfig,
+}
+
+impl BevyGamePlugins {
+    pub fn new(config: GameConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl PluginGroup for BevyGamePlugins {
+    fn build(self) -> PluginGroupBuilder {
+        let subsystems = self.config.subsystems;
+        let mut group = PluginGroupBuilder::start::<Self>()
+            .add(DelayedStateSwitchPlugin)
+            .add(SpriteAnimationPlugin)
+            .add(ResourcePlugin)
+            .add(UiInteractionPlugin)
+            .add(GamePlugin {
+                config: self.config,
+            })
+            .add(RollbackPlugin)
+            .add(MovementPlugin)
+            .add(ExplosionPlugin)
+            .add(PlayerPlugin)
+            .add(WeaponPlugin)
+            .add(HomingPlugin)
+            .add(BeamPlugin)
+            .add(DamageFeedbackPlugin)
+            .add(EnemyPlugin)
+            .add(DirectorPlugin)
+            .add(EnemyBehaviorPlugin)
+            .add(EnemyAiPlugin)
+            .add(BulletEmitterPlugin)
+            .add(SpawnPlugin)
+            .add(FormationPlugin)
+            .add(BossPlugin)
+            .add(GameModePlugin)
+            .add(DailyChallengePlugin)
+            .add(PickupPlugin)
+            .add(HudPlugin)
+            .add(PauseMenuPlugin)
+            .add(ReplayPlugin)
+            .add(NetPlugin)
+            .add(StartMenuPlugin)
+            .add(GameOverPlugin)
+            .add(HighScoresPlugin)
+            .add(LeaderboardPlugin)
+            .add(LobbyPlugin);
+        if !subsystems.contains(Subsystems::HUD) {
+            group = group.disable::<HudPlugin>();
+        }
+        if !subsystems.contains(Subsystems::PAUSE_MENU) {
+            group = group.disable::<PauseMenuPlugin>();
+        }
+        if !subsystems.contains(Subsystems::MENUS) {
+            group = group
+                .disable::<StartMenuPlugin>()
+                .disable::<GameOverPlugin>()
+                .disable::<HighScoresPlugin>()
+                .disable::<LeaderboardPlugin>()
+                .disable::<LobbyPlugin>();
+        }
+        group
+    }
+}
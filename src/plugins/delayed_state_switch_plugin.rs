@@ -4,12 +4,14 @@ use bevy::{app::AppExit, prelude::*};
 
 use crate::AppState;
 
-use super::ui_interaction_plugin::UiButton;
+use super::{game::rollback_plugin::RollbackApp, ui_interaction_plugin::UiButton};
 
 #[derive(Component, Clone, Copy, PartialEq)]
 pub enum StateActionButton {
     StartGame,
     MainMenu,
+    HighScores,
+    Lobby,
     Exit,
 }
 
@@ -19,7 +21,7 @@ pub struct DelayedOnclick {
     pub timer: Timer,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone)]
 pub struct StateSetCommand {
     pub target: AppState,
     pub delay: Timer,
@@ -34,7 +36,8 @@ pub struct DelayedStateSwitchPlugin;
 
 impl Plugin for DelayedStateSwitchPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(button_click_system)
+        app.rollback_component::<StateSetCommand>()
+            .add_system(button_click_system)
             .add_system(state_management_system);
     }
 }
@@ -67,6 +70,7 @@ fn state_management_system(
 fn button_click_system(
     mut commands: Commands,
     mut exit: EventWriter<AppExit>,
+    app_state: Res<State<AppState>>,
     mut query: Query<(&Interaction, &StateActionButton, &mut UiButton), Changed<Interaction>>,
 ) {
     for (interaction, button, mut menu_button) in query.iter_mut() {
@@ -83,14 +87,29 @@ fn button_click_system(
                             });
                         }
                         StateActionButton::MainMenu => {
-                            commands.spawn_empty().insert(StatePopCommand {
-                                delay: delay.clone(),
-                            });
+                            // leave the pause overlay first when there is one
+                            if !app_state.inactives().is_empty() {
+                                commands.spawn_empty().insert(StatePopCommand {
+                                    delay: delay.clone(),
+                                });
+                            }
                             commands.spawn_empty().insert(StateSetCommand {
                                 target: AppState::MainMenu,
                                 delay,
                             });
                         }
+                        StateActionButton::HighScores => {
+                            commands.spawn_empty().insert(StateSetCommand {
+                                target: AppState::HighScores,
+                                delay,
+                            });
+                        }
+                        StateActionButton::Lobby => {
+                            commands.spawn_empty().insert(StateSetCommand {
+                                target: AppState::Lobby,
+                                delay,
+                            });
+                        }
                         StateActionButton::Exit => exit.send(AppExit),
                     };
                 }
@@ -0,0 +1,192 @@
+use bevy::{
+    ecs::entity::{EntityMap, MapEntities, MapEntitiesError},
+    prelude::*,
+    sprite::Anchor,
+};
+
+use crate::{
+    components::{health::Health, sizeable::Sizeable, velocity::Velocity},
+    resources::{game_tuning::GameTuning, textures::Textures},
+    AppState,
+};
+
+use super::enemy_plugin::{ray_cast_entity, Enemy, LastHitBy};
+use super::game_plugin::{SimulationSet, SimulationStage};
+use super::player_plugin::PlayerId;
+use super::rollback_plugin::RollbackApp;
+use super::weapon_plugin::{Weapon, WeaponTrigger};
+
+const BEAM_IMPACT_SIZE: f32 = 48.;
+
+#[derive(Clone, Copy)]
+pub struct BeamStats {
+    pub width: f32,
+    pub max_length: f32,
+}
+
+#[derive(Component, Clone)]
+pub struct Beam {
+    pub owner: Entity,
+}
+
+impl MapEntities for Beam {
+    fn map_entities(&mut self, entities: &EntityMap) -> Result<(), MapEntitiesError> {
+        self.owner = entities.get(self.owner)?;
+        Ok(())
+    }
+}
+
+#[derive(Component, Clone)]
+struct BeamImpact;
+
+pub struct BeamPlugin;
+
+impl Plugin for BeamPlugin {
+    fn build(&self, app: &mut App) {
+        app.rollback_mapped_component::<Beam>()
+            .rollback_component::<BeamImpact>()
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationSet::Beams
+                    .system_set()
+                    .with_system(beam_spawn_system)
+                    .with_system(beam_update_system.after(beam_spawn_system))
+                    .with_system(beam_impact_animation_system.after(beam_update_system)),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(cleanup_system));
+    }
+}
+
+fn cleanup_system(mut commands: Commands, query: Query<Entity, With<Beam>>) {
+    for e in query.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
+
+fn is_firing_beam(weapon: &Weapon, trigger: &WeaponTrigger) -> bool {
+    trigger.held && weapon.stats().beam.is_some()
+}
+
+fn beam_spawn_system(
+    mut commands: Commands,
+    textures: Res<Textures>,
+    shooter_query: Query<(Entity, &Weapon, &WeaponTrigger)>,
+    beam_query: Query<(Entity, &Beam)>,
+) {
+    for (beam_entity, beam) in beam_query.iter() {
+        let firing = shooter_query
+            .get(beam.owner)
+            .is_ok_and(|(_, weapon, trigger)| is_firing_beam(weapon, trigger));
+        if !firing {
+            commands.entity(beam_entity).despawn_recursive();
+        }
+    }
+
+    for (shooter, weapon, trigger) in shooter_query.iter() {
+        if !is_firing_beam(weapon, trigger) || beam_query.iter().any(|(_, b)| b.owner == shooter) {
+            continue;
+        }
+        let impact_scale = BEAM_IMPACT_SIZE / textures.boom.size_px.x;
+        commands
+            .spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: weapon.stats().color,
+                    anchor: Anchor::BottomCenter,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .insert(Beam { owner: shooter })
+            .with_children(|parent| {
+                parent
+                    .spawn(SpriteSheetBundle {
+                        texture_atlas: textures.boom.texture_atlas.clone(),
+                        transform: Transform::from_scale(Vec3::new(impact_scale, impact_scale, 1.)),
+                        ..Default::default()
+                    })
+                    .insert(BeamImpact);
+            });
+    }
+}
+
+type BeamShooterQuery<'a> = (&'a Weapon, &'a Transform, Option<&'a PlayerId>);
+type BeamTargetQuery<'a> = (
+    Entity,
+    &'a Transform,
+    &'a Sizeable,
+    &'a mut Health,
+    Option<&'a mut LastHitBy>,
+);
+type BeamTargetFilter = (With<Enemy>, Without<Beam>, Without<BeamImpact>);
+
+fn beam_update_system(
+    tuning: Res<GameTuning>,
+    shooter_query: Query<BeamShooterQuery, (Without<Beam>, Without<BeamImpact>)>,
+    mut beam_query: Query<(&Beam, &mut Transform, &mut Sprite, &Children), Without<BeamImpact>>,
+    mut impact_query: Query<(&mut Transform, &mut Visibility), With<BeamImpact>>,
+    mut enemy_query: Query<BeamTargetQuery, BeamTargetFilter>,
+) {
+    for (beam, mut beam_trans, mut sprite, children) in beam_query.iter_mut() {
+        let (weapon, shooter_trans, player) = match shooter_query.get(beam.owner) {
+            Ok(shooter) => shooter,
+            Err(_) => continue,
+        };
+        let stats = weapon.stats();
+        let beam_stats = match stats.beam {
+            Some(beam_stats) => beam_stats,
+            None => continue,
+        };
+
+        let muzzle = *Velocity((stats.muzzles[0] * shooter_trans.scale.truncate()).extend(0.))
+            .rotate(shooter_trans.rotation);
+        let origin = shooter_trans.translation + muzzle;
+        let direction = Velocity(Vec3::Y).rotate(shooter_trans.rotation).truncate();
+
+        let hit = enemy_query
+            .iter()
+            .filter_map(|(enemy, enemy_trans, enemy_size, ..)| {
+                ray_cast_entity(origin.truncate(), direction, enemy_trans, enemy_size)
+                    .map(|distance| (enemy, distance))
+            })
+            .filter(|(_, distance)| *distance <= beam_stats.max_length)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b));
+
+        let length = match hit {
+            Some((enemy, distance)) => {
+                if let Ok((_, _, _, mut health, last_hit)) = enemy_query.get_mut(enemy) {
+                    health.current -= stats.damage * tuning.time_step;
+                    if let (Some(mut last_hit), Some(player)) = (last_hit, player) {
+                        last_hit.0 = Some(*player);
+                    }
+                }
+                distance
+            }
+            None => beam_stats.max_length,
+        };
+
+        *beam_trans = Transform {
+            translation: origin.truncate().extend(shooter_trans.translation.z - 1.),
+            rotation: shooter_trans.rotation,
+            ..Default::default()
+        };
+        sprite.custom_size = Some(Vec2::new(beam_stats.width, length));
+
+        for child in children.iter() {
+            if let Ok((mut impact_trans, mut visibility)) = impact_query.get_mut(*child) {
+                impact_trans.translation = Vec3::new(0., length, 1.);
+                visibility.is_visible = hit.is_some();
+            }
+        }
+    }
+}
+
+fn beam_impact_animation_system(
+    textures: Res<Textures>,
+    mut query: Query<&mut TextureAtlasSprite, With<BeamImpact>>,
+) {
+    let frames = (textures.boom.grid_size.x * textures.boom.grid_size.y) as usize;
+    for mut sprite in query.iter_mut() {
+        // loop through the first half of the explosion to keep the sparks bright
+        sprite.index = (sprite.index + 1) % (frames / 2).max(1);
+    }
+}
@@ -0,0 +1,393 @@
+use bevy::{
+    ecs::entity::{EntityMap, MapEntities, MapEntitiesError},
+    prelude::*,
+};
+use rand::Rng;
+
+use crate::{
+    components::{health::Health, sizeable::Sizeable},
+    resources::{
+        enemy_archetypes::EnemyArchetypes,
+        game_rng::{GameRng, RngStream},
+        game_tuning::GameTuning,
+        run_settings::{GameMode, RunSettings},
+        score::Score,
+        textures::Textures,
+    },
+    AppState, ViewportSize,
+};
+
+use super::bullet_emitter_plugin::BulletEmitter;
+use super::enemy_plugin::{spawn_enemy, LastEnemyRespawn};
+use super::explosion_plugin::ExplosionInvoke;
+use super::game_plugin::{SimulationSet, SimulationStage};
+use super::movement_plugin::GameClock;
+use super::rollback_plugin::RollbackApp;
+
+const BOSS_SCORE_INTERVAL: u32 = 5000;
+const BOSS_BODY_SCALE: f32 = 2.2;
+const BOSS_ENTRY_SPEED: f32 = 60.;
+const BOSS_HOVER_MARGIN: f32 = 130.;
+const BOSS_DEATH_EXPLOSIONS: u32 = 12;
+const BOSS_DEATH_INTERVAL_S: f32 = 0.15;
+const BOSS_DEATH_SCORE: u32 = 5000;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BossPartKind {
+    Turret,
+    WeakPoint,
+    Armor,
+}
+
+impl BossPartKind {
+    fn archetype(&self) -> &'static str {
+        match self {
+            BossPartKind::Turret => "boss_turret",
+            BossPartKind::WeakPoint => "boss_weak_point",
+            BossPartKind::Armor => "boss_armor",
+        }
+    }
+}
+
+/// Part layout relative to the boss body center, in pixels.
+const BOSS_LAYOUT: [(BossPartKind, (f32, f32)); 9] = [
+    (BossPartKind::WeakPoint, (-60., 10.)),
+    (BossPartKind::WeakPoint, (60., 10.)),
+    (BossPartKind::Armor, (-60., -45.)),
+    (BossPartKind::Armor, (0., -55.)),
+    (BossPartKind::Armor, (60., -45.)),
+    (BossPartKind::Turret, (-150., -10.)),
+    (BossPartKind::Turret, (150., -10.)),
+    (BossPartKind::Turret, (-100., 60.)),
+    (BossPartKind::Turret, (100., 60.)),
+];
+
+/// Attack pattern of a boss phase, the phase starts once the weak point
+/// health fraction drops to `threshold`.
+pub struct BossPhase {
+    pub threshold: f32,
+    pub sway_speed: f32,
+    pub sway_amplitude: f32,
+    pub turret_pattern: &'static str,
+    pub body_pattern: &'static str,
+    pub drop_armor: bool,
+}
+
+const BOSS_PHASES: [BossPhase; 3] = [
+    BossPhase {
+        threshold: 1.,
+        sway_speed: 0.6,
+        sway_amplitude: 150.,
+        turret_pattern: "boss_turret_aimed",
+        body_pattern: "boss_fan",
+        drop_armor: false,
+    },
+    BossPhase {
+        threshold: 0.66,
+        sway_speed: 1.,
+        sway_amplitude: 250.,
+        turret_pattern: "boss_turret_fan",
+        body_pattern: "boss_flak",
+        drop_armor: false,
+    },
+    BossPhase {
+        threshold: 0.33,
+        sway_speed: 1.6,
+        sway_amplitude: 320.,
+        turret_pattern: "boss_turret_wave",
+        body_pattern: "boss_spiral",
+        drop_armor: true,
+    },
+];
+
+#[derive(Component, Clone)]
+pub struct Boss {
+    pub phase: usize,
+    pub health: f32,
+    pub max_health: f32,
+    sway_time: f32,
+}
+
+impl Boss {
+    pub fn health_fraction(&self) -> f32 {
+        (self.health / self.max_health).clamp(0., 1.)
+    }
+}
+
+#[derive(Component, Clone)]
+pub struct BossPart {
+    pub boss: Entity,
+    pub kind: BossPartKind,
+    pub offset: Vec2,
+}
+
+impl MapEntities for BossPart {
+    fn map_entities(&mut self, entities: &EntityMap) -> Result<(), MapEntitiesError> {
+        self.boss = entities.get(self.boss)?;
+        Ok(())
+    }
+}
+
+#[derive(Component, Clone)]
+struct BossDeath {
+    timer: Timer,
+    explosions_left: u32,
+}
+
+#[derive(Resource, Clone)]
+struct BossSchedule {
+    next_score: u32,
+}
+
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.rollback_component::<Boss>()
+            .rollback_mapped_component::<BossPart>()
+            .rollback_component::<BossDeath>()
+            .rollback_resource::<BossSchedule>()
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(setup_system))
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationSet::Boss
+                    .system_set()
+                    .with_system(boss_spawn_system)
+                    .with_system(boss_phase_system.after(boss_spawn_system))
+                    .with_system(boss_movement_system.after(boss_phase_system)),
+            )
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationSet::BossParts
+                    .system_set()
+                    .with_system(boss_part_follow_system),
+            )
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationSet::BossDeath
+                    .system_set()
+                    .with_system(boss_death_system),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(cleanup_system));
+    }
+}
+
+fn setup_system(mut commands: Commands) {
+    commands.insert_resource(BossSchedule {
+        next_score: BOSS_SCORE_INTERVAL,
+    });
+}
+
+fn cleanup_system(mut commands: Commands, query: Query<Entity, With<Boss>>) {
+    for e in query.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
+
+fn boss_spawn_system(
+    mut commands: Commands,
+    mut schedule: ResMut<BossSchedule>,
+    (score, settings): (Res<Score>, Res<RunSettings>),
+    textures: Res<Textures>,
+    archetypes: Res<EnemyArchetypes>,
+    viewport_size: Res<ViewportSize>,
+    boss_query: Query<(), With<Boss>>,
+) {
+    // wave survival sticks to its scripted waves
+    if **score < schedule.next_score
+        || !boss_query.is_empty()
+        || settings.mode == GameMode::WaveSurvival
+    {
+        return;
+    }
+    schedule.next_score = **score + BOSS_SCORE_INTERVAL;
+
+    let body_scale = textures.sprite_scale * BOSS_BODY_SCALE;
+    let translation = Vec3::new(0., viewport_size.h / 2. + textures.enemy.size_px.y, 8.);
+    let boss = commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgb(0.45, 0.15, 0.2),
+                ..Default::default()
+            },
+            texture: textures.enemy.image.clone(),
+            transform: Transform {
+                translation,
+                scale: Vec3::new(body_scale, body_scale, 1.),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .id();
+
+    let mut max_health = 0.;
+    for (kind, (x, y)) in BOSS_LAYOUT {
+        let archetype = match archetypes.get(kind.archetype()) {
+            Some(archetype) => archetype,
+            None => continue,
+        };
+        if kind == BossPartKind::WeakPoint {
+            max_health += archetype.health;
+        }
+        let offset = Vec2::new(x, y);
+        let part = spawn_enemy(
+            &mut commands,
+            &textures,
+            archetype,
+            translation + offset.extend(2.),
+        );
+        commands
+            .entity(part)
+            .insert(BossPart { boss, kind, offset });
+    }
+    commands
+        .entity(boss)
+        .insert(Sizeable(textures.enemy.size_px))
+        .insert(BulletEmitter::new(BOSS_PHASES[0].body_pattern))
+        .insert(Boss {
+            phase: 0,
+            health: max_health,
+            max_health,
+            sway_time: 0.,
+        });
+}
+
+fn boss_movement_system(
+    tuning: Res<GameTuning>,
+    clock: Res<GameClock>,
+    viewport_size: Res<ViewportSize>,
+    mut last_spawn: ResMut<LastEnemyRespawn>,
+    mut query: Query<(&mut Boss, &mut Transform), Without<BossDeath>>,
+) {
+    for (mut boss, mut trans) in query.iter_mut() {
+        // hold regular enemy respawns while the boss fight is on
+        **last_spawn = clock.elapsed_seconds();
+
+        let hover_y = viewport_size.h / 2. - BOSS_HOVER_MARGIN;
+        if trans.translation.y > hover_y {
+            trans.translation.y =
+                (trans.translation.y - BOSS_ENTRY_SPEED * tuning.time_step).max(hover_y);
+            continue;
+        }
+        let phase = &BOSS_PHASES[boss.phase];
+        boss.sway_time += tuning.time_step * phase.sway_speed;
+        trans.translation.x = boss.sway_time.sin() * phase.sway_amplitude;
+    }
+}
+
+fn boss_part_follow_system(
+    boss_query: Query<&Transform, (With<Boss>, Without<BossPart>)>,
+    mut part_query: Query<(&BossPart, &mut Transform)>,
+) {
+    for (part, mut trans) in part_query.iter_mut() {
+        if let Ok(boss_trans) = boss_query.get(part.boss) {
+            trans.translation.x = boss_trans.translation.x + part.offset.x;
+            trans.translation.y = boss_trans.translation.y + part.offset.y;
+        }
+    }
+}
+
+type BossPhaseQuery<'a> = (Entity, &'a mut Boss, &'a Transform, &'a mut BulletEmitter);
+
+fn boss_phase_system(
+    mut commands: Commands,
+    mut boss_query: Query<BossPhaseQuery, (Without<BossDeath>, Without<BossPart>)>,
+    mut part_query: Query<(
+        Entity,
+        &BossPart,
+        &Health,
+        &Transform,
+        Option<&mut BulletEmitter>,
+    )>,
+) {
+    for (boss_entity, mut boss, boss_trans, mut boss_emitter) in boss_query.iter_mut() {
+        boss.health = part_query
+            .iter()
+            .filter(|(_, part, ..)| {
+                part.boss == boss_entity && part.kind == BossPartKind::WeakPoint
+            })
+            .map(|(_, _, health, ..)| health.current.max(0.))
+            .sum();
+
+        if boss.health <= 0. {
+            for (part_entity, part, _, part_trans, _) in part_query.iter() {
+                if part.boss != boss_entity {
+                    continue;
+                }
+                commands.entity(part_entity).despawn_recursive();
+                commands.spawn_empty().insert(ExplosionInvoke {
+                    translation: part_trans.translation,
+                });
+            }
+            commands
+                .entity(boss_entity)
+                .remove::<BulletEmitter>()
+                .insert(BossDeath {
+                    timer: Timer::from_seconds(BOSS_DEATH_INTERVAL_S, TimerMode::Repeating),
+                    explosions_left: BOSS_DEATH_EXPLOSIONS,
+                });
+            continue;
+        }
+
+        let phase = BOSS_PHASES
+            .iter()
+            .rposition(|phase| boss.health_fraction() <= phase.threshold)
+            .unwrap_or(0);
+        if phase <= boss.phase {
+            continue;
+        }
+        boss.phase = phase;
+        commands.spawn_empty().insert(ExplosionInvoke {
+            translation: boss_trans.translation,
+        });
+
+        let phase = &BOSS_PHASES[phase];
+        boss_emitter.set_pattern(phase.body_pattern);
+        for (part_entity, part, _, part_trans, emitter) in part_query.iter_mut() {
+            if part.boss != boss_entity {
+                continue;
+            }
+            if let Some(mut emitter) = emitter {
+                emitter.set_pattern(phase.turret_pattern);
+            }
+            if phase.drop_armor && part.kind == BossPartKind::Armor {
+                commands.entity(part_entity).despawn_recursive();
+                commands.spawn_empty().insert(ExplosionInvoke {
+                    translation: part_trans.translation,
+                });
+            }
+        }
+    }
+}
+
+fn boss_death_system(
+    mut commands: Commands,
+    (clock, mut rng): (Res<GameClock>, ResMut<GameRng>),
+    mut score: ResMut<Score>,
+    mut query: Query<(Entity, &mut BossDeath, &Transform, &Sizeable)>,
+) {
+    let rand = rng.stream(RngStream::Effects);
+    for (entity, mut death, trans, size) in query.iter_mut() {
+        death.timer.tick(clock.delta());
+        if !death.timer.just_finished() {
+            continue;
+        }
+        let half = **size * trans.scale.truncate() / 2.;
+        let offset = Vec2::new(
+            rand.gen_range(-half.x..half.x),
+            rand.gen_range(-half.y..half.y),
+        );
+        commands.spawn_empty().insert(ExplosionInvoke {
+            translation: trans.translation + offset.extend(5.),
+        });
+
+        death.explosions_left -= 1;
+        if death.explosions_left == 0 {
+            commands.entity(entity).despawn_recursive();
+            commands.spawn_empty().insert(ExplosionInvoke {
+                translation: trans.translation + Vec3::Z * 5.,
+            });
+            **score += BOSS_DEATH_SCORE;
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    components::{
+        damage::Damage,
+        movable::{Movable, MovementOptions, MovementViewportBehavior},
+        sizeable::Sizeable,
+        velocity::Velocity,
+    },
+    resources::{
+        bullet_patterns::{BulletPattern, BulletPatterns, EmitterPattern},
+        game_rng::{GameRng, RngStream},
+        game_tuning::GameTuning,
+        textures::Textures,
+    },
+    AppState,
+};
+
+use super::enemy_plugin::collide_entities;
+use super::game_plugin::{SimulationSet, SimulationStage};
+use super::player_plugin::{nearest_player, HitCause, HitPlayer, Player};
+use super::rollback_plugin::RollbackApp;
+
+/// Angle between the shots of a single wave burst.
+const WAVE_SHOT_SPACING: f32 = 0.15;
+
+/// Fires projectiles following a named pattern from `BulletPatterns`.
+#[derive(Component, Clone)]
+pub struct BulletEmitter {
+    pub pattern: String,
+    /// `None` until the first update, which staggers the first burst.
+    cooldown: Option<f32>,
+    angle: f32,
+    time: f32,
+    bursts_left: Option<u32>,
+}
+
+impl BulletEmitter {
+    pub fn new(pattern: &str) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            cooldown: None,
+            angle: 0.,
+            time: 0.,
+            bursts_left: None,
+        }
+    }
+
+    /// Emitter firing `bursts` times after `delay_s`, its entity is despawned afterwards.
+    fn limited(pattern: &str, delay_s: f32, bursts: u32) -> Self {
+        Self {
+            cooldown: Some(delay_s),
+            bursts_left: Some(bursts),
+            ..Self::new(pattern)
+        }
+    }
+
+    pub fn set_pattern(&mut self, pattern: &str) {
+        if self.pattern != pattern {
+            *self = Self::new(pattern);
+        }
+    }
+}
+
+#[derive(Component, Clone)]
+pub struct EnemyProjectile;
+
+pub struct BulletEmitterPlugin;
+
+impl Plugin for BulletEmitterPlugin {
+    fn build(&self, app: &mut App) {
+        app.rollback_component::<BulletEmitter>()
+            .rollback_component::<EnemyProjectile>()
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationSet::Emitters
+                    .system_set()
+                    .with_system(bullet_emitter_system),
+            )
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationSet::EnemyProjectiles
+                    .system_set()
+                    .with_system(enemy_projectile_hit_system),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(cleanup_system));
+    }
+}
+
+fn cleanup_system(mut commands: Commands, query: Query<Entity, With<EnemyProjectile>>) {
+    for e in query.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
+
+/// `count` angles evenly spread over `arc` around `center`.
+fn spread(center: f32, arc: f32, count: u32) -> Vec<f32> {
+    match count {
+        0 => Vec::new(),
+        1 => vec![center],
+        _ => (0..count)
+            .map(|i| center - arc / 2. + arc * i as f32 / (count - 1) as f32)
+            .collect(),
+    }
+}
+
+fn radial(base: f32, count: u32) -> Vec<f32> {
+    (0..count)
+        .map(|i| base + TAU * i as f32 / count as f32)
+        .collect()
+}
+
+fn shot_angles(emitter: &BulletEmitter, pattern: &EmitterPattern, aim: Option<Vec2>) -> Vec<f32> {
+    match *pattern {
+        EmitterPattern::Radial { count } => radial(emitter.angle, count),
+        EmitterPattern::Spiral { arms, .. } => radial(emitter.angle, arms),
+        EmitterPattern::AimedFan { count, arc } => {
+            let center = aim.map_or(-FRAC_PI_2, |aim| aim.y.atan2(aim.x));
+            spread(center, arc, count)
+        }
+        EmitterPattern::Wave {
+            count,
+            arc,
+            frequency,
+        } => {
+            let center = -FRAC_PI_2 + (emitter.time * frequency * TAU).sin() * arc / 2.;
+            spread(center, WAVE_SHOT_SPACING * count as f32, count)
+        }
+    }
+}
+
+pub fn spawn_enemy_projectile(
+    commands: &mut Commands,
+    textures: &Textures,
+    pattern: &BulletPattern,
+    origin: Vec3,
+    velocity: Vec2,
+) -> Entity {
+    let scale = textures.sprite_scale * pattern.scale;
+    let mut projectile = commands.spawn(SpriteBundle {
+        texture: textures.enemy_fire.image.clone(),
+        transform: Transform {
+            translation: origin,
+            scale: Vec3::new(scale, scale, 1.),
+            rotation: Quat::from_rotation_z((-velocity.x).atan2(velocity.y)),
+        },
+        ..Default::default()
+    });
+    projectile
+        .insert(EnemyProjectile)
+        .insert(Velocity::from(velocity))
+        .insert(Damage(pattern.damage))
+        .insert(Sizeable(textures.enemy_fire.size_px))
+        .insert(Movable(MovementOptions {
+            viewport_behavior: MovementViewportBehavior::DespawnOnLeave,
+        }));
+    if let Some(sub) = &pattern.sub_emitter {
+        projectile.insert(BulletEmitter::limited(
+            &sub.pattern,
+            sub.delay_s,
+            sub.bursts,
+        ));
+    }
+    projectile.id()
+}
+
+fn bullet_emitter_system(
+    mut commands: Commands,
+    tuning: Res<GameTuning>,
+    (textures, patterns, mut rng): (Res<Textures>, Res<BulletPatterns>, ResMut<GameRng>),
+    player_query: Query<&Transform, (With<Player>, Without<BulletEmitter>)>,
+    mut emitter_query: Query<(Entity, &mut BulletEmitter, &Transform, &Sizeable)>,
+) {
+    let rand = rng.stream(RngStream::Ai);
+    for (entity, mut emitter, trans, size) in emitter_query.iter_mut() {
+        let pattern = match patterns.get(&emitter.pattern) {
+            Some(pattern) => pattern,
+            None => continue,
+        };
+        let interval_s = pattern.interval_s / tuning.enemy_fire_rate_scale;
+        emitter.time += tuning.time_step;
+        if let EmitterPattern::Spiral { angular_speed, .. } = pattern.pattern {
+            emitter.angle += angular_speed * tuning.time_step;
+        }
+        // stagger the first burst so freshly spawned enemies don't fire in sync
+        let cooldown = emitter
+            .cooldown
+            .unwrap_or_else(|| rand.gen_range(0.5..1.) * interval_s)
+            - tuning.time_step;
+        emitter.cooldown = Some(cooldown);
+        if cooldown > 0. {
+            continue;
+        }
+        emitter.cooldown = Some(interval_s);
+
+        let aim = nearest_player(&player_query, trans.translation)
+            .map(|player| (player.translation - trans.translation).truncate());
+        for angle in shot_angles(&emitter, &pattern.pattern, aim) {
+            let direction = Vec2::from_angle(angle);
+            let muzzle = direction * size.y * trans.scale.y / 2.;
+            spawn_enemy_projectile(
+                &mut commands,
+                &textures,
+                pattern,
+                trans.translation + muzzle.extend(0.),
+                direction * pattern.projectile_speed * tuning.enemy_projectile_speed_scale,
+            );
+        }
+
+        if let Some(bursts_left) = emitter.bursts_left.as_mut() {
+            *bursts_left = bursts_left.saturating_sub(1);
+            if *bursts_left == 0 {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
+
+fn enemy_projectile_hit_system(
+    mut commands: Commands,
+    player_query: Query<(Entity, &Sizeable, &Transform), With<Player>>,
+    proj_query: Query<(Entity, &Sizeable, &Transform), With<EnemyProjectile>>,
+) {
+    for (proj, proj_size, proj_trans) in proj_query.iter() {
+        // a projectile is spent on the first player it hits
+        let hit = player_query.iter().find(|(_, player_size, player_trans)| {
+            collide_entities(proj_trans, proj_size, player_trans, player_size)
+        });
+        if let Some((player, ..)) = hit {
+            commands.entity(proj).despawn_recursive();
+            commands.spawn_empty().insert(HitPlayer {
+                player,
+                cause: HitCause::Projectile,
+            });
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+
+use crate::{
+    plugins::high_scores::high_scores_plugin::HighScoresTable,
+    resources::{
+        daily_challenge::{DailyChallenge, DailyResults},
+        run_settings::{GameMode, Modifiers, RunSettings},
+    },
+    AppState, Headless,
+};
+
+use super::replay_plugin::ReplayPlayback;
+
+/// Whether the daily challenge being played counts, only the first run of a day does.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DailyAttempt {
+    Scored,
+    Practice,
+}
+
+/// Today's challenge and its once a day attempt.
+pub struct DailyChallengePlugin;
+
+impl Plugin for DailyChallengePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(CoreStage::PreUpdate, today_system)
+            .add_system_set(SystemSet::on_enter(AppState::MainMenu).with_system(refresh_system))
+            .add_system_set(SystemSet::on_update(AppState::MainMenu).with_system(modifiers_system))
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(attempt_system));
+    }
+}
+
+/// Works out today's challenge once the daily mode or its high scores are picked.
+fn today_system(
+    mut commands: Commands,
+    (settings, table): (Res<RunSettings>, Option<Res<HighScoresTable>>),
+    daily: Option<Res<DailyChallenge>>,
+) {
+    let selected = settings.mode == GameMode::DailyChallenge
+        || table.is_some_and(|table| table.0 == GameMode::DailyChallenge);
+    if selected && daily.is_none() {
+        commands.insert_resource(DailyChallenge::today());
+    }
+}
+
+/// Forgets the challenge in the menu, the date may have changed since.
+fn refresh_system(mut commands: Commands) {
+    commands.remove_resource::<DailyChallenge>();
+}
+
+/// Daily challenges are played with the day's modifiers, other modes without any.
+fn modifiers_system(mut settings: ResMut<RunSettings>, daily: Option<Res<DailyChallenge>>) {
+    let modifiers = match (settings.mode, daily) {
+        (GameMode::DailyChallenge, Some(daily)) => daily.modifiers,
+        (GameMode::DailyChallenge, None) => return,
+        _ => Modifiers::empty(),
+    };
+    if settings.modifiers != modifiers {
+        settings.modifiers = modifiers;
+    }
+}
+
+fn attempt_system(
+    mut commands: Commands,
+    (settings, daily): (Res<RunSettings>, Option<Res<DailyChallenge>>),
+    mut results: ResMut<DailyResults>,
+    (playback, headless): (Option<Res<ReplayPlayback>>, Option<Res<Headless>>),
+) {
+    let daily = match daily {
+        Some(daily) if settings.mode == GameMode::DailyChallenge && playback.is_none() => daily,
+        _ => {
+            commands.remove_resource::<DailyAttempt>();
+            return;
+        }
+    };
+    // the attempt is taken right away so quitting a bad start doesn't give another
+    let attempt = match results.start_attempt(&daily) {
+        true => DailyAttempt::Scored,
+        false => DailyAttempt::Practice,
+    };
+    if attempt == DailyAttempt::Scored && headless.is_none() {
+        results.save();
+    }
+    commands.insert_resource(attempt);
+}
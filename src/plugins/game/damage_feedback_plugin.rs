@@ -0,0 +1,263 @@
+use bevy::{
+    ecs::entity::{EntityMap, MapEntities, MapEntitiesError},
+    prelude::*,
+    sprite::Anchor,
+};
+
+use crate::{
+    components::{health::Health, sizeable::Sizeable},
+    resources::{fonts::Fonts, game_tuning::GameTuning},
+    AppState,
+};
+
+use super::game_plugin::{SimulationSet, SimulationStage};
+use super::movement_plugin::GameClock;
+use super::rollback_plugin::RollbackApp;
+
+const HIT_FLASH_S: f32 = 0.1;
+const HIT_FLASH_COLOR: Color = Color::rgb(1., 0.3, 0.3);
+const HEALTH_BAR_SIZE: Vec2 = Vec2::new(60., 6.);
+const HEALTH_BAR_MARGIN: f32 = 8.;
+const DAMAGE_NUMBER_INTERVAL_S: f32 = 0.25;
+const DAMAGE_NUMBER_LIFETIME_S: f32 = 0.8;
+const DAMAGE_NUMBER_RISE_SPEED: f32 = 40.;
+const DAMAGE_NUMBER_FONT_SIZE: f32 = 20.;
+
+/// Tracks health changes of an entity, damage from continuous sources gets
+/// accumulated so a single floating number is shown per interval.
+#[derive(Component, Clone)]
+struct DamageFeedback {
+    last_health: f32,
+    pending: f32,
+    cooldown: f32,
+}
+
+#[derive(Component, Clone)]
+struct HitFlash {
+    timer: Timer,
+    base_color: Color,
+}
+
+/// Health bars and damage numbers are spawned by the simulation, so they're
+/// part of the run and a rollback neither repeats nor loses them.
+#[derive(Component, Clone)]
+struct HealthBar {
+    owner: Entity,
+}
+
+impl MapEntities for HealthBar {
+    fn map_entities(&mut self, entities: &EntityMap) -> Result<(), MapEntitiesError> {
+        self.owner = entities.get(self.owner)?;
+        Ok(())
+    }
+}
+
+#[derive(Component, Clone)]
+struct HealthBarFill;
+
+#[derive(Component, Clone, Deref, DerefMut)]
+struct DamageNumber(Timer);
+
+pub struct DamageFeedbackPlugin;
+
+impl Plugin for DamageFeedbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.rollback_component::<DamageFeedback>()
+            .rollback_component::<HitFlash>()
+            .rollback_mapped_component::<HealthBar>()
+            .rollback_attached_component::<HealthBarFill>()
+            .rollback_component::<DamageNumber>()
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationSet::Feedback
+                    .system_set()
+                    .with_system(damage_feedback_attach_system)
+                    .with_system(damage_feedback_system.after(damage_feedback_attach_system))
+                    .with_system(hit_flash_system.after(damage_feedback_system))
+                    .with_system(health_bar_system.after(hit_flash_system))
+                    .with_system(damage_number_system.after(health_bar_system)),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(cleanup_system));
+    }
+}
+
+fn cleanup_system(
+    mut commands: Commands,
+    bar_query: Query<Entity, With<HealthBar>>,
+    number_query: Query<Entity, With<DamageNumber>>,
+) {
+    bar_query.iter().chain(number_query.iter()).for_each(|e| {
+        commands.entity(e).despawn_recursive();
+    });
+}
+
+fn damage_feedback_attach_system(
+    mut commands: Commands,
+    query: Query<(Entity, &Health), Added<Health>>,
+) {
+    for (entity, health) in query.iter() {
+        commands.entity(entity).insert(DamageFeedback {
+            last_health: health.current,
+            pending: 0.,
+            cooldown: 0.,
+        });
+    }
+}
+
+type DamageFeedbackQuery<'a> = (
+    Entity,
+    &'a Health,
+    &'a mut DamageFeedback,
+    &'a Transform,
+    &'a Sizeable,
+    Option<&'a Sprite>,
+    Option<&'a HitFlash>,
+);
+
+fn damage_feedback_system(
+    mut commands: Commands,
+    tuning: Res<GameTuning>,
+    fonts: Res<Fonts>,
+    mut query: Query<DamageFeedbackQuery>,
+    bar_query: Query<&HealthBar>,
+) {
+    for (entity, health, mut feedback, trans, size, sprite, flash) in query.iter_mut() {
+        let taken = feedback.last_health - health.current.max(0.);
+        feedback.last_health = health.current.max(0.);
+        feedback.cooldown -= tuning.time_step;
+        feedback.pending += taken.max(0.);
+
+        let top = trans.translation + Vec3::new(0., size.y * trans.scale.y / 2., 0.);
+        if feedback.pending > 0. && (feedback.cooldown <= 0. || health.is_dead()) {
+            commands.spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        format!("{}", (feedback.pending * 10.).round() / 10.),
+                        TextStyle {
+                            font: fonts.bold.clone(),
+                            font_size: DAMAGE_NUMBER_FONT_SIZE,
+                            color: Color::YELLOW,
+                        },
+                    )
+                    .with_alignment(TextAlignment::CENTER),
+                    transform: Transform::from_translation(top + Vec3::Z * 20.),
+                    ..Default::default()
+                },
+                DamageNumber(Timer::from_seconds(
+                    DAMAGE_NUMBER_LIFETIME_S,
+                    TimerMode::Once,
+                )),
+            ));
+            feedback.pending = 0.;
+            feedback.cooldown = DAMAGE_NUMBER_INTERVAL_S;
+        }
+
+        if taken <= 0. || health.is_dead() {
+            continue;
+        }
+        if let Some(sprite) = sprite {
+            commands.entity(entity).insert(HitFlash {
+                timer: Timer::from_seconds(HIT_FLASH_S, TimerMode::Once),
+                base_color: flash.map_or(sprite.color, |f| f.base_color),
+            });
+        }
+        if !bar_query.iter().any(|bar| bar.owner == entity) {
+            commands
+                .spawn(SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgba(0., 0., 0., 0.6),
+                        custom_size: Some(HEALTH_BAR_SIZE),
+                        ..Default::default()
+                    },
+                    transform: Transform::from_translation(top),
+                    ..Default::default()
+                })
+                .insert(HealthBar { owner: entity })
+                .with_children(|parent| {
+                    parent
+                        .spawn(SpriteBundle {
+                            sprite: Sprite {
+                                color: Color::LIME_GREEN,
+                                custom_size: Some(HEALTH_BAR_SIZE),
+                                anchor: Anchor::CenterLeft,
+                                ..Default::default()
+                            },
+                            transform: Transform::from_xyz(-HEALTH_BAR_SIZE.x / 2., 0., 1.),
+                            ..Default::default()
+                        })
+                        .insert(HealthBarFill);
+                });
+        }
+    }
+}
+
+/// Ticks with the simulation, the flash comes and goes with a component of
+/// gameplay entities whose storage has to be the same on every peer.
+fn hit_flash_system(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    mut query: Query<(Entity, &mut HitFlash, &mut Sprite)>,
+) {
+    for (entity, mut flash, mut sprite) in query.iter_mut() {
+        flash.timer.tick(clock.delta());
+        if flash.timer.finished() {
+            sprite.color = flash.base_color;
+            commands.entity(entity).remove::<HitFlash>();
+        } else {
+            sprite.color = HIT_FLASH_COLOR;
+        }
+    }
+}
+
+fn health_bar_system(
+    mut commands: Commands,
+    owner_query: Query<(&Health, &Transform, &Sizeable), Without<HealthBar>>,
+    mut bar_query: Query<(Entity, &HealthBar, &mut Transform, &Children)>,
+    mut fill_query: Query<&mut Sprite, With<HealthBarFill>>,
+) {
+    for (bar_entity, bar, mut bar_trans, children) in bar_query.iter_mut() {
+        let (health, owner_trans, owner_size) = match owner_query.get(bar.owner) {
+            Ok(owner) => owner,
+            Err(_) => {
+                commands.entity(bar_entity).despawn_recursive();
+                continue;
+            }
+        };
+        bar_trans.translation = owner_trans.translation
+            + Vec3::new(
+                0.,
+                owner_size.y * owner_trans.scale.y / 2. + HEALTH_BAR_MARGIN,
+                1.,
+            );
+        for child in children.iter() {
+            if let Ok(mut sprite) = fill_query.get_mut(*child) {
+                sprite.custom_size = Some(Vec2::new(
+                    HEALTH_BAR_SIZE.x * health.fraction(),
+                    HEALTH_BAR_SIZE.y,
+                ));
+                sprite.color = match health.fraction() > 0.3 {
+                    true => Color::LIME_GREEN,
+                    false => Color::ORANGE_RED,
+                };
+            }
+        }
+    }
+}
+
+fn damage_number_system(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    mut query: Query<(Entity, &mut DamageNumber, &mut Transform, &mut Text)>,
+) {
+    for (entity, mut number, mut trans, mut text) in query.iter_mut() {
+        number.tick(clock.delta());
+        if number.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        trans.translation.y += DAMAGE_NUMBER_RISE_SPEED * clock.step_s;
+        for section in text.sections.iter_mut() {
+            section.style.color.set_a(number.percent_left());
+        }
+    }
+}
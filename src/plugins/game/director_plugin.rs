@@ -0,0 +1,89 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{
+    resources::{game_tuning::GameTuning, run_settings::RunSettings},
+    AppState,
+};
+
+use super::enemy_plugin::EnemyKilled;
+use super::game_plugin::{SimulationSet, SimulationStage};
+use super::movement_plugin::GameClock;
+use super::player_plugin::PlayerDamaged;
+use super::rollback_plugin::RollbackApp;
+
+/// Adaptive difficulty of the current run. The pressure stays at 1 unless the
+/// run is adaptive, then it follows the recent kills and hits of the player.
+#[derive(Resource, Clone)]
+pub struct Director {
+    pub pressure: f32,
+    kills: VecDeque<f32>,
+    hits: VecDeque<f32>,
+}
+
+impl Default for Director {
+    fn default() -> Self {
+        Self {
+            pressure: 1.,
+            kills: VecDeque::new(),
+            hits: VecDeque::new(),
+        }
+    }
+}
+
+pub struct DirectorPlugin;
+
+impl Plugin for DirectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.rollback_resource::<Director>()
+            .init_resource::<Director>()
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(setup_system))
+            .add_system_set_to_stage(
+                SimulationStage,
+                // events are read in the step they're sent, so no step leaves any unread
+                SimulationSet::Director
+                    .system_set()
+                    .with_system(director_system),
+            );
+    }
+}
+
+fn setup_system(mut commands: Commands) {
+    commands.insert_resource(Director::default());
+}
+
+fn director_system(
+    mut director: ResMut<Director>,
+    (clock, tuning, settings): (Res<GameClock>, Res<GameTuning>, Res<RunSettings>),
+    mut killed: EventReader<EnemyKilled>,
+    mut damaged: EventReader<PlayerDamaged>,
+) {
+    if !settings.adaptive {
+        killed.clear();
+        damaged.clear();
+        return;
+    }
+    let now = clock.elapsed_seconds();
+    let bounds = tuning.director;
+    director.kills.extend(killed.iter().map(|_| now));
+    director.hits.extend(damaged.iter().map(|_| now));
+    let window_start = now - bounds.window_s;
+    let director = &mut *director;
+    for events in [&mut director.kills, &mut director.hits] {
+        while events.front().is_some_and(|&time| time < window_start) {
+            events.pop_front();
+        }
+    }
+
+    // the kill pace only counts as much of the window as has been played
+    let played = (now / bounds.window_s).min(1.);
+    let kill_pace = match played > 0. {
+        true => director.kills.len() as f32 / (bounds.target_kills * played),
+        false => 1.,
+    };
+    let target = (1. + (kill_pace - 1.) * played - director.hits.len() as f32 * bounds.hit_penalty)
+        .clamp(bounds.min_pressure, bounds.max_pressure);
+    let max_change = bounds.adjust_per_s * tuning.time_step;
+    director.pressure += (target - director.pressure).clamp(-max_change, max_change);
+}
@@ -0,0 +1,427 @@
+use bevy::prelude::*;
+
+use crate::{
+    components::{health::Health, sizeable::Sizeable, velocity::Velocity},
+    resources::{
+        enemy_archetypes::{AiProfile, EnemyBehavior},
+        fonts::Fonts,
+        game_tuning::GameTuning,
+    },
+    AppState, ViewportSize,
+};
+
+use super::bullet_emitter_plugin::BulletEmitter;
+use super::enemy_plugin::Enemy;
+use super::game_plugin::{SimulationSet, SimulationStage};
+use super::player_plugin::{nearest_player, Player};
+use super::rollback_plugin::RollbackApp;
+use super::spawn_plugin::SpawnEntry;
+
+const AI_DEBUG_KEY: KeyCode = KeyCode::F3;
+const AI_DEBUG_FONT_SIZE: f32 = 16.;
+/// Sight is lost only past this multiple of the range to avoid flickering between states.
+const AI_RANGE_HYSTERESIS: f32 = 1.3;
+const AI_ATTACK_MIN_S: f32 = 1.;
+const AI_PATROL_MARGIN: f32 = 60.;
+const AI_DYING_S: f32 = 0.4;
+const AI_DYING_SPIN: f32 = 12.;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AiState {
+    Idle,
+    Patrol,
+    Approach,
+    Attack,
+    Flee,
+    Dying,
+}
+
+#[derive(Component, Clone)]
+pub struct AiStateMachine {
+    profile: AiProfile,
+    speed: f32,
+    state: AiState,
+    time_in_state: f32,
+    has_fled: bool,
+    requested: Option<AiState>,
+}
+
+impl AiStateMachine {
+    pub fn new(profile: AiProfile, speed: f32) -> Self {
+        Self {
+            profile,
+            speed,
+            state: AiState::Idle,
+            time_in_state: 0.,
+            has_fled: false,
+            requested: None,
+        }
+    }
+
+    pub fn state(&self) -> AiState {
+        self.state
+    }
+
+    /// Forces a transition on the next update regardless of the conditions.
+    pub fn request(&mut self, state: AiState) {
+        self.requested = Some(state);
+    }
+
+    fn next_state(&self, distance: f32, health: f32) -> Option<AiState> {
+        let profile = &self.profile;
+        if self.state == AiState::Dying {
+            return None;
+        }
+        if self.state != AiState::Flee
+            && !self.has_fled
+            && profile.flee_health > 0.
+            && health <= profile.flee_health
+        {
+            return Some(AiState::Flee);
+        }
+        match self.state {
+            AiState::Idle if self.time_in_state >= profile.idle_s => Some(AiState::Patrol),
+            AiState::Patrol if distance <= profile.sight_range => Some(AiState::Approach),
+            AiState::Approach if distance <= profile.attack_range => Some(AiState::Attack),
+            AiState::Approach if distance > profile.sight_range * AI_RANGE_HYSTERESIS => {
+                Some(AiState::Patrol)
+            }
+            AiState::Attack
+                if self.time_in_state >= AI_ATTACK_MIN_S
+                    && distance > profile.attack_range * AI_RANGE_HYSTERESIS =>
+            {
+                Some(AiState::Approach)
+            }
+            AiState::Flee if self.time_in_state >= profile.flee_s => Some(AiState::Patrol),
+            _ => None,
+        }
+    }
+}
+
+pub struct AiStateChanged {
+    pub entity: Entity,
+    pub from: AiState,
+    pub to: AiState,
+}
+
+#[derive(Resource, Default, Deref, DerefMut)]
+struct AiDebug(bool);
+
+#[derive(Component)]
+struct AiDebugLabel {
+    owner: Entity,
+}
+
+pub struct EnemyAiPlugin;
+
+impl Plugin for EnemyAiPlugin {
+    fn build(&self, app: &mut App) {
+        app.rollback_component::<AiStateMachine>()
+            .init_resource::<AiDebug>()
+            .add_event::<AiStateChanged>()
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationSet::EnemyAi
+                    .system_set()
+                    .with_system(ai_transition_system)
+                    .with_system(idle_enter_system.after(ai_transition_system))
+                    .with_system(patrol_enter_system.after(idle_enter_system))
+                    .with_system(attack_enter_system.after(patrol_enter_system))
+                    .with_system(attack_exit_system.after(attack_enter_system))
+                    .with_system(dying_enter_system.after(attack_exit_system))
+                    .with_system(patrol_update_system.after(dying_enter_system))
+                    .with_system(approach_update_system.after(patrol_update_system))
+                    .with_system(attack_update_system.after(approach_update_system))
+                    .with_system(flee_update_system.after(attack_update_system))
+                    .with_system(dying_update_system.after(flee_update_system)),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(ai_debug_toggle_system)
+                    .with_system(ai_debug_label_system),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(cleanup_system));
+    }
+}
+
+fn cleanup_system(
+    mut commands: Commands,
+    machine_query: Query<Entity, With<AiStateMachine>>,
+    label_query: Query<Entity, With<AiDebugLabel>>,
+) {
+    machine_query
+        .iter()
+        .chain(label_query.iter())
+        .for_each(|e| {
+            commands.entity(e).despawn_recursive();
+        });
+}
+
+type AiTransitionQuery<'a> = (
+    Entity,
+    &'a mut AiStateMachine,
+    &'a Transform,
+    Option<&'a Health>,
+);
+
+fn ai_transition_system(
+    tuning: Res<GameTuning>,
+    mut events: EventWriter<AiStateChanged>,
+    player_query: Query<&Transform, With<Player>>,
+    // enemies still flying in from off-screen stay idle until they arrive
+    mut query: Query<AiTransitionQuery, Without<SpawnEntry>>,
+) {
+    for (entity, mut machine, trans, health) in query.iter_mut() {
+        machine.time_in_state += tuning.time_step;
+        let player_trans = nearest_player(&player_query, trans.translation);
+        let distance = player_trans.map_or(f32::INFINITY, |player| {
+            player
+                .translation
+                .truncate()
+                .distance(trans.translation.truncate())
+        });
+        let health = health.map_or(1., Health::fraction);
+        let next = match machine.requested.take() {
+            Some(requested) => Some(requested),
+            None => machine.next_state(distance, health),
+        };
+        let next = match next {
+            Some(next) if next != machine.state => next,
+            _ => continue,
+        };
+        events.send(AiStateChanged {
+            entity,
+            from: machine.state,
+            to: next,
+        });
+        machine.has_fled |= next == AiState::Flee;
+        machine.state = next;
+        machine.time_in_state = 0.;
+    }
+}
+
+fn entered(events: &mut EventReader<AiStateChanged>, state: AiState) -> Vec<Entity> {
+    events
+        .iter()
+        .filter(|e| e.to == state)
+        .map(|e| e.entity)
+        .collect()
+}
+
+fn exited(events: &mut EventReader<AiStateChanged>, state: AiState) -> Vec<Entity> {
+    events
+        .iter()
+        .filter(|e| e.from == state)
+        .map(|e| e.entity)
+        .collect()
+}
+
+/// Towards the nearest player, zero once they're all gone.
+fn direction_to_player<'a>(
+    players: impl IntoIterator<Item = &'a Transform>,
+    trans: &Transform,
+) -> Vec2 {
+    nearest_player(players, trans.translation).map_or(Vec2::ZERO, |player| {
+        (player.translation - trans.translation)
+            .truncate()
+            .normalize_or_zero()
+    })
+}
+
+fn idle_enter_system(
+    mut events: EventReader<AiStateChanged>,
+    mut query: Query<&mut Velocity, With<AiStateMachine>>,
+) {
+    for entity in entered(&mut events, AiState::Idle) {
+        if let Ok(mut velocity) = query.get_mut(entity) {
+            **velocity = Vec3::ZERO;
+        }
+    }
+}
+
+fn patrol_enter_system(
+    mut events: EventReader<AiStateChanged>,
+    mut query: Query<(&AiStateMachine, &Transform, &mut Velocity)>,
+) {
+    for entity in entered(&mut events, AiState::Patrol) {
+        if let Ok((machine, trans, mut velocity)) = query.get_mut(entity) {
+            // head towards the wider side of the screen
+            let side = if trans.translation.x > 0. { -1. } else { 1. };
+            **velocity = Vec3::new(side * machine.profile.patrol_speed, 0., 0.);
+        }
+    }
+}
+
+fn patrol_update_system(
+    viewport_size: Res<ViewportSize>,
+    mut query: Query<(&AiStateMachine, &Transform, &mut Velocity)>,
+) {
+    let bound = viewport_size.w / 2. - AI_PATROL_MARGIN;
+    for (machine, trans, mut velocity) in query.iter_mut() {
+        if machine.state != AiState::Patrol {
+            continue;
+        }
+        let x = trans.translation.x;
+        if (x > bound && velocity.x > 0.) || (x < -bound && velocity.x < 0.) {
+            velocity.x = -velocity.x;
+        }
+    }
+}
+
+fn approach_update_system(
+    player_query: Query<&Transform, (With<Player>, Without<AiStateMachine>)>,
+    mut query: Query<(&AiStateMachine, &Transform, &mut Velocity)>,
+) {
+    for (machine, trans, mut velocity) in query.iter_mut() {
+        if machine.state != AiState::Approach {
+            continue;
+        }
+        **velocity = (direction_to_player(&player_query, trans) * machine.speed).extend(0.);
+    }
+}
+
+fn attack_enter_system(
+    mut events: EventReader<AiStateChanged>,
+    player_query: Query<&Transform, (With<Player>, Without<AiStateMachine>)>,
+    mut query: Query<(&AiStateMachine, &EnemyBehavior, &Transform, &mut Velocity)>,
+) {
+    for entity in entered(&mut events, AiState::Attack) {
+        if let Ok((machine, behavior, trans, mut velocity)) = query.get_mut(entity) {
+            // kamikaze ships commit to a straight dash, others hold their ground
+            **velocity = match behavior {
+                EnemyBehavior::Kamikaze => (direction_to_player(&player_query, trans)
+                    * machine.profile.attack_speed)
+                    .extend(0.),
+                _ => Vec3::ZERO,
+            };
+        }
+    }
+}
+
+fn attack_update_system(
+    player_query: Query<&Transform, (With<Player>, Without<AiStateMachine>)>,
+    mut query: Query<(&AiStateMachine, &EnemyBehavior, &Transform, &mut Velocity)>,
+) {
+    for (machine, behavior, trans, mut velocity) in query.iter_mut() {
+        if machine.state != AiState::Attack || *behavior == EnemyBehavior::Kamikaze {
+            continue;
+        }
+        // strafe around the player while the emitter does the work
+        let direction = direction_to_player(&player_query, trans);
+        let strafe = Vec2::new(-direction.y, direction.x) * machine.time_in_state.sin().signum();
+        **velocity = (strafe * machine.profile.attack_speed).extend(0.);
+    }
+}
+
+fn attack_exit_system(
+    mut events: EventReader<AiStateChanged>,
+    mut query: Query<&mut Velocity, With<AiStateMachine>>,
+) {
+    for entity in exited(&mut events, AiState::Attack) {
+        if let Ok(mut velocity) = query.get_mut(entity) {
+            **velocity = Vec3::ZERO;
+        }
+    }
+}
+
+fn flee_update_system(
+    player_query: Query<&Transform, (With<Player>, Without<AiStateMachine>)>,
+    mut query: Query<(&AiStateMachine, &Transform, &mut Velocity)>,
+) {
+    for (machine, trans, mut velocity) in query.iter_mut() {
+        if machine.state != AiState::Flee {
+            continue;
+        }
+        **velocity = (-direction_to_player(&player_query, trans) * machine.speed).extend(0.);
+    }
+}
+
+fn dying_enter_system(
+    mut commands: Commands,
+    mut events: EventReader<AiStateChanged>,
+    mut query: Query<&mut Velocity, With<AiStateMachine>>,
+) {
+    for entity in entered(&mut events, AiState::Dying) {
+        commands
+            .entity(entity)
+            .remove::<Enemy>()
+            .remove::<Health>()
+            .remove::<BulletEmitter>();
+        if let Ok(mut velocity) = query.get_mut(entity) {
+            **velocity = Vec3::ZERO;
+        }
+    }
+}
+
+fn dying_update_system(
+    mut commands: Commands,
+    tuning: Res<GameTuning>,
+    mut query: Query<(Entity, &AiStateMachine, &mut Transform)>,
+) {
+    for (entity, machine, mut trans) in query.iter_mut() {
+        if machine.state != AiState::Dying {
+            continue;
+        }
+        if machine.time_in_state >= AI_DYING_S {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        trans.rotate_z(AI_DYING_SPIN * tuning.time_step);
+        let shrink =
+            1. - tuning.time_step / (AI_DYING_S - machine.time_in_state + tuning.time_step);
+        trans.scale *= Vec3::new(shrink, shrink, 1.);
+    }
+}
+
+fn ai_debug_toggle_system(keys: Res<Input<KeyCode>>, mut debug: ResMut<AiDebug>) {
+    if keys.just_pressed(AI_DEBUG_KEY) {
+        **debug = !**debug;
+    }
+}
+
+fn ai_debug_label_system(
+    mut commands: Commands,
+    debug: Res<AiDebug>,
+    fonts: Res<Fonts>,
+    machine_query: Query<(Entity, &AiStateMachine, &Transform, &Sizeable), Without<AiDebugLabel>>,
+    mut label_query: Query<(Entity, &AiDebugLabel, &mut Transform, &mut Text)>,
+) {
+    for (label_entity, label, mut label_trans, mut text) in label_query.iter_mut() {
+        let (_, machine, trans, size) = match machine_query.get(label.owner) {
+            Ok(owner) if **debug => owner,
+            _ => {
+                commands.entity(label_entity).despawn_recursive();
+                continue;
+            }
+        };
+        label_trans.translation =
+            trans.translation + Vec3::new(0., -size.y * trans.scale.y / 2. - 10., 30.);
+        text.sections[0].value = format!("{:?}", machine.state());
+    }
+    if !**debug {
+        return;
+    }
+    for (entity, ..) in machine_query.iter() {
+        if label_query
+            .iter()
+            .any(|(_, label, ..)| label.owner == entity)
+        {
+            continue;
+        }
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    "",
+                    TextStyle {
+                        font: fonts.bold.clone(),
+                        font_size: AI_DEBUG_FONT_SIZE,
+                        color: Color::CYAN,
+                    },
+                )
+                .with_alignment(TextAlignment::CENTER),
+                ..Default::default()
+            },
+            AiDebugLabel { owner: entity },
+        ));
+    }
+}
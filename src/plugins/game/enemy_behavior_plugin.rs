@@ -0,0 +1,46 @@
+use bevy::prelude::*;
+
+use crate::resources::enemy_archetypes::EnemyBehavior;
+
+use super::enemy_plugin::Enemy;
+use super::game_plugin::{SimulationSet, SimulationStage};
+use super::player_plugin::{nearest_player, Player};
+use super::rollback_plugin::RollbackApp;
+
+pub struct EnemyBehaviorPlugin;
+
+impl Plugin for EnemyBehaviorPlugin {
+    fn build(&self, app: &mut App) {
+        app.rollback_component::<EnemyBehavior>()
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationSet::EnemyAim
+                    .system_set()
+                    .with_system(enemy_aim_system),
+            );
+    }
+}
+
+/// Rotation making the enemy sprite, which faces down, look along `direction`.
+pub fn enemy_facing(direction: Vec2) -> Quat {
+    Quat::from_rotation_z(direction.x.atan2(-direction.y))
+}
+
+fn enemy_aim_system(
+    player_query: Query<&Transform, (With<Player>, Without<Enemy>)>,
+    mut enemy_query: Query<(&EnemyBehavior, &mut Transform), With<Enemy>>,
+) {
+    for (behavior, mut trans) in enemy_query.iter_mut() {
+        if *behavior == EnemyBehavior::Drifter {
+            continue;
+        }
+        let player_trans = match nearest_player(&player_query, trans.translation) {
+            Some(player_trans) => player_trans,
+            None => return,
+        };
+        let direction = (player_trans.translation - trans.translation).truncate();
+        if direction != Vec2::ZERO {
+            trans.rotation = enemy_facing(direction);
+        }
+    }
+}
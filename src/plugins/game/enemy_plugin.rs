@@ -1,48 +1,86 @@
 use bevy::prelude::*;
 use bevy::sprite::collide_aabb::collide as bevy_collide;
-use rand::Rng;
 
 use crate::AppState;
 use crate::{
-    components::sizeable::Sizeable, resources::textures::Textures, ViewportSize, SPRITE_SCALE,
+    components::{
+        damage::Damage, health::Health, movable::Movable, sizeable::Sizeable, velocity::Velocity,
+    },
+    resources::{
+        enemy_archetypes::{EnemyArchetype, EnemyArchetypes, EnemyBehavior},
+        game_rng::{GameRng, RngStream},
+        game_tuning::GameTuning,
+        run_settings::{GameMode, RunSettings},
+        score::Score,
+        textures::Textures,
+    },
+    ViewportSize,
 };
 
+use super::bullet_emitter_plugin::BulletEmitter;
+use super::director_plugin::Director;
+use super::enemy_ai_plugin::{AiState, AiStateMachine};
 use super::explosion_plugin::ExplosionInvoke;
-use super::movement_plugin::TIME_STEP;
-use super::player_plugin::{HitPlayer, Player, PlayerProjectile};
+use super::formation_plugin::FormationMember;
+use super::game_plugin::{SimulationSet, SimulationStage};
+use super::movement_plugin::GameClock;
+use super::pickup_plugin::{DropTable, PickupDropInvoke};
+use super::player_plugin::{HitCause, HitPlayer, Player, PlayerId, PlayerProjectile, PlayerScores};
+use super::rollback_plugin::RollbackApp;
+use super::spawn_plugin::{telegraph_spawn, SpawnPlacement, SpawnTelegraph};
 
-const INITIAL_ENEMIES_COUNT: u16 = 5;
-const ENEMY_RESPAWN_DELAY: f32 = TIME_STEP * 120.;
+const SPLIT_SPREAD_PX: f32 = 40.;
 
-#[derive(Component)]
+#[derive(Component, Clone)]
 pub struct EnemyRespawn;
 
-#[derive(Resource, Deref, DerefMut)]
+#[derive(Resource, Clone, Deref, DerefMut)]
 pub struct LastEnemyRespawn(pub f32);
 
 impl Default for LastEnemyRespawn {
+    /// Lets the first respawn through right away, whatever the delay.
     fn default() -> Self {
-        Self(-(ENEMY_RESPAWN_DELAY + 1.))
+        Self(f32::NEG_INFINITY)
     }
 }
 
-#[derive(Component)]
-pub struct Enemy;
+#[derive(Component, Clone)]
+pub struct Enemy {
+    pub archetype: String,
+}
+
+/// Sent for every enemy shot down by the player.
+pub struct EnemyKilled {
+    pub archetype: String,
+}
 
 pub struct EnemyPlugin;
 
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(
-            SystemSet::on_enter(AppState::Playing).with_system(initial_enemies_spawn_system),
-        )
-        .add_system_set(
-            SystemSet::on_update(AppState::Playing)
-                .with_system(enemy_respawn_system)
-                .with_system(enemy_get_hit_system)
-                .with_system(enemy_hit_player_on_collision_system),
-        )
-        .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(cleanup_system));
+        app.rollback_component::<Enemy>()
+            .rollback_component::<EnemyRespawn>()
+            .rollback_component::<LastHitBy>()
+            .rollback_resource::<LastEnemyRespawn>()
+            .rollback_resource::<Score>()
+            .add_event::<EnemyKilled>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing).with_system(initial_enemies_spawn_system),
+            )
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationSet::Hits
+                    .system_set()
+                    .with_system(enemy_get_hit_system)
+                    .with_system(enemy_hit_player_on_collision_system.after(enemy_get_hit_system)),
+            )
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationSet::Respawns
+                    .system_set()
+                    .with_system(enemy_respawn_system),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(cleanup_system));
     }
 }
 
@@ -61,115 +99,310 @@ fn cleanup_system(
     last_spawn.0 = *LastEnemyRespawn::default();
 }
 
-fn initial_enemies_spawn_system(mut commands: Commands) {
+fn initial_enemies_spawn_system(mut commands: Commands, tuning: Res<GameTuning>) {
     commands.insert_resource(LastEnemyRespawn::default());
-    (0..INITIAL_ENEMIES_COUNT).for_each(|_| {
+    commands.insert_resource(Score::default());
+    (0..tuning.initial_enemies).for_each(|_| {
         commands.spawn_empty().insert(EnemyRespawn);
     });
 }
 
+pub fn spawn_enemy(
+    commands: &mut Commands,
+    textures: &Textures,
+    archetype: &EnemyArchetype,
+    translation: Vec3,
+) -> Entity {
+    let image = textures.image(&archetype.sprite).unwrap_or(&textures.enemy);
+    let scale = textures.sprite_scale * archetype.scale;
+    let mut enemy = commands.spawn(SpriteBundle {
+        sprite: Sprite {
+            color: archetype.color(),
+            ..Default::default()
+        },
+        texture: image.image.clone(),
+        transform: Transform {
+            translation,
+            scale: Vec3::new(scale, scale, 1.),
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    enemy
+        .insert(Enemy {
+            archetype: archetype.name.clone(),
+        })
+        .insert(archetype.behavior)
+        .insert(DropTable::default())
+        .insert(Health::new(archetype.health))
+        .insert(LastHitBy::default())
+        .insert(Sizeable(image.size_px));
+    if archetype.speed > 0. {
+        enemy
+            .insert(Velocity(Vec3::ZERO))
+            .insert(Movable::default());
+    }
+    if let Some(profile) = archetype.ai {
+        enemy.insert(AiStateMachine::new(profile, archetype.speed));
+    }
+    if let Some(pattern) = &archetype.emitter {
+        enemy.insert(BulletEmitter::new(pattern));
+    }
+    enemy.id()
+}
+
+/// Entities new enemies keep their distance from.
+type OccupiedFilter = Or<(With<Enemy>, With<SpawnTelegraph>)>;
+
 fn enemy_respawn_system(
     mut commands: Commands,
-    time: Res<Time>,
+    (clock, mut rng, tuning, director): (
+        Res<GameClock>,
+        ResMut<GameRng>,
+        Res<GameTuning>,
+        Res<Director>,
+    ),
     mut last_spawn: ResMut<LastEnemyRespawn>,
-    textures: Res<Textures>,
-    viewport_size: Res<ViewportSize>,
+    (textures, archetypes, placement, viewport_size): (
+        Res<Textures>,
+        Res<EnemyArchetypes>,
+        Res<SpawnPlacement>,
+        Res<ViewportSize>,
+    ),
     query: Query<Entity, With<EnemyRespawn>>,
-    player_query: Query<(&Sizeable, &Transform), With<Player>>,
+    player_query: Query<&Transform, With<Player>>,
+    occupied_query: Query<&Transform, OccupiedFilter>,
 ) {
-    if time.elapsed_seconds() - **last_spawn < ENEMY_RESPAWN_DELAY {
+    let delay = tuning.enemy_respawn_delay_s / director.pressure;
+    if clock.elapsed_seconds() - **last_spawn < delay {
         return;
     }
+    let respawn = match query.iter().next() {
+        Some(respawn) => respawn,
+        None => return,
+    };
 
-    let mut rand = rand::thread_rng();
-
-    let mut spawn_enemy = |entity| {
-        let mut get_next_trans = || {
-            let w_span = viewport_size.w / 2. - textures.enemy.size_px.x * SPRITE_SCALE;
-            let h_span = viewport_size.h / 2. - textures.enemy.size_px.y * SPRITE_SCALE;
-            let w = rand.gen_range(-w_span..w_span);
-            let h = rand.gen_range(-h_span..h_span);
-            Vec3 { x: w, y: h, z: 10. }
-        };
-        let enemy_size = textures.enemy.size_px;
-        let mut enemy_trans = get_next_trans();
-        let enemy_scale = Vec3 {
-            x: SPRITE_SCALE,
-            y: SPRITE_SCALE,
-            z: 1.,
-        };
-
-        if let Ok((player_size, player_trans)) = player_query.get_single() {
-            while collide(
-                player_trans.translation,
-                **player_size,
-                player_trans.scale,
-                enemy_trans,
-                enemy_size,
-                enemy_scale,
-            ) {
-                enemy_trans = get_next_trans();
-            }
-        }
+    let rand = rng.stream(RngStream::Spawn);
+    let archetype = archetypes.pick(rand);
+    let image = textures.image(&archetype.sprite).unwrap_or(&textures.enemy);
+    let half_size = image.size_px * textures.sprite_scale * archetype.scale / 2.;
+    let others: Vec<Vec2> = occupied_query
+        .iter()
+        .map(|trans| trans.translation.truncate())
+        .collect();
+    let position = placement.find_position(
+        rand,
+        &viewport_size,
+        half_size,
+        &player_query
+            .iter()
+            .map(|trans| trans.translation.truncate())
+            .collect::<Vec<_>>(),
+        &others,
+    );
 
-        commands.entity(entity).despawn();
-        commands
-            .spawn(SpriteBundle {
-                texture: textures.enemy.image.clone(),
-                transform: Transform {
-                    translation: enemy_trans,
-                    scale: enemy_scale,
-                    ..Default::default()
-                },
-                ..Default::default()
-            })
-            .insert(Enemy {})
-            .insert(Sizeable(enemy_size));
-    };
-    if let Some(enemy) = query.iter().next() {
-        spawn_enemy(enemy);
-        last_spawn.0 = time.elapsed_seconds();
-    }
+    commands.entity(respawn).despawn();
+    telegraph_spawn(
+        &mut commands,
+        &textures,
+        &archetypes,
+        &placement,
+        &archetype.name,
+        position,
+    );
+    last_spawn.0 = clock.elapsed_seconds();
 }
 
+type RammingEnemyQuery<'a> = (
+    Entity,
+    &'a Enemy,
+    &'a Sizeable,
+    &'a Transform,
+    &'a EnemyBehavior,
+    &'a Health,
+    Option<&'a FormationMember>,
+);
+
+/// Kamikaze ships are destroyed by ramming a player, without counting as a kill.
 fn enemy_hit_player_on_collision_system(
     mut commands: Commands,
-    player_query: Query<(&Sizeable, &Transform), With<Player>>,
-    enemy_query: Query<(&Sizeable, &Transform), With<Enemy>>,
+    (archetypes, settings): (Res<EnemyArchetypes>, Res<RunSettings>),
+    player_query: Query<(Entity, &Sizeable, &Transform), With<Player>>,
+    enemy_query: Query<RammingEnemyQuery>,
 ) {
-    if let Ok((player_size, player_trans)) = player_query.get_single() {
-        for (enemy_size, enemy_trans) in enemy_query.iter() {
+    for (enemy_entity, enemy, enemy_size, enemy_trans, behavior, health, member) in
+        enemy_query.iter()
+    {
+        // shot down this step, the kill already paid out
+        if health.is_dead() {
+            continue;
+        }
+        let mut rammed = false;
+        for (player, player_size, player_trans) in player_query.iter() {
             if collide_entities(player_trans, player_size, enemy_trans, enemy_size) {
-                commands.spawn_empty().insert(HitPlayer {});
+                commands.spawn_empty().insert(HitPlayer {
+                    player,
+                    cause: HitCause::Collision(enemy.archetype.clone()),
+                });
+                rammed = true;
             }
         }
+        if !rammed || *behavior != EnemyBehavior::Kamikaze {
+            continue;
+        }
+        commands.entity(enemy_entity).despawn_recursive();
+        commands.spawn_empty().insert(ExplosionInvoke {
+            translation: enemy_trans.translation,
+        });
+        let respawns = archetypes
+            .get(&enemy.archetype)
+            .is_some_and(|archetype| archetype.respawns());
+        if respawns && member.is_none() && settings.mode != GameMode::WaveSurvival {
+            commands.spawn_empty().insert(EnemyRespawn {});
+        }
     }
 }
 
+/// Player who last damaged the enemy, credited with the kill.
+#[derive(Component, Clone, Copy, Default)]
+pub struct LastHitBy(pub Option<PlayerId>);
+
+type EnemyHitQuery<'a> = (
+    Entity,
+    &'a Enemy,
+    &'a Sizeable,
+    &'a Transform,
+    &'a mut Health,
+    &'a DropTable,
+    Option<&'a FormationMember>,
+    Option<&'a mut AiStateMachine>,
+    Option<&'a mut LastHitBy>,
+);
+
+type PlayerProjectileQuery<'a> = (
+    Entity,
+    &'a Sizeable,
+    &'a Transform,
+    &'a mut Damage,
+    &'a PlayerProjectile,
+);
+
 fn enemy_get_hit_system(
     mut commands: Commands,
-    proj_query: Query<(Entity, &Sizeable, &Transform), With<PlayerProjectile>>,
-    enemy_query: Query<(Entity, &Sizeable, &Transform), With<Enemy>>,
+    (textures, archetypes, settings): (Res<Textures>, Res<EnemyArchetypes>, Res<RunSettings>),
+    (mut score, mut player_scores): (ResMut<Score>, ResMut<PlayerScores>),
+    mut killed: EventWriter<EnemyKilled>,
+    mut proj_query: Query<PlayerProjectileQuery>,
+    mut enemy_query: Query<EnemyHitQuery>,
 ) {
-    let mut despawn_enemy = |enemy_entity: Entity, enemy_trans: &Transform, proj_entity: Entity| {
-        commands.entity(enemy_entity).despawn();
-        commands.entity(proj_entity).despawn();
+    let mut spent_projectiles = Vec::new();
+    for (
+        enemy_entity,
+        enemy,
+        enemy_size,
+        enemy_trans,
+        mut health,
+        drop_table,
+        member,
+        ai,
+        mut last_hit,
+    ) in enemy_query.iter_mut()
+    {
+        for (proj_entity, proj_size, proj_trans, mut damage, projectile) in proj_query.iter_mut() {
+            if health.is_dead() {
+                break;
+            }
+            if spent_projectiles.contains(&proj_entity)
+                || !collide_entities(proj_trans, proj_size, enemy_trans, enemy_size)
+            {
+                continue;
+            }
+            // damage above the remaining health lets the projectile pierce through
+            let dealt = damage.min(health.current);
+            health.current -= dealt;
+            **damage -= dealt;
+            if let Some(last_hit) = last_hit.as_mut() {
+                last_hit.0 = Some(projectile.0);
+            }
+            if **damage <= 0. {
+                spent_projectiles.push(proj_entity);
+                commands.entity(proj_entity).despawn_recursive();
+            }
+        }
+
+        if !health.is_dead() {
+            continue;
+        }
+        // enemies with an AI play out their dying state before despawning
+        match ai {
+            Some(mut ai) => {
+                commands.entity(enemy_entity).remove::<Enemy>();
+                ai.request(AiState::Dying);
+            }
+            None => commands.entity(enemy_entity).despawn_recursive(),
+        }
         commands.spawn_empty().insert(ExplosionInvoke {
             translation: enemy_trans.translation,
         });
-        commands.spawn_empty().insert(EnemyRespawn {});
-    };
-    for (enemy_entity, enemy_size, enemy_trans) in enemy_query.iter() {
-        for (proj_entity, proj_size, proj_trans) in proj_query.iter() {
-            if collide_entities(proj_trans, proj_size, enemy_trans, enemy_size) {
-                despawn_enemy(enemy_entity, enemy_trans, proj_entity);
-                break;
+        commands.spawn_empty().insert(PickupDropInvoke {
+            translation: enemy_trans.translation,
+            table: drop_table.clone(),
+        });
+        killed.send(EnemyKilled {
+            archetype: enemy.archetype.clone(),
+        });
+
+        let archetype = match archetypes.get(&enemy.archetype) {
+            Some(archetype) => archetype,
+            None => continue,
+        };
+        **score += archetype.score;
+        let killer = last_hit.and_then(|last_hit| last_hit.0);
+        if let Some(player_score) = killer.and_then(|player| player_scores.get_mut(player.index()))
+        {
+            *player_score += archetype.score;
+        }
+        // formation members are replaced by the next formation instead,
+        // wave survival has nothing but its waves
+        if archetype.respawns() && member.is_none() && settings.mode != GameMode::WaveSurvival {
+            commands.spawn_empty().insert(EnemyRespawn {});
+        }
+        if let Some(split) = &archetype.split {
+            if let Some(child) = archetypes.get(&split.into) {
+                for i in 0..split.count {
+                    let angle = std::f32::consts::TAU * i as f32 / split.count as f32;
+                    let offset = Vec2::from_angle(angle) * SPLIT_SPREAD_PX;
+                    spawn_enemy(
+                        &mut commands,
+                        &textures,
+                        child,
+                        enemy_trans.translation + offset.extend(0.),
+                    );
+                }
             }
         }
     }
 }
 
-fn collide_entities(
+/// Distance along the ray to the entry point into the entity box, `None` if the ray misses it.
+pub fn ray_cast_entity(
+    origin: Vec2,
+    direction: Vec2,
+    trans: &Transform,
+    size: &Sizeable,
+) -> Option<f32> {
+    let half = **size * trans.scale.x / 2.;
+    let min = (trans.translation.truncate() - half - origin) / direction;
+    let max = (trans.translation.truncate() + half - origin) / direction;
+    let enter = min.min(max).max_element();
+    let exit = min.max(max).min_element();
+    match exit >= enter.max(0.) {
+        true => Some(enter.max(0.)),
+        false => None,
+    }
+}
+
+pub fn collide_entities(
     proj_trans: &Transform,
     proj_size: &Sizeable,
     enemy_trans: &Transform,
@@ -3,16 +3,15 @@ use std::time::Duration;
 use bevy::prelude::*;
 
 use crate::{
-    plugins::sprite_animation_plugin::AnimationTimer, resources::textures::Textures, AppState,
-    SPRITE_SCALE,
+    plugins::sprite_animation_plugin::AnimationTimer,
+    resources::{game_tuning::GameTuning, textures::Textures},
+    AppState,
 };
 
-use super::movement_plugin::TIME_STEP;
+use super::game_plugin::{SimulationSet, SimulationStage};
+use super::rollback_plugin::RollbackApp;
 
-const FRAMES_PER_TIME_STEP: u32 = 2;
-const ANIMATION_STEP_S: f32 = TIME_STEP * FRAMES_PER_TIME_STEP as f32;
-
-#[derive(Component)]
+#[derive(Component, Clone)]
 pub struct ExplosionInvoke {
     pub translation: Vec3,
 }
@@ -21,10 +20,14 @@ pub struct ExplosionPlugin;
 
 impl Plugin for ExplosionPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(
-            SystemSet::on_update(AppState::Playing).with_system(explosion_spawn_system),
-        )
-        .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(cleanup_system));
+        app.rollback_component::<ExplosionInvoke>()
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationSet::Explosions
+                    .system_set()
+                    .with_system(explosion_spawn_system),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(cleanup_system));
     }
 }
 
@@ -36,9 +39,10 @@ fn cleanup_system(mut commands: Commands, query: Query<Entity, With<ExplosionInv
 
 fn explosion_spawn_system(
     mut commands: Commands,
-    textures: Res<Textures>,
+    (textures, tuning): (Res<Textures>, Res<GameTuning>),
     query: Query<(Entity, &ExplosionInvoke)>,
 ) {
+    let frame_s = tuning.time_step * tuning.explosion_frames_per_step as f32;
     for (explosion, pos) in query.iter() {
         commands.spawn((
             SpriteSheetBundle {
@@ -46,8 +50,8 @@ fn explosion_spawn_system(
                 transform: Transform {
                     translation: pos.translation,
                     scale: Vec3 {
-                        x: SPRITE_SCALE,
-                        y: SPRITE_SCALE,
+                        x: textures.sprite_scale,
+                        y: textures.sprite_scale,
                         z: 1.,
                     },
                     ..Default::default()
@@ -55,10 +59,7 @@ fn explosion_spawn_system(
                 ..Default::default()
             },
             AnimationTimer {
-                timer: Timer::new(
-                    Duration::from_secs_f32(ANIMATION_STEP_S),
-                    TimerMode::Repeating,
-                ),
+                timer: Timer::new(Duration::from_secs_f32(frame_s), TimerMode::Repeating),
                 play_once: true,
             },
         ));
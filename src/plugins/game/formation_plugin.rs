@@ -0,0 +1,304 @@
+use bevy::{
+    ecs::entity::{EntityMap, MapEntities, MapEntitiesError},
+    prelude::*,
+};
+
+use crate::{
+    components::{movable::Movable, velocity::Velocity},
+    resources::{
+        enemy_archetypes::{EnemyArchetypes, EnemyBehavior},
+        formations::{FlightPath, Formations},
+        game_tuning::GameTuning,
+        run_settings::{GameMode, RunSettings},
+        textures::Textures,
+    },
+    AppState, ViewportSize,
+};
+
+use super::boss_plugin::Boss;
+use super::enemy_behavior_plugin::enemy_facing;
+use super::enemy_plugin::spawn_enemy;
+use super::game_plugin::{SimulationSet, SimulationStage};
+use super::movement_plugin::GameClock;
+use super::rollback_plugin::RollbackApp;
+
+const FORMATION_INITIAL_DELAY_S: f32 = 8.;
+const FORMATION_INTERVAL_S: f32 = 25.;
+
+#[derive(Resource, Clone)]
+pub struct FormationSchedule {
+    timer: Timer,
+    next: usize,
+    spawned: u32,
+    /// Waves the run ends after, `None` keeps them coming.
+    limit: Option<u32>,
+}
+
+impl FormationSchedule {
+    /// Waves spawned in this run so far.
+    pub fn spawned(&self) -> u32 {
+        self.spawned
+    }
+
+    pub fn limit(&self) -> Option<u32> {
+        self.limit
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.limit.is_some_and(|limit| self.spawned >= limit)
+    }
+}
+
+/// Group anchor the members settle around, spawns its members one by one.
+#[derive(Component, Clone)]
+pub struct Formation {
+    name: String,
+    pending: Vec<Vec2>,
+    spawned: usize,
+    spawn_cooldown: f32,
+    sway_time: f32,
+}
+
+#[derive(Clone)]
+enum MemberState {
+    Entering { distance: f32 },
+    Joining,
+    Settled,
+}
+
+#[derive(Component, Clone)]
+pub struct FormationMember {
+    formation: Entity,
+    slot: Vec2,
+    mirror: bool,
+    state: MemberState,
+}
+
+impl MapEntities for FormationMember {
+    fn map_entities(&mut self, entities: &EntityMap) -> Result<(), MapEntitiesError> {
+        self.formation = entities.get(self.formation)?;
+        Ok(())
+    }
+}
+
+pub struct FormationPlugin;
+
+impl Plugin for FormationPlugin {
+    fn build(&self, app: &mut App) {
+        app.rollback_component::<Formation>()
+            .rollback_mapped_component::<FormationMember>()
+            .rollback_resource::<FormationSchedule>()
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(setup_system))
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationSet::Formations
+                    .system_set()
+                    .with_system(formation_schedule_system)
+                    .with_system(formation_system.after(formation_schedule_system))
+                    .with_system(formation_member_system.after(formation_system)),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(cleanup_system));
+    }
+}
+
+fn setup_system(mut commands: Commands, settings: Res<RunSettings>, tuning: Res<GameTuning>) {
+    // wave survival is nothing but waves, so they follow each other closely
+    let (interval, initial_delay, limit) = match settings.mode {
+        GameMode::WaveSurvival => (
+            tuning.modes.survival_wave_interval_s,
+            tuning.modes.survival_wave_interval_s,
+            Some(tuning.modes.survival_waves),
+        ),
+        _ => (FORMATION_INTERVAL_S, FORMATION_INITIAL_DELAY_S, None),
+    };
+    let mut timer = Timer::from_seconds(interval, TimerMode::Repeating);
+    timer.set_elapsed(std::time::Duration::from_secs_f32(interval - initial_delay));
+    commands.insert_resource(FormationSchedule {
+        timer,
+        next: settings.start_wave.saturating_sub(1) as usize,
+        spawned: 0,
+        limit,
+    });
+}
+
+fn cleanup_system(mut commands: Commands, query: Query<Entity, With<Formation>>) {
+    for e in query.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
+
+/// A new formation waits for the previous one and for any boss fight.
+type FormationBusyFilter = Or<(With<Formation>, With<Boss>)>;
+
+fn formation_schedule_system(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    formations: Res<Formations>,
+    mut schedule: ResMut<FormationSchedule>,
+    busy_query: Query<(), FormationBusyFilter>,
+) {
+    if !busy_query.is_empty() || formations.formations.is_empty() || schedule.is_done() {
+        return;
+    }
+    schedule.timer.tick(clock.delta());
+    if !schedule.timer.just_finished() {
+        return;
+    }
+    let (name, def) = &formations.formations[schedule.next % formations.formations.len()];
+    schedule.next += 1;
+    schedule.spawned += 1;
+    commands.spawn((
+        TransformBundle::default(),
+        Formation {
+            name: name.clone(),
+            pending: def.shape.slots(),
+            spawned: 0,
+            spawn_cooldown: 0.,
+            sway_time: 0.,
+        },
+    ));
+}
+
+/// Path point in pixels, odd members fly the path mirrored.
+fn path_point(path: &FlightPath, distance: f32, mirror: bool, half: Vec2) -> Vec2 {
+    let point = path.point_at(distance) * half;
+    match mirror {
+        true => Vec2::new(-point.x, point.y),
+        false => point,
+    }
+}
+
+fn formation_system(
+    mut commands: Commands,
+    tuning: Res<GameTuning>,
+    (textures, archetypes, formations): (Res<Textures>, Res<EnemyArchetypes>, Res<Formations>),
+    viewport_size: Res<ViewportSize>,
+    mut formation_query: Query<(Entity, &mut Formation, &mut Transform)>,
+    member_query: Query<&FormationMember>,
+) {
+    let half = Vec2::new(viewport_size.w, viewport_size.h) / 2.;
+    for (entity, mut formation, mut trans) in formation_query.iter_mut() {
+        let def = match formations
+            .formations
+            .iter()
+            .find(|(n, _)| *n == formation.name)
+        {
+            Some((_, def)) => def,
+            None => continue,
+        };
+        formation.sway_time += tuning.time_step * def.sway_speed;
+        trans.translation = Vec3::new(
+            formation.sway_time.sin() * def.sway_amplitude,
+            def.anchor_y * half.y,
+            0.,
+        );
+
+        if formation.pending.is_empty() {
+            if !member_query.iter().any(|m| m.formation == entity) {
+                commands.entity(entity).despawn_recursive();
+            }
+            continue;
+        }
+        formation.spawn_cooldown -= tuning.time_step;
+        if formation.spawn_cooldown > 0. {
+            continue;
+        }
+        formation.spawn_cooldown = def.spawn_interval_s;
+
+        let (archetype, path) = match (
+            archetypes.get(&def.archetype),
+            formations.paths.get(&def.path),
+        ) {
+            (Some(archetype), Some(path)) => (archetype, path),
+            _ => continue,
+        };
+        let slot = formation.pending.remove(0);
+        let mirror = def.mirror && formation.spawned % 2 == 1;
+        formation.spawned += 1;
+        let start = path_point(path, 0., mirror, half);
+        let member = spawn_enemy(&mut commands, &textures, archetype, start.extend(10.));
+        commands
+            .entity(member)
+            .insert(Velocity(Vec3::ZERO))
+            .insert(Movable::default())
+            .insert(FormationMember {
+                formation: entity,
+                slot,
+                mirror,
+                state: MemberState::Entering { distance: 0. },
+            });
+    }
+}
+
+type FormationMemberQuery<'a> = (
+    &'a mut FormationMember,
+    &'a mut Transform,
+    &'a mut Velocity,
+    &'a EnemyBehavior,
+);
+
+fn formation_member_system(
+    tuning: Res<GameTuning>,
+    formations: Res<Formations>,
+    viewport_size: Res<ViewportSize>,
+    formation_query: Query<(&Formation, &Transform), Without<FormationMember>>,
+    mut member_query: Query<FormationMemberQuery>,
+) {
+    let half = Vec2::new(viewport_size.w, viewport_size.h) / 2.;
+    for (mut member, mut trans, mut velocity, behavior) in member_query.iter_mut() {
+        let (formation, formation_trans) = match formation_query.get(member.formation) {
+            Ok(formation) => formation,
+            Err(_) => {
+                **velocity = Vec3::ZERO;
+                continue;
+            }
+        };
+        let (def, path) = match formations
+            .formations
+            .iter()
+            .find(|(n, _)| *n == formation.name)
+            .and_then(|(_, def)| formations.paths.get(&def.path).map(|path| (def, path)))
+        {
+            Some(found) => found,
+            None => continue,
+        };
+        let position = trans.translation.truncate();
+        let slot = formation_trans.translation.truncate() + member.slot;
+        // members are moved as enemies, faster with the enemy speed scale
+        let speed = tuning.base_speed * tuning.enemy_speed_scale;
+        let step = def.speed * speed * tuning.time_step;
+
+        let target = match member.state {
+            MemberState::Entering { distance } => {
+                // advance by a fixed pixel step, the path itself is in viewport units
+                let here = path_point(path, distance, member.mirror, half);
+                let ahead = path_point(path, distance + 0.01, member.mirror, half);
+                let distance = distance + 0.01 * step / here.distance(ahead).max(0.01);
+                member.state = match distance >= path.length() {
+                    true => MemberState::Joining,
+                    false => MemberState::Entering { distance },
+                };
+                path_point(path, distance, member.mirror, half)
+            }
+            MemberState::Joining => {
+                if position.distance(slot) <= step {
+                    member.state = MemberState::Settled;
+                    slot
+                } else {
+                    position + (slot - position).normalize() * step
+                }
+            }
+            MemberState::Settled => slot,
+        };
+        **velocity = ((target - position) / (tuning.time_step * speed)).extend(0.);
+
+        // turrets aim on their own, drifters face where they fly
+        if *behavior == EnemyBehavior::Drifter {
+            trans.rotation = match member.state {
+                MemberState::Settled => Quat::IDENTITY,
+                _ if target != position => enemy_facing(target - position),
+                _ => trans.rotation,
+            };
+        }
+    }
+}
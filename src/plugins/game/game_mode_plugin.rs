@@ -0,0 +1,114 @@
+use bevy::prelude::*;
+
+use crate::{
+    plugins::delayed_state_switch_plugin::StateSetCommand,
+    resources::{
+        game_tuning::GameTuning,
+        run_settings::{GameMode, RunSettings},
+    },
+    AppState,
+};
+
+use super::enemy_plugin::Enemy;
+use super::formation_plugin::{Formation, FormationSchedule};
+use super::game_plugin::{SimulationSet, SimulationStage};
+use super::movement_plugin::GameClock;
+use super::player_plugin::{Lives, Player};
+use super::rollback_plugin::RollbackApp;
+use super::spawn_plugin::SpawnTelegraph;
+
+const RUN_END_DELAY_S: f32 = 2.;
+
+/// How the current run ended, inserted once the game over screen is on its way.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunOutcome {
+    /// The last life was lost.
+    Defeat,
+    /// The time attack limit ran out.
+    TimeUp,
+    /// Every wave of a wave survival run was cleared.
+    Victory,
+    /// The other player of an online run left or stopped answering.
+    Disconnected,
+}
+
+impl RunOutcome {
+    pub fn title(&self) -> &'static str {
+        match self {
+            RunOutcome::Defeat => "GAME OVER",
+            RunOutcome::TimeUp => "TIME UP",
+            RunOutcome::Victory => "VICTORY",
+            RunOutcome::Disconnected => "DISCONNECTED",
+        }
+    }
+}
+
+/// Win and lose conditions of the game modes, the last life ends a run in all of them.
+pub struct GameModePlugin;
+
+impl Plugin for GameModePlugin {
+    fn build(&self, app: &mut App) {
+        app.rollback_resource::<RunOutcome>()
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(setup_system))
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationSet::GameMode
+                    .system_set()
+                    .with_system(time_attack_system)
+                    .with_system(wave_survival_system.after(time_attack_system)),
+            );
+    }
+}
+
+fn setup_system(mut commands: Commands) {
+    commands.remove_resource::<RunOutcome>();
+}
+
+/// Ends the run and heads for the game over screen.
+pub fn end_run(commands: &mut Commands, outcome: RunOutcome) {
+    commands.insert_resource(outcome);
+    commands.spawn_empty().insert(StateSetCommand {
+        target: AppState::GameOver,
+        delay: Timer::from_seconds(RUN_END_DELAY_S, TimerMode::Once),
+    });
+}
+
+/// A run that already ended or whose last life went this step can't end another way.
+fn is_running(
+    outcome: &Option<Res<RunOutcome>>,
+    player_query: &Query<&Lives, With<Player>>,
+) -> bool {
+    outcome.is_none() && player_query.iter().any(|lives| **lives > 0)
+}
+
+fn time_attack_system(
+    mut commands: Commands,
+    (settings, tuning, clock): (Res<RunSettings>, Res<GameTuning>, Res<GameClock>),
+    outcome: Option<Res<RunOutcome>>,
+    player_query: Query<&Lives, With<Player>>,
+) {
+    if settings.mode != GameMode::TimeAttack || !is_running(&outcome, &player_query) {
+        return;
+    }
+    if clock.elapsed_seconds() >= tuning.modes.time_attack_s {
+        end_run(&mut commands, RunOutcome::TimeUp);
+    }
+}
+
+/// Anything still to be fought in the current wave.
+type WaveActiveFilter = Or<(With<Enemy>, With<Formation>, With<SpawnTelegraph>)>;
+
+fn wave_survival_system(
+    mut commands: Commands,
+    (settings, schedule): (Res<RunSettings>, Res<FormationSchedule>),
+    outcome: Option<Res<RunOutcome>>,
+    player_query: Query<&Lives, With<Player>>,
+    active_query: Query<(), WaveActiveFilter>,
+) {
+    if settings.mode != GameMode::WaveSurvival || !is_running(&outcome, &player_query) {
+        return;
+    }
+    if schedule.is_done() && active_query.is_empty() {
+        end_run(&mut commands, RunOutcome::Victory);
+    }
+}
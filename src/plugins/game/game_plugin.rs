@@ -1,28 +1,203 @@
-use bevy::prelude::*;
-
-use crate::AppState;
+use bevy::{
+    ecs::schedule::{ParallelSystemExecutor, SystemContainer},
+    prelude::*,
+};
 
-use super::{
-    enemy_plugin::EnemyPlugin, explosion_plugin::ExplosionPlugin, movement_plugin::MovementPlugin,
-    pause_menu_plugin::PauseMenuPlugin, player_plugin::PlayerPlugin,
+use crate::{
+    plugins::net_plugin::NetSession,
+    resources::{
+        daily_challenge::DailyChallenge,
+        game_config::GameConfig,
+        game_rng::{GameRng, SeedSettings},
+        game_tuning::{GameTuning, TuningPresets},
+        run_settings::{GameMode, RunSettings},
+    },
+    AppState,
 };
 
-pub struct GamePlugin;
+use super::replay_plugin::{simulation_steps_criteria, ReplayPlayback};
+use super::rollback_plugin::RollbackApp;
+
+/// Core of a run: the simulation stage and its step order, seeding and the
+/// configured viewport and starting state. Gameplay comes from the other game plugins.
+#[derive(Default)]
+pub struct GamePlugin {
+    pub config: GameConfig,
+}
+
+/// Stage after `CoreStage::Update` holding the simulation systems, run as
+/// many steps per frame as the run allows.
+#[derive(StageLabel)]
+pub struct SimulationStage;
+
+/// Run criteria of every simulation system, stepping only while playing.
+#[derive(RunCriteriaLabel)]
+pub struct SimulationSteps;
+
+/// Parts of a simulation step, run in this order. The systems of a part are
+/// ordered among themselves too, a step runs the same way in every app.
+#[derive(SystemLabel, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SimulationSet {
+    Clock,
+    /// Reacts to the last step's hits before this one can despawn anything.
+    Feedback,
+    NetInput,
+    ReplayInput,
+    PlayerControl,
+    Record,
+    Weapons,
+    Formations,
+    EnemyAi,
+    EnemyAim,
+    Emitters,
+    Boss,
+    Homing,
+    Movement,
+    BossParts,
+    Beams,
+    Hits,
+    EnemyProjectiles,
+    BossDeath,
+    Pickups,
+    PlayerHits,
+    Director,
+    GameMode,
+    Respawns,
+    Telegraphs,
+    Explosions,
+    ShieldTint,
+}
+
+impl SimulationSet {
+    const ALL: [SimulationSet; 27] = [
+        SimulationSet::Clock,
+        SimulationSet::Feedback,
+        SimulationSet::NetInput,
+        SimulationSet::ReplayInput,
+        SimulationSet::PlayerControl,
+        SimulationSet::Record,
+        SimulationSet::Weapons,
+        SimulationSet::Formations,
+        SimulationSet::EnemyAi,
+        SimulationSet::EnemyAim,
+        SimulationSet::Emitters,
+        SimulationSet::Boss,
+        SimulationSet::Homing,
+        SimulationSet::Movement,
+        SimulationSet::BossParts,
+        SimulationSet::Beams,
+        SimulationSet::Hits,
+        SimulationSet::EnemyProjectiles,
+        SimulationSet::BossDeath,
+        SimulationSet::Pickups,
+        SimulationSet::PlayerHits,
+        SimulationSet::Director,
+        SimulationSet::GameMode,
+        SimulationSet::Respawns,
+        SimulationSet::Telegraphs,
+        SimulationSet::Explosions,
+        SimulationSet::ShieldTint,
+    ];
+
+    /// Set of the part for `SimulationStage`, running after the part before it
+    /// on every step of a run.
+    pub fn system_set(self) -> SystemSet {
+        let set = SystemSet::new()
+            .with_run_criteria(SimulationSteps)
+            .label(self);
+        match self as usize {
+            0 => set,
+            index => set.after(Self::ALL[index - 1]),
+        }
+    }
+}
+
+/// Runs the simulation systems one at a time. Bevy orders systems without
+/// constraints between them through a randomly seeded hash set, so every
+/// system should be ordered against the one before it, a warning names any
+/// that aren't.
+#[derive(Default)]
+struct StepExecutor;
+
+impl ParallelSystemExecutor for StepExecutor {
+    fn rebuild_cached_data(&mut self, systems: &[SystemContainer]) {
+        // sorted by their constraints, two neighbours only keep their order
+        // if the second depends on the first
+        for (index, pair) in systems.windows(2).enumerate() {
+            if !pair[1].dependencies().contains(&index) {
+                warn!(
+                    "simulation systems {} and {} have no order between them, runs may not replay the same",
+                    pair[0].name(),
+                    pair[1].name(),
+                );
+            }
+        }
+    }
+
+    fn run_systems(&mut self, systems: &mut [SystemContainer], world: &mut World) {
+        for system in systems {
+            if system.should_run() {
+                system.system_mut().run((), world);
+            }
+        }
+    }
+}
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(MovementPlugin)
-            .add_plugin(ExplosionPlugin)
-            .add_plugin(PlayerPlugin)
-            .add_plugin(EnemyPlugin)
-            .add_plugin(PauseMenuPlugin)
+        app.rollback_resource::<GameRng>()
+            .add_stage_after(
+                CoreStage::Update,
+                SimulationStage,
+                SystemStage::new(Box::<StepExecutor>::default())
+                    .with_system_run_criteria(simulation_steps_criteria.label(SimulationSteps)),
+            )
+            .init_resource::<SeedSettings>()
+            .init_resource::<GameRng>()
+            .init_resource::<RunSettings>()
+            .insert_resource(self.config.viewport)
+            .insert_resource(self.config.tuning.tuning(RunSettings::default().difficulty))
+            .insert_resource(self.config.tuning.clone())
+            .insert_resource(self.config.clone())
+            .add_system_to_stage(CoreStage::PreUpdate, tuning_system)
+            .add_state(self.config.start_state)
             .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(setup_system))
             .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(cleanup_system));
     }
 }
 
-fn setup_system(mut commands: Commands) {
+fn setup_system(
+    mut commands: Commands,
+    (seed_settings, settings, daily): (
+        Res<SeedSettings>,
+        Res<RunSettings>,
+        Option<Res<DailyChallenge>>,
+    ),
+    (playback, net): (Option<Res<ReplayPlayback>>, Option<Res<NetSession>>),
+) {
     commands.spawn(Camera2dBundle::default());
+    let seed = match (net, playback, daily) {
+        // both players of an online run simulate it from the host's seed
+        (Some(net), _, _) => net.seed(),
+        (None, Some(playback), _) => playback.replay.seed,
+        (None, None, Some(daily)) if settings.mode == GameMode::DailyChallenge => daily.seed,
+        (None, None, _) => seed_settings.next_seed(),
+    };
+    commands.insert_resource(GameRng::new(seed));
+}
+
+/// Keeps the tuning in line with the difficulty, mode and modifiers of the run.
+fn tuning_system(
+    settings: Res<RunSettings>,
+    presets: Res<TuningPresets>,
+    mut tuning: ResMut<GameTuning>,
+) {
+    if settings.is_changed() || presets.is_changed() {
+        *tuning = presets
+            .tuning(settings.difficulty)
+            .with_mode(settings.mode)
+            .with_modifiers(settings.modifiers);
+    }
 }
 
 fn cleanup_system(mut commands: Commands, query: Query<Entity, With<Camera2d>>) {
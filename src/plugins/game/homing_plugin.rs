@@ -0,0 +1,128 @@
+use bevy::{
+    ecs::entity::{EntityMap, MapEntities, MapEntitiesError},
+    prelude::*,
+};
+
+use crate::{components::velocity::Velocity, resources::game_tuning::GameTuning};
+
+use super::enemy_plugin::Enemy;
+use super::explosion_plugin::ExplosionInvoke;
+use super::game_plugin::{SimulationSet, SimulationStage};
+use super::movement_plugin::GameClock;
+use super::rollback_plugin::RollbackApp;
+
+#[derive(Clone, Copy)]
+pub struct HomingStats {
+    /// Maximum steering speed in radians per second.
+    pub turn_rate: f32,
+    /// Half-angle of the forward cone used for target acquisition, in radians.
+    pub cone: f32,
+    pub lifetime_s: f32,
+}
+
+#[derive(Component, Clone)]
+pub struct Homing {
+    pub stats: HomingStats,
+    pub target: Option<Entity>,
+    pub lifetime: Timer,
+}
+
+impl MapEntities for Homing {
+    fn map_entities(&mut self, entities: &EntityMap) -> Result<(), MapEntitiesError> {
+        self.target = self.target.map(|target| entities.get(target)).transpose()?;
+        Ok(())
+    }
+}
+
+impl From<HomingStats> for Homing {
+    fn from(stats: HomingStats) -> Self {
+        Self {
+            stats,
+            target: None,
+            lifetime: Timer::from_seconds(stats.lifetime_s, TimerMode::Once),
+        }
+    }
+}
+
+pub struct HomingPlugin;
+
+impl Plugin for HomingPlugin {
+    fn build(&self, app: &mut App) {
+        app.rollback_mapped_component::<Homing>()
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationSet::Homing
+                    .system_set()
+                    .with_system(homing_target_system)
+                    .with_system(homing_steer_system.after(homing_target_system))
+                    .with_system(homing_lifetime_system.after(homing_steer_system)),
+            );
+    }
+}
+
+fn homing_lifetime_system(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    mut query: Query<(Entity, &mut Homing, &Transform)>,
+) {
+    for (entity, mut homing, trans) in query.iter_mut() {
+        homing.lifetime.tick(clock.delta());
+        if homing.lifetime.just_finished() {
+            commands.entity(entity).despawn_recursive();
+            commands.spawn_empty().insert(ExplosionInvoke {
+                translation: trans.translation,
+            });
+        }
+    }
+}
+
+fn homing_target_system(
+    mut homing_query: Query<(&mut Homing, &Transform, &Velocity), Without<Enemy>>,
+    enemy_query: Query<(Entity, &Transform), With<Enemy>>,
+) {
+    for (mut homing, trans, velocity) in homing_query.iter_mut() {
+        if homing
+            .target
+            .is_some_and(|target| enemy_query.contains(target))
+        {
+            continue;
+        }
+        let forward = velocity.truncate().normalize_or_zero();
+        let position = trans.translation.truncate();
+        homing.target = enemy_query
+            .iter()
+            .filter_map(|(enemy, enemy_trans)| {
+                let to_enemy = enemy_trans.translation.truncate() - position;
+                match forward.angle_between(to_enemy).abs() <= homing.stats.cone {
+                    true => Some((enemy, to_enemy.length_squared())),
+                    false => None,
+                }
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(enemy, _)| enemy);
+    }
+}
+
+fn homing_steer_system(
+    tuning: Res<GameTuning>,
+    mut homing_query: Query<(&Homing, &mut Transform, &mut Velocity), Without<Enemy>>,
+    enemy_query: Query<&Transform, With<Enemy>>,
+) {
+    for (homing, mut trans, mut velocity) in homing_query.iter_mut() {
+        let target_trans = match homing.target.and_then(|t| enemy_query.get(t).ok()) {
+            Some(target_trans) => target_trans,
+            None => continue,
+        };
+        let current = velocity.truncate();
+        let desired = target_trans.translation.truncate() - trans.translation.truncate();
+        if current == Vec2::ZERO || desired == Vec2::ZERO {
+            continue;
+        }
+
+        let max_turn = homing.stats.turn_rate * tuning.time_step;
+        let turn = current.angle_between(desired).clamp(-max_turn, max_turn);
+        let steered = Vec2::from_angle(turn).rotate(current);
+        **velocity = steered.extend(0.);
+        trans.rotation = Quat::from_rotation_z((-steered.x).atan2(steered.y));
+    }
+}
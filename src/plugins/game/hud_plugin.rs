@@ -0,0 +1,248 @@
+use bevy::prelude::*;
+
+use crate::{
+    resources::{
+        fonts::Fonts,
+        game_tuning::GameTuning,
+        run_settings::{GameMode, RunSettings},
+        score::Score,
+    },
+    AppState,
+};
+
+use super::boss_plugin::Boss;
+use super::formation_plugin::FormationSchedule;
+use super::movement_plugin::GameClock;
+use super::pickup_plugin::PowerUps;
+use super::player_plugin::{Lives, Player, PlayerId, PlayerScores};
+use super::weapon_plugin::Weapon;
+
+const HUD_FONT_SIZE: f32 = 24.;
+const BOSS_BAR_SIZE: (f32, f32) = (400., 14.);
+
+#[derive(Component)]
+struct HudNode;
+
+#[derive(Component)]
+struct HudText;
+
+#[derive(Component)]
+struct BossBar;
+
+#[derive(Component)]
+struct BossBarFill;
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::Playing).with_system(setup_system))
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(hud_update_system)
+                    .with_system(boss_bar_update_system),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(cleanup_system));
+    }
+}
+
+fn cleanup_system(mut commands: Commands, query: Query<Entity, With<HudNode>>) {
+    for e in query.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
+
+fn setup_system(mut commands: Commands, fonts: Res<Fonts>) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(10.),
+                    top: Val::Px(10.),
+                    ..Default::default()
+                },
+                flex_direction: FlexDirection::Column,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(HudNode)
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_sections(Vec::new()))
+                .insert(HudText);
+        });
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(20.),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(100.), Val::Auto),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                display: Display::None,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(HudNode)
+        .insert(BossBar)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "BOSS",
+                TextStyle {
+                    font: fonts.bold.clone(),
+                    font_size: HUD_FONT_SIZE,
+                    color: Color::ORANGE_RED,
+                },
+            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(BOSS_BAR_SIZE.0), Val::Px(BOSS_BAR_SIZE.1)),
+                        ..Default::default()
+                    },
+                    background_color: Color::rgba(0., 0., 0., 0.6).into(),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                                ..Default::default()
+                            },
+                            background_color: Color::ORANGE_RED.into(),
+                            ..Default::default()
+                        })
+                        .insert(BossBarFill);
+                });
+        });
+}
+
+fn boss_bar_update_system(
+    boss_query: Query<&Boss>,
+    mut bar_query: Query<&mut Style, (With<BossBar>, Without<BossBarFill>)>,
+    mut fill_query: Query<&mut Style, With<BossBarFill>>,
+) {
+    let boss = boss_query.iter().next();
+    if let Ok(mut style) = bar_query.get_single_mut() {
+        style.display = match boss {
+            Some(_) => Display::Flex,
+            None => Display::None,
+        };
+    }
+    if let (Some(boss), Ok(mut style)) = (boss, fill_query.get_single_mut()) {
+        style.size.width = Val::Percent(boss.health_fraction() * 100.);
+    }
+}
+
+/// Time left in time attack, waves so far in wave survival, the rule changes of a daily challenge.
+fn objective_text(
+    settings: &RunSettings,
+    tuning: &GameTuning,
+    clock: &GameClock,
+    schedule: Option<&FormationSchedule>,
+) -> Option<String> {
+    match settings.mode {
+        GameMode::TimeAttack => {
+            let left = (tuning.modes.time_attack_s - clock.elapsed_seconds())
+                .max(0.)
+                .ceil() as u32;
+            Some(format!("Time: {}:{:02}\n", left / 60, left % 60))
+        }
+        GameMode::WaveSurvival => schedule.map(|schedule| {
+            format!(
+                "Wave: {}/{}\n",
+                schedule.spawned(),
+                schedule.limit().unwrap_or_default()
+            )
+        }),
+        GameMode::DailyChallenge => Some(format!("{}\n", settings.modifiers.labels().join(", "))),
+        GameMode::Endless | GameMode::Hardcore => None,
+    }
+}
+
+type HudPlayerQuery<'a> = (&'a PlayerId, &'a Lives, &'a PowerUps, &'a Weapon);
+
+fn hud_update_system(
+    fonts: Res<Fonts>,
+    score: Option<Res<Score>>,
+    (settings, tuning, clock, schedule): (
+        Res<RunSettings>,
+        Res<GameTuning>,
+        Res<GameClock>,
+        Option<Res<FormationSchedule>>,
+    ),
+    player_scores: Res<PlayerScores>,
+    player_query: Query<HudPlayerQuery, With<Player>>,
+    mut text_query: Query<&mut Text, With<HudText>>,
+) {
+    if let Ok(mut text) = text_query.get_single_mut() {
+        let style = TextStyle {
+            font: fonts.bold.clone(),
+            font_size: HUD_FONT_SIZE,
+            color: Color::WHITE,
+        };
+        let mut sections = Vec::new();
+        if let Some(score) = score {
+            sections.push(TextSection {
+                value: format!("Score: {}\n", **score),
+                style: style.clone(),
+            });
+        }
+        if let Some(value) = objective_text(&settings, &tuning, &clock, schedule.as_deref()) {
+            sections.push(TextSection {
+                value,
+                style: style.clone(),
+            });
+        }
+        let mut players = player_query.iter().collect::<Vec<_>>();
+        players.sort_by_key(|(id, ..)| **id);
+        for (id, lives, power_ups, weapon) in players {
+            // with company each line is tagged and tinted with its player
+            let (prefix, style) = match settings.players > 1 {
+                true => {
+                    let points = player_scores.get(id.index()).copied().unwrap_or_default();
+                    sections.push(TextSection {
+                        value: format!("{} Score: {}\n", id.label(), points),
+                        style: TextStyle {
+                            color: id.color(),
+                            ..style.clone()
+                        },
+                    });
+                    let style = TextStyle {
+                        color: id.color(),
+                        ..style.clone()
+                    };
+                    (format!("{} ", id.label()), style)
+                }
+                false => (String::new(), style.clone()),
+            };
+            sections.push(TextSection {
+                value: format!("{}Lives: {}\n", prefix, **lives),
+                style: style.clone(),
+            });
+            sections.push(TextSection {
+                value: format!("{}Weapon: {}\n", prefix, weapon.kind().label()),
+                style: style.clone(),
+            });
+            for active in power_ups.0.iter() {
+                let remaining = active.timer.duration() - active.timer.elapsed();
+                sections.push(TextSection {
+                    value: format!("{} {:.1}s\n", active.kind.label(), remaining.as_secs_f32()),
+                    style: TextStyle {
+                        color: active.kind.color(),
+                        ..style.clone()
+                    },
+                });
+            }
+        }
+        text.sections = sections;
+    }
+}
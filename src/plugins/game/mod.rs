@@ -1,7 +1,25 @@
 pub mod game_plugin;
+pub mod movement_plugin;
+pub mod replay_plugin;
+pub mod rollback_plugin;
+pub mod simulation;
 
-mod enemy_plugin;
-mod explosion_plugin;
-mod movement_plugin;
-mod pause_menu_plugin;
-mod player_plugin;
+pub mod beam_plugin;
+pub mod boss_plugin;
+pub mod bullet_emitter_plugin;
+pub mod daily_challenge_plugin;
+pub mod damage_feedback_plugin;
+pub mod director_plugin;
+pub mod enemy_ai_plugin;
+pub mod enemy_behavior_plugin;
+pub mod enemy_plugin;
+pub mod explosion_plugin;
+pub mod formation_plugin;
+pub mod game_mode_plugin;
+pub mod homing_plugin;
+pub mod hud_plugin;
+pub mod pause_menu_plugin;
+pub mod pickup_plugin;
+pub mod player_plugin;
+pub mod spawn_plugin;
+pub mod weapon_plugin;
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use bevy::prelude::*;
 
 use crate::{
@@ -6,19 +8,74 @@ use crate::{
         sizeable::Sizeable,
         velocity::{AngleVelocity, Velocity},
     },
+    resources::game_tuning::GameTuning,
     AppState, ViewportSize,
 };
-pub const TIME_STEP: f32 = 1. / 60.;
-pub const BASE_SPEED: f32 = 250.;
+
+use super::enemy_plugin::Enemy;
+use super::game_plugin::{SimulationSet, SimulationStage};
+use super::rollback_plugin::RollbackApp;
+
+/// Simulation time of the current run, advanced by one fixed step per update
+/// so runs don't depend on the frame rate.
+#[derive(Resource, Clone)]
+pub struct GameClock {
+    pub tick: u64,
+    /// Seconds of a single step, the tuned time step of the run.
+    pub step_s: f32,
+}
+
+impl Default for GameClock {
+    fn default() -> Self {
+        Self {
+            tick: 0,
+            step_s: GameTuning::default().time_step,
+        }
+    }
+}
+
+impl GameClock {
+    pub fn elapsed_seconds(&self) -> f32 {
+        self.tick as f32 * self.step_s
+    }
+
+    /// Duration of a single simulation step, for ticking timers.
+    pub fn delta(&self) -> Duration {
+        Duration::from_secs_f32(self.step_s)
+    }
+}
 
 pub struct MovementPlugin;
 
 impl Plugin for MovementPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(SystemSet::on_update(AppState::Playing).with_system(movement_system));
+        app.rollback_resource::<GameClock>()
+            .init_resource::<GameClock>()
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(setup_system))
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationSet::Clock
+                    .system_set()
+                    .with_system(game_clock_system),
+            )
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationSet::Movement
+                    .system_set()
+                    .with_system(movement_system),
+            );
     }
 }
 
+fn setup_system(mut clock: ResMut<GameClock>, tuning: Res<GameTuning>) {
+    clock.tick = 0;
+    clock.step_s = tuning.time_step;
+}
+
+fn game_clock_system(mut clock: ResMut<GameClock>) {
+    clock.tick += 1;
+}
+
 type MovementQuery<'a> = (
     Entity,
     &'a Velocity,
@@ -26,22 +83,27 @@ type MovementQuery<'a> = (
     &'a mut Transform,
     &'a Movable,
     &'a Sizeable,
+    Option<&'a Enemy>,
 );
 
 fn movement_system(
     mut commands: Commands,
-    viewport_size: Res<ViewportSize>,
+    (viewport_size, tuning): (Res<ViewportSize>, Res<GameTuning>),
     mut query: Query<MovementQuery>,
 ) {
-    for (e, vel, ang_vel, mut trans, movable, sizeable) in query.iter_mut() {
+    for (e, vel, ang_vel, mut trans, movable, sizeable, enemy) in query.iter_mut() {
+        let speed = match enemy {
+            Some(_) => tuning.base_speed * tuning.enemy_speed_scale,
+            None => tuning.base_speed,
+        };
         let velocity = match ang_vel {
             Some(ang_vel) => {
-                trans.rotate_z(ang_vel.0 * TIME_STEP * 5.);
+                trans.rotate_z(ang_vel.0 * tuning.time_step * 5.);
                 *vel.rotate(trans.rotation)
             }
             None => **vel,
-        } * TIME_STEP
-            * BASE_SPEED;
+        } * tuning.time_step
+            * speed;
 
         trans.translation += velocity;
 
@@ -49,7 +111,7 @@ fn movement_system(
             MovementViewportBehavior::None => (),
             MovementViewportBehavior::DespawnOnLeave => {
                 if is_outside_viewport(&trans, sizeable, &viewport_size) {
-                    commands.entity(e).despawn();
+                    commands.entity(e).despawn_recursive();
                 }
             }
             MovementViewportBehavior::Contain => {
@@ -2,7 +2,10 @@ use bevy::prelude::*;
 
 use crate::{
     components::root_node::RootNode,
-    plugins::{delayed_state_switch_plugin::StateActionButton, ui_interaction_plugin::UiButton},
+    plugins::{
+        delayed_state_switch_plugin::StateActionButton, net_plugin::NetSession,
+        ui_interaction_plugin::UiButton,
+    },
     resources::fonts::Fonts,
     AppState,
 };
@@ -136,6 +139,7 @@ fn setup_bindings_system(mut commands: Commands) {
 
 fn playing_active_keyboard_events_system(
     mut app_state: ResMut<State<AppState>>,
+    net: Option<Res<NetSession>>,
     bindings: Res<PauseMenuKeyBindings>,
     mut key_input: ResMut<Input<KeyCode>>,
 ) {
@@ -144,7 +148,9 @@ fn playing_active_keyboard_events_system(
             continue;
         }
         match app_state.current() {
-            AppState::MainMenu => (),
+            AppState::MainMenu | AppState::GameOver | AppState::HighScores | AppState::Lobby => (),
+            // the other player's run goes on
+            AppState::Playing if net.is_some() => (),
             AppState::Playing => app_state.push(AppState::Paused).unwrap(),
             AppState::Paused => app_state.pop().unwrap(),
         }
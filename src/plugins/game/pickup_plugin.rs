@@ -0,0 +1,324 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    components::{
+        movable::{Movable, MovementOptions, MovementViewportBehavior},
+        sizeable::Sizeable,
+        velocity::Velocity,
+    },
+    resources::{
+        fonts::Fonts,
+        game_rng::{GameRng, RngStream},
+        run_settings::{GameMode, Modifiers, RunSettings},
+    },
+    AppState,
+};
+
+use super::enemy_plugin::collide_entities;
+use super::game_plugin::{SimulationSet, SimulationStage};
+use super::movement_plugin::GameClock;
+use super::player_plugin::{Lives, Player};
+use super::rollback_plugin::RollbackApp;
+use super::weapon_plugin::{Weapon, WeaponKind};
+
+const PICKUP_SIZE: f32 = 32.;
+const PICKUP_LIFETIME_S: f32 = 8.;
+const PICKUP_BLINK_S: f32 = 2.;
+const PICKUP_DRIFT_SPEED: f32 = 0.3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PickupKind {
+    RapidFire,
+    SpreadShot,
+    Shield,
+    ExtraLife,
+    SpeedBoost,
+    Weapon(WeaponKind),
+}
+
+impl PickupKind {
+    /// Duration of the effect in seconds, `None` for instant effects.
+    pub fn duration(&self) -> Option<f32> {
+        match self {
+            PickupKind::RapidFire => Some(8.),
+            PickupKind::SpreadShot => Some(8.),
+            PickupKind::Shield => Some(6.),
+            PickupKind::ExtraLife => None,
+            PickupKind::SpeedBoost => Some(6.),
+            PickupKind::Weapon(_) => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PickupKind::RapidFire => "Rapid fire",
+            PickupKind::SpreadShot => "Spread shot",
+            PickupKind::Shield => "Shield",
+            PickupKind::ExtraLife => "Extra life",
+            PickupKind::SpeedBoost => "Speed boost",
+            PickupKind::Weapon(kind) => kind.label(),
+        }
+    }
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            PickupKind::RapidFire => "R",
+            PickupKind::SpreadShot => "S",
+            PickupKind::Shield => "D",
+            PickupKind::ExtraLife => "+",
+            PickupKind::SpeedBoost => "V",
+            PickupKind::Weapon(_) => "W",
+        }
+    }
+
+    /// Hardcore runs leave out everything that saves a life, runs without pickups all of them.
+    fn allowed_in(&self, settings: &RunSettings) -> bool {
+        if settings.modifiers.contains(Modifiers::NO_PICKUPS) {
+            return false;
+        }
+        settings.mode != GameMode::Hardcore
+            || !matches!(self, PickupKind::ExtraLife | PickupKind::Shield)
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            PickupKind::RapidFire => Color::ORANGE,
+            PickupKind::SpreadShot => Color::YELLOW_GREEN,
+            PickupKind::Shield => Color::CYAN,
+            PickupKind::ExtraLife => Color::PINK,
+            PickupKind::SpeedBoost => Color::GOLD,
+            PickupKind::Weapon(_) => Color::SILVER,
+        }
+    }
+}
+
+/// Weighted list of possible drops, `None` entries stand for "drop nothing".
+#[derive(Component, Clone, Deref)]
+pub struct DropTable(pub Vec<(Option<PickupKind>, u32)>);
+
+impl Default for DropTable {
+    fn default() -> Self {
+        Self(vec![
+            (None, 80),
+            (Some(PickupKind::RapidFire), 5),
+            (Some(PickupKind::SpreadShot), 5),
+            (Some(PickupKind::Shield), 4),
+            (Some(PickupKind::SpeedBoost), 4),
+            (Some(PickupKind::ExtraLife), 2),
+            (Some(PickupKind::Weapon(WeaponKind::Spread)), 1),
+            (Some(PickupKind::Weapon(WeaponKind::Burst)), 1),
+            (Some(PickupKind::Weapon(WeaponKind::ChargeShot)), 1),
+            (Some(PickupKind::Weapon(WeaponKind::Laser)), 1),
+            (Some(PickupKind::Weapon(WeaponKind::HomingMissile)), 1),
+        ])
+    }
+}
+
+impl DropTable {
+    pub fn roll(&self, rand: &mut impl Rng) -> Option<PickupKind> {
+        let total: u32 = self.iter().map(|(_, weight)| weight).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut roll = rand.gen_range(0..total);
+        for (kind, weight) in self.iter() {
+            if roll < *weight {
+                return *kind;
+            }
+            roll -= weight;
+        }
+        None
+    }
+}
+
+#[derive(Component, Clone)]
+pub struct PickupDropInvoke {
+    pub translation: Vec3,
+    pub table: DropTable,
+}
+
+#[derive(Component, Clone)]
+pub struct Pickup {
+    pub kind: PickupKind,
+    pub lifetime: Timer,
+}
+
+#[derive(Clone)]
+pub struct ActivePowerUp {
+    pub kind: PickupKind,
+    pub timer: Timer,
+}
+
+#[derive(Component, Clone, Default)]
+pub struct PowerUps(pub Vec<ActivePowerUp>);
+
+impl PowerUps {
+    pub fn is_active(&self, kind: PickupKind) -> bool {
+        self.0.iter().any(|p| p.kind == kind)
+    }
+
+    pub fn activate(&mut self, kind: PickupKind, duration_s: f32) {
+        let timer = Timer::from_seconds(duration_s, TimerMode::Once);
+        match self.0.iter_mut().find(|p| p.kind == kind) {
+            Some(active) => active.timer = timer,
+            None => self.0.push(ActivePowerUp { kind, timer }),
+        }
+    }
+
+    pub fn deactivate(&mut self, kind: PickupKind) {
+        self.0.retain(|p| p.kind != kind);
+    }
+}
+
+pub struct PickupPlugin;
+
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut App) {
+        app.rollback_component::<DropTable>()
+            .rollback_component::<PickupDropInvoke>()
+            .rollback_component::<Pickup>()
+            .rollback_component::<PowerUps>()
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationSet::Pickups
+                    .system_set()
+                    .with_system(pickup_collect_system)
+                    .with_system(pickup_lifetime_system.after(pickup_collect_system))
+                    .with_system(power_up_tick_system.after(pickup_lifetime_system))
+                    .with_system(pickup_spawn_system.after(power_up_tick_system)),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(cleanup_system));
+    }
+}
+
+fn cleanup_system(
+    mut commands: Commands,
+    invoke_query: Query<Entity, With<PickupDropInvoke>>,
+    pickup_query: Query<Entity, With<Pickup>>,
+) {
+    invoke_query.iter().for_each(|e| {
+        commands.entity(e).despawn();
+    });
+    pickup_query.iter().for_each(|e| {
+        commands.entity(e).despawn_recursive();
+    });
+}
+
+fn pickup_spawn_system(
+    mut commands: Commands,
+    (fonts, settings): (Res<Fonts>, Res<RunSettings>),
+    mut rng: ResMut<GameRng>,
+    query: Query<(Entity, &PickupDropInvoke)>,
+) {
+    let rand = rng.stream(RngStream::Drops);
+    for (invoke, drop) in query.iter() {
+        commands.entity(invoke).despawn();
+        let kind = match drop.table.roll(rand) {
+            Some(kind) if kind.allowed_in(&settings) => kind,
+            _ => continue,
+        };
+
+        let drift = Vec2::new(
+            rand.gen_range(-PICKUP_DRIFT_SPEED..PICKUP_DRIFT_SPEED),
+            -PICKUP_DRIFT_SPEED,
+        );
+        commands
+            .spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: kind.color(),
+                    custom_size: Some(Vec2::splat(PICKUP_SIZE)),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(Vec3 {
+                    z: 5.,
+                    ..drop.translation
+                }),
+                ..Default::default()
+            })
+            .insert(Pickup {
+                kind,
+                lifetime: Timer::from_seconds(PICKUP_LIFETIME_S, TimerMode::Once),
+            })
+            .insert(Velocity::from(drift))
+            .insert(Sizeable(Vec2::splat(PICKUP_SIZE)))
+            .insert(Movable(MovementOptions {
+                viewport_behavior: MovementViewportBehavior::DespawnOnLeave,
+            }))
+            .with_children(|parent| {
+                parent.spawn(Text2dBundle {
+                    text: Text::from_section(
+                        kind.symbol(),
+                        TextStyle {
+                            font: fonts.bold.clone(),
+                            font_size: PICKUP_SIZE * 0.8,
+                            color: Color::BLACK,
+                        },
+                    )
+                    .with_alignment(TextAlignment::CENTER),
+                    transform: Transform::from_xyz(0., 0., 1.),
+                    ..Default::default()
+                });
+            });
+    }
+}
+
+fn pickup_lifetime_system(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    mut query: Query<(Entity, &mut Pickup, &mut Visibility)>,
+) {
+    for (entity, mut pickup, mut visibility) in query.iter_mut() {
+        pickup.lifetime.tick(clock.delta());
+        if pickup.lifetime.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let remaining = pickup.lifetime.duration() - pickup.lifetime.elapsed();
+        if remaining.as_secs_f32() < PICKUP_BLINK_S {
+            visibility.is_visible = (remaining.as_millis() / 150) % 2 == 0;
+        }
+    }
+}
+
+type PickupCollectorQuery<'a> = (
+    &'a Sizeable,
+    &'a Transform,
+    &'a mut PowerUps,
+    &'a mut Lives,
+    &'a mut Weapon,
+);
+
+fn pickup_collect_system(
+    mut commands: Commands,
+    mut player_query: Query<PickupCollectorQuery, With<Player>>,
+    pickup_query: Query<(Entity, &Sizeable, &Transform, &Pickup)>,
+) {
+    let mut collected = Vec::new();
+    for (player_size, player_trans, mut power_ups, mut lives, mut weapon) in player_query.iter_mut()
+    {
+        for (entity, pickup_size, pickup_trans, pickup) in pickup_query.iter() {
+            if collected.contains(&entity)
+                || !collide_entities(pickup_trans, pickup_size, player_trans, player_size)
+            {
+                continue;
+            }
+            collected.push(entity);
+            match (pickup.kind, pickup.kind.duration()) {
+                (PickupKind::Weapon(kind), _) => *weapon = kind.build(),
+                (_, Some(duration)) => power_ups.activate(pickup.kind, duration),
+                (_, None) => **lives += 1,
+            }
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn power_up_tick_system(clock: Res<GameClock>, mut query: Query<&mut PowerUps>) {
+    for mut power_ups in query.iter_mut() {
+        for active in power_ups.0.iter_mut() {
+            active.timer.tick(clock.delta());
+        }
+        power_ups.0.retain(|p| !p.timer.finished());
+    }
+}
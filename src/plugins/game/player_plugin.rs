@@ -4,27 +4,31 @@ use crate::{
         sizeable::Sizeable,
         velocity::{AngleVelocity, Velocity},
     },
-    plugins::delayed_state_switch_plugin::StateSetCommand,
-    resources::textures::Textures,
-    AppState, ViewportSize, SPRITE_SCALE,
+    plugins::net_plugin::NetSession,
+    resources::{
+        game_tuning::GameTuning,
+        player_input::{InputActions, PlayerInputs, ScriptedInput, MAX_PLAYERS},
+        run_settings::{Modifiers, RunSettings},
+        textures::Textures,
+    },
+    AppState, ViewportSize,
+};
+use bevy::{
+    ecs::entity::{EntityMap, MapEntities, MapEntitiesError},
+    prelude::*,
 };
-use bevy::prelude::*;
 
 use super::explosion_plugin::ExplosionInvoke;
+use super::game_mode_plugin::{end_run, RunOutcome};
+use super::game_plugin::{SimulationSet, SimulationStage};
+use super::movement_plugin::GameClock;
+use super::pickup_plugin::{PickupKind, PowerUps};
+use super::replay_plugin::ReplayPlayback;
+use super::rollback_plugin::RollbackApp;
+use super::weapon_plugin::{Weapon, WeaponTrigger};
 
-#[derive(PartialEq)]
-enum PlayerKey {
-    Up,
-    Down,
-    Left,
-    Right,
-    RotateCw,
-    RotateCcw,
-    Fire,
-}
-
-#[derive(Resource)]
-struct PlayerKeyBinding {
+#[derive(Clone)]
+pub struct PlayerKeyBinding {
     up: Vec<KeyCode>,
     down: Vec<KeyCode>,
     left: Vec<KeyCode>,
@@ -32,47 +36,146 @@ struct PlayerKeyBinding {
     rotate_cw: Vec<KeyCode>,
     rotate_ccw: Vec<KeyCode>,
     fire: Vec<KeyCode>,
+    next_weapon: Vec<KeyCode>,
 }
 
-const FIRE_COOLDOWN_S: f32 = 0.25;
+/// Controls of every player, the keyboard layout of their seat if there is one
+/// and the gamepad connected in their place.
+#[derive(Resource)]
+pub struct PlayerBindings(Vec<Option<PlayerKeyBinding>>);
 
-#[derive(Resource, Deref, DerefMut)]
-struct PlayerLastFire(pub f32);
+const SPEED_BOOST_MULTIPLIER: f32 = 1.6;
+const RESPAWN_INVULNERABILITY_S: f32 = 2.;
+/// Distance between the ships at the start of a co-op run.
+const PLAYER_SPACING_PX: f32 = 120.;
+/// Stick tilt counted as a held direction.
+const STICK_DEADZONE: f32 = 0.5;
+const PLAYER_COLORS: [Color; MAX_PLAYERS] = [
+    Color::WHITE,
+    Color::rgb(0.55, 0.85, 1.),
+    Color::rgb(0.6, 1., 0.6),
+    Color::rgb(1., 0.75, 0.45),
+];
 
-#[derive(Component)]
+#[derive(Component, Clone)]
 pub struct Player;
 
-#[derive(Component)]
-pub struct HitPlayer;
+/// Which of the local players a ship belongs to, from 0 in seat order.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PlayerId(pub u8);
+
+impl PlayerId {
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
 
-#[derive(Component)]
-pub struct PlayerProjectile;
+    pub fn label(&self) -> String {
+        format!("P{}", self.0 + 1)
+    }
 
-impl PlayerKeyBinding {
-    fn pressed(&self, key: Res<Input<KeyCode>>) -> Vec<PlayerKey> {
-        let mut result = Vec::new();
-        if self.up.iter().any(|k| key.pressed(*k)) {
-            result.push(PlayerKey::Up);
-        }
-        if self.down.iter().any(|k| key.pressed(*k)) {
-            result.push(PlayerKey::Down);
-        }
-        if self.left.iter().any(|k| key.pressed(*k)) {
-            result.push(PlayerKey::Left);
-        }
-        if self.right.iter().any(|k| key.pressed(*k)) {
-            result.push(PlayerKey::Right);
-        }
-        if self.rotate_cw.iter().any(|k| key.pressed(*k)) {
-            result.push(PlayerKey::RotateCw);
+    /// Tint of the ship, the first player keeps the sprite's own colors.
+    pub fn color(&self) -> Color {
+        PLAYER_COLORS[self.index() % MAX_PLAYERS]
+    }
+}
+
+/// Score of each player's own kills, the run's score also counts the boss bonuses.
+#[derive(Resource, Clone, Default, Deref, DerefMut)]
+pub struct PlayerScores(pub Vec<u32>);
+
+#[derive(Component, Clone)]
+pub struct HitPlayer {
+    pub player: Entity,
+    pub cause: HitCause,
+}
+
+impl MapEntities for HitPlayer {
+    fn map_entities(&mut self, entities: &EntityMap) -> Result<(), MapEntitiesError> {
+        self.player = entities.get(self.player)?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum HitCause {
+    /// Rammed by an enemy of the archetype.
+    Collision(String),
+    Projectile,
+}
+
+impl std::fmt::Display for HitCause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HitCause::Collision(archetype) => write!(f, "collision:{}", archetype),
+            HitCause::Projectile => write!(f, "projectile"),
         }
-        if self.rotate_ccw.iter().any(|k| key.pressed(*k)) {
-            result.push(PlayerKey::RotateCcw);
+    }
+}
+
+/// Sent when a hit gets through to the player, breaking a shield or taking a life.
+pub struct PlayerDamaged {
+    pub cause: HitCause,
+}
+
+/// What took the last life in the current run.
+#[derive(Resource, Clone)]
+pub struct DeathCause(pub HitCause);
+
+#[derive(Component, Clone, Deref, DerefMut)]
+pub struct Lives(pub u32);
+
+#[derive(Component, Clone, Deref, DerefMut)]
+pub struct Invulnerable(pub Timer);
+
+#[derive(Component, Clone)]
+pub struct PlayerProjectile(pub PlayerId);
+
+impl PlayerKeyBinding {
+    fn actions(&self, key: &Input<KeyCode>) -> InputActions {
+        let bindings = [
+            (&self.up, InputActions::UP),
+            (&self.down, InputActions::DOWN),
+            (&self.left, InputActions::LEFT),
+            (&self.right, InputActions::RIGHT),
+            (&self.rotate_cw, InputActions::ROTATE_CW),
+            (&self.rotate_ccw, InputActions::ROTATE_CCW),
+            (&self.fire, InputActions::FIRE),
+            (&self.next_weapon, InputActions::NEXT_WEAPON),
+        ];
+        bindings
+            .iter()
+            .filter(|(keys, _)| keys.iter().any(|k| key.pressed(*k)))
+            .fold(InputActions::empty(), |actions, (_, action)| {
+                actions | *action
+            })
+    }
+
+    /// Left side of a shared keyboard.
+    fn wasd() -> Self {
+        Self {
+            up: vec![KeyCode::W],
+            down: vec![KeyCode::S],
+            left: vec![KeyCode::A],
+            right: vec![KeyCode::D],
+            rotate_cw: vec![KeyCode::E],
+            rotate_ccw: vec![KeyCode::Q],
+            fire: vec![KeyCode::Space],
+            next_weapon: vec![KeyCode::Tab],
         }
-        if self.fire.iter().any(|k| key.pressed(*k)) {
-            result.push(PlayerKey::Fire);
+    }
+
+    /// Right side of a shared keyboard.
+    fn arrows() -> Self {
+        Self {
+            up: vec![KeyCode::Up],
+            down: vec![KeyCode::Down],
+            left: vec![KeyCode::Left],
+            right: vec![KeyCode::Right],
+            rotate_cw: vec![KeyCode::Period],
+            rotate_ccw: vec![KeyCode::Comma],
+            fire: vec![KeyCode::RControl],
+            next_weapon: vec![KeyCode::RShift],
         }
-        result
     }
 }
 
@@ -86,20 +189,154 @@ impl Default for PlayerKeyBinding {
             rotate_cw: vec![KeyCode::E],
             rotate_ccw: vec![KeyCode::Q],
             fire: vec![KeyCode::Space],
+            next_weapon: vec![KeyCode::Tab],
         }
     }
 }
 
+impl PlayerBindings {
+    /// A lone player gets the whole keyboard, two share it and any others
+    /// play with gamepads only.
+    pub fn new(players: usize) -> Self {
+        let keys = match players {
+            1 => vec![Some(PlayerKeyBinding::default())],
+            _ => vec![
+                Some(PlayerKeyBinding::wasd()),
+                Some(PlayerKeyBinding::arrows()),
+            ],
+        };
+        Self(
+            (0..players)
+                .map(|i| keys.get(i).cloned().flatten())
+                .collect(),
+        )
+    }
+
+    /// Actions of every player, the n-th connected gamepad plays for the n-th player.
+    pub fn actions(
+        &self,
+        keys: &Input<KeyCode>,
+        gamepads: &Gamepads,
+        (buttons, axes): (&Input<GamepadButton>, &Axis<GamepadAxis>),
+    ) -> Vec<InputActions> {
+        let mut connected: Vec<_> = gamepads.iter().collect();
+        connected.sort_by_key(|gamepad| gamepad.id);
+        self.0
+            .iter()
+            .enumerate()
+            .map(|(i, binding)| {
+                let keyboard = binding
+                    .as_ref()
+                    .map_or(InputActions::empty(), |binding| binding.actions(keys));
+                let gamepad = connected.get(i).map_or(InputActions::empty(), |gamepad| {
+                    gamepad_actions(*gamepad, buttons, axes)
+                });
+                keyboard | gamepad
+            })
+            .collect()
+    }
+}
+
+fn gamepad_actions(
+    gamepad: Gamepad,
+    buttons: &Input<GamepadButton>,
+    axes: &Axis<GamepadAxis>,
+) -> InputActions {
+    let button = |kind| buttons.pressed(GamepadButton::new(gamepad, kind));
+    let axis = |kind| axes.get(GamepadAxis::new(gamepad, kind)).unwrap_or(0.);
+    let stick_x = axis(GamepadAxisType::LeftStickX);
+    let stick_y = axis(GamepadAxisType::LeftStickY);
+    [
+        (
+            button(GamepadButtonType::DPadUp) || stick_y > STICK_DEADZONE,
+            InputActions::UP,
+        ),
+        (
+            button(GamepadButtonType::DPadDown) || stick_y < -STICK_DEADZONE,
+            InputActions::DOWN,
+        ),
+        (
+            button(GamepadButtonType::DPadLeft) || stick_x < -STICK_DEADZONE,
+            InputActions::LEFT,
+        ),
+        (
+            button(GamepadButtonType::DPadRight) || stick_x > STICK_DEADZONE,
+            InputActions::RIGHT,
+        ),
+        (
+            button(GamepadButtonType::RightTrigger),
+            InputActions::ROTATE_CW,
+        ),
+        (
+            button(GamepadButtonType::LeftTrigger),
+            InputActions::ROTATE_CCW,
+        ),
+        (button(GamepadButtonType::South), InputActions::FIRE),
+        (button(GamepadButtonType::West), InputActions::NEXT_WEAPON),
+    ]
+    .into_iter()
+    .filter(|(held, _)| *held)
+    .fold(InputActions::empty(), |actions, (_, action)| {
+        actions | action
+    })
+}
+
+/// The player closest to `position`, the one enemies go after.
+pub fn nearest_player<'a>(
+    players: impl IntoIterator<Item = &'a Transform>,
+    position: Vec3,
+) -> Option<&'a Transform> {
+    players.into_iter().min_by(|a, b| {
+        let a = a
+            .translation
+            .truncate()
+            .distance_squared(position.truncate());
+        let b = b
+            .translation
+            .truncate()
+            .distance_squared(position.truncate());
+        a.total_cmp(&b)
+    })
+}
+
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(SystemSet::on_enter(AppState::Playing).with_system(player_spawn_system))
-            .add_system_set(
-                SystemSet::on_update(AppState::Playing)
-                    .with_system(player_fire_system)
+        app.rollback_component::<Player>()
+            .rollback_component::<PlayerId>()
+            .rollback_mapped_component::<HitPlayer>()
+            .rollback_component::<Lives>()
+            .rollback_component::<Invulnerable>()
+            .rollback_component::<PlayerProjectile>()
+            .rollback_resource::<PlayerScores>()
+            .rollback_resource::<PlayerInputs>()
+            .rollback_resource::<DeathCause>()
+            .add_event::<PlayerDamaged>()
+            .init_resource::<PlayerInputs>()
+            .init_resource::<PlayerScores>()
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(player_spawn_system))
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationSet::PlayerControl
+                    .system_set()
+                    .with_system(player_input_system)
+                    .with_system(player_movement_input_system.after(player_input_system))
+                    .with_system(player_weapon_switch_system.after(player_movement_input_system))
+                    .with_system(player_fire_system.after(player_weapon_switch_system)),
+            )
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationSet::PlayerHits
+                    .system_set()
                     .with_system(player_on_hit_system)
-                    .with_system(player_keyboard_event_system),
+                    .with_system(player_invulnerability_system.after(player_on_hit_system)),
+            )
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationSet::ShieldTint
+                    .system_set()
+                    .with_system(player_shield_tint_system),
             )
             .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(cleanup_system));
     }
@@ -110,7 +347,6 @@ fn cleanup_system(
     player_query: Query<Entity, With<Player>>,
     hit_query: Query<Entity, With<HitPlayer>>,
     proj_query: Query<Entity, With<PlayerProjectile>>,
-    mut last_fire: ResMut<PlayerLastFire>,
 ) {
     player_query
         .iter()
@@ -119,125 +355,144 @@ fn cleanup_system(
         .for_each(|e| {
             commands.entity(e).despawn();
         });
-    last_fire.0 = 0.;
 }
 
 fn player_spawn_system(
     mut commands: Commands,
     viewport_size: Res<ViewportSize>,
-    textures: Res<Textures>,
+    (textures, tuning, settings): (Res<Textures>, Res<GameTuning>, Res<RunSettings>),
 ) {
-    commands.insert_resource(PlayerKeyBinding::default());
-    commands.insert_resource(PlayerLastFire(0.));
-
-    let ytrans = -viewport_size.h / 2. + textures.player.size_px.y * SPRITE_SCALE / 2.;
-    commands
-        .spawn(SpriteBundle {
-            texture: textures.player.image.clone(),
-            transform: Transform {
-                translation: Vec3 {
-                    x: 0.,
-                    y: ytrans,
-                    z: 10.,
+    let players = (settings.players as usize).clamp(1, MAX_PLAYERS);
+    commands.insert_resource(PlayerBindings::new(players));
+    commands.insert_resource(PlayerInputs::new(players));
+    commands.insert_resource(PlayerScores(vec![0; players]));
+
+    let ytrans = -viewport_size.h / 2. + textures.player.size_px.y * textures.sprite_scale / 2.;
+    for i in 0..players {
+        let id = PlayerId(i as u8);
+        let xtrans = (i as f32 - (players - 1) as f32 / 2.) * PLAYER_SPACING_PX;
+        commands
+            .spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: id.color(),
+                    ..Default::default()
+                },
+                texture: textures.player.image.clone(),
+                transform: Transform {
+                    translation: Vec3 {
+                        x: xtrans,
+                        y: ytrans,
+                        z: 10.,
+                    },
+                    scale: Vec3::new(textures.sprite_scale, textures.sprite_scale, 1.),
+                    ..Default::default()
                 },
-                scale: Vec3::new(SPRITE_SCALE, SPRITE_SCALE, 1.),
                 ..Default::default()
-            },
-            ..Default::default()
-        })
-        .insert(Player {})
-        .insert(Velocity::from(Vec2::new(0., 0.)))
-        .insert(AngleVelocity(0.))
-        .insert(Sizeable(textures.player.size_px))
-        .insert(Movable(MovementOptions {
-            viewport_behavior: MovementViewportBehavior::Contain,
-        }));
+            })
+            .insert(Player {})
+            .insert(id)
+            .insert(Velocity::from(Vec2::new(0., 0.)))
+            .insert(AngleVelocity(0.))
+            .insert(Sizeable(textures.player.size_px))
+            .insert(Lives(tuning.player_lives))
+            .insert(PowerUps::default())
+            .insert(Weapon::default())
+            .insert(WeaponTrigger::default())
+            .insert(Movable(MovementOptions {
+                viewport_behavior: MovementViewportBehavior::Contain,
+            }));
+    }
+    commands.remove_resource::<DeathCause>();
+}
+
+/// Captures this step's actions from the keyboard and gamepads, replays feed
+/// them from the recording instead.
+fn player_input_system(
+    (keys, bindings): (Res<Input<KeyCode>>, Res<PlayerBindings>),
+    (gamepads, buttons, axes): (
+        Res<Gamepads>,
+        Res<Input<GamepadButton>>,
+        Res<Axis<GamepadAxis>>,
+    ),
+    scripted: Option<Res<ScriptedInput>>,
+    (playback, net): (Option<Res<ReplayPlayback>>, Option<Res<NetSession>>),
+    mut input: ResMut<PlayerInputs>,
+) {
+    if playback.is_some() || net.is_some() {
+        return;
+    }
+    match scripted {
+        Some(scripted) => input.push(&scripted.0),
+        None => input.push(&bindings.actions(&keys, &gamepads, (&buttons, &axes))),
+    }
 }
 
 fn player_fire_system(
-    mut commands: Commands,
-    key: Res<Input<KeyCode>>,
-    bindings: Res<PlayerKeyBinding>,
-    textures: Res<Textures>,
-    time: Res<Time>,
-    mut last_fire: ResMut<PlayerLastFire>,
-    query: Query<(&Transform, &Sizeable), With<Player>>,
+    input: Res<PlayerInputs>,
+    mut query: Query<(&PlayerId, &mut WeaponTrigger), With<Player>>,
 ) {
-    if let Ok((player_trans, player_size)) = query.get_single() {
-        let pressed = bindings.pressed(key);
-        if pressed.contains(&PlayerKey::Fire) {
-            if (time.elapsed_seconds() - **last_fire) < FIRE_COOLDOWN_S {
-                return;
-            }
-
-            let offset = *Velocity(Vec3 {
-                x: player_size.x / 2. * player_trans.scale.x - 5.,
-                y: player_size.x / 4. * player_trans.scale.x,
-                z: 0.,
-            })
-            .rotate(player_trans.rotation);
-            let mut spawn_fire = |offset: Vec3| {
-                let trans = player_trans.translation + offset;
-                commands
-                    .spawn(SpriteBundle {
-                        texture: textures.player_fire.image.clone(),
-                        transform: Transform {
-                            translation: trans,
-                            scale: player_trans.scale,
-                            rotation: player_trans.rotation,
-                        },
-                        ..Default::default()
-                    })
-                    .insert(PlayerProjectile)
-                    .insert(Velocity(Vec3::new(0., 1.5, 0.)).rotate(player_trans.rotation))
-                    .insert(Sizeable(textures.player_fire.size_px))
-                    .insert(Movable(MovementOptions {
-                        viewport_behavior: MovementViewportBehavior::DespawnOnLeave,
-                    }));
-            };
-            spawn_fire(offset);
+    for (id, mut trigger) in query.iter_mut() {
+        let input = input.get(id.index());
+        trigger.held = input.pressed(InputActions::FIRE);
+        trigger.released = input.just_released(InputActions::FIRE);
+    }
+}
 
-            let offset = *Velocity(Vec3 {
-                x: -(player_size.x / 2. * player_trans.scale.x - 5.),
-                y: player_size.x / 4. * player_trans.scale.x,
-                z: 0.,
-            })
-            .rotate(player_trans.rotation);
-            spawn_fire(offset);
-            last_fire.0 = time.elapsed_seconds();
+fn player_weapon_switch_system(
+    input: Res<PlayerInputs>,
+    mut query: Query<(&PlayerId, &mut Weapon), With<Player>>,
+) {
+    for (id, mut weapon) in query.iter_mut() {
+        if input
+            .get(id.index())
+            .just_pressed(InputActions::NEXT_WEAPON)
+        {
+            *weapon = weapon.kind().next().build();
         }
     }
 }
 
-fn player_keyboard_event_system(
-    key: Res<Input<KeyCode>>,
-    bindings: Res<PlayerKeyBinding>,
-    mut query: Query<(&mut Velocity, &mut AngleVelocity), With<Player>>,
+type PlayerMovementQuery<'a> = (
+    &'a PlayerId,
+    &'a mut Velocity,
+    &'a mut AngleVelocity,
+    &'a PowerUps,
+);
+
+fn player_movement_input_system(
+    input: Res<PlayerInputs>,
+    settings: Res<RunSettings>,
+    mut query: Query<PlayerMovementQuery, With<Player>>,
 ) {
-    if let Ok((mut velocity, mut angle_velocity)) = query.get_single_mut() {
-        let pressed_keys = &bindings.pressed(key);
+    for (id, mut velocity, mut angle_velocity, power_ups) in query.iter_mut() {
+        let input = input.get(id.index());
+        // mirrored controls swap what the keys do, the recorded actions stay as pressed
+        let mirrored = settings.modifiers.contains(Modifiers::MIRRORED_CONTROLS);
+        let pressed = |action| match (mirrored, action) {
+            (true, InputActions::LEFT) => input.pressed(InputActions::RIGHT),
+            (true, InputActions::RIGHT) => input.pressed(InputActions::LEFT),
+            _ => input.pressed(action),
+        };
+        let speed = match power_ups.is_active(PickupKind::SpeedBoost) {
+            true => SPEED_BOOST_MULTIPLIER,
+            false => 1.0,
+        };
 
-        velocity.x = match (
-            pressed_keys.contains(&PlayerKey::Left),
-            pressed_keys.contains(&PlayerKey::Right),
-        ) {
-            (true, false) => -1.0,
-            (false, true) => 1.0,
+        velocity.x = match (pressed(InputActions::LEFT), pressed(InputActions::RIGHT)) {
+            (true, false) => -speed,
+            (false, true) => speed,
             _ => 0.0,
         };
 
-        velocity.y = match (
-            pressed_keys.contains(&PlayerKey::Down),
-            pressed_keys.contains(&PlayerKey::Up),
-        ) {
-            (true, false) => -1.0,
-            (false, true) => 1.0,
+        velocity.y = match (pressed(InputActions::DOWN), pressed(InputActions::UP)) {
+            (true, false) => -speed,
+            (false, true) => speed,
             _ => 0.0,
         };
 
         angle_velocity.0 = match (
-            pressed_keys.contains(&PlayerKey::RotateCw),
-            pressed_keys.contains(&PlayerKey::RotateCcw),
+            pressed(InputActions::ROTATE_CW),
+            pressed(InputActions::ROTATE_CCW),
         ) {
             (true, false) => -1.0,
             (false, true) => 1.0,
@@ -246,23 +501,100 @@ fn player_keyboard_event_system(
     }
 }
 
+type PlayerHitQuery<'a> = (
+    Entity,
+    &'a Transform,
+    &'a mut Lives,
+    &'a mut PowerUps,
+    Option<&'a Invulnerable>,
+);
+
+/// Takes at most one hit per player and step. The run is lost once the last
+/// player standing loses their last life.
 fn player_on_hit_system(
     mut commands: Commands,
-    hit_query: Query<Entity, With<HitPlayer>>,
-    player_query: Query<(Entity, &Transform), With<Player>>,
+    mut damaged: EventWriter<PlayerDamaged>,
+    outcome: Option<Res<RunOutcome>>,
+    hit_query: Query<(Entity, &HitPlayer)>,
+    mut player_query: Query<PlayerHitQuery, With<Player>>,
+) {
+    let mut hit_players = Vec::new();
+    let mut last_death = None;
+    for (hit, hit_player) in hit_query.iter() {
+        commands.entity(hit).despawn();
+        if hit_players.contains(&hit_player.player) {
+            continue;
+        }
+        hit_players.push(hit_player.player);
+        let (player, player_trans, mut lives, mut power_ups, invulnerable) =
+            match player_query.get_mut(hit_player.player) {
+                Ok(player) => player,
+                Err(_) => continue,
+            };
+        // nothing touches the players once the run has been won or timed out
+        if invulnerable.is_some() || outcome.is_some() || **lives == 0 {
+            continue;
+        }
+        damaged.send(PlayerDamaged {
+            cause: hit_player.cause.clone(),
+        });
+        if power_ups.is_active(PickupKind::Shield) {
+            power_ups.deactivate(PickupKind::Shield);
+            commands
+                .entity(player)
+                .insert(Invulnerable(Timer::from_seconds(
+                    RESPAWN_INVULNERABILITY_S / 2.,
+                    TimerMode::Once,
+                )));
+            continue;
+        }
+
+        commands.spawn_empty().insert(ExplosionInvoke {
+            translation: player_trans.translation,
+        });
+        **lives -= 1;
+        if **lives > 0 {
+            commands
+                .entity(player)
+                .insert(Invulnerable(Timer::from_seconds(
+                    RESPAWN_INVULNERABILITY_S,
+                    TimerMode::Once,
+                )));
+            continue;
+        }
+        commands.entity(player).despawn();
+        last_death = Some(hit_player.cause.clone());
+    }
+
+    if let Some(cause) = last_death {
+        if player_query.iter().all(|(_, _, lives, ..)| **lives == 0) {
+            commands.insert_resource(DeathCause(cause));
+            end_run(&mut commands, RunOutcome::Defeat);
+        }
+    }
+}
+
+fn player_invulnerability_system(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    mut query: Query<(Entity, &mut Invulnerable, &mut Visibility), With<Player>>,
 ) {
-    // immortality on double hit yay!
-    if let Ok(hit) = hit_query.get_single() {
-        if let Ok((player, player_trans)) = player_query.get_single() {
-            commands.spawn_empty().insert(ExplosionInvoke {
-                translation: player_trans.translation,
-            });
-            commands.spawn_empty().insert(StateSetCommand {
-                target: AppState::MainMenu,
-                delay: Timer::from_seconds(2., TimerMode::Once),
-            });
-            commands.entity(player).despawn();
-            commands.entity(hit).despawn();
+    for (player, mut invulnerable, mut visibility) in query.iter_mut() {
+        invulnerable.tick(clock.delta());
+        if invulnerable.finished() {
+            visibility.is_visible = true;
+            commands.entity(player).remove::<Invulnerable>();
+            continue;
         }
+        visibility.is_visible = (invulnerable.elapsed().as_millis() / 100) % 2 == 0;
+    }
+}
+
+fn player_shield_tint_system(mut query: Query<(&mut Sprite, &PowerUps, &PlayerId)>) {
+    for (mut sprite, power_ups, id) in query.iter_mut() {
+        sprite.color = match power_ups.is_active(PickupKind::Shield) {
+            true => PickupKind::Shield.color(),
+            false => id.color(),
+        };
     }
 }
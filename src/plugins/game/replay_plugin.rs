@@ -0,0 +1,364 @@
+use std::time::Duration;
+
+use bevy::{ecs::schedule::ShouldRun, input::InputSystem, prelude::*};
+
+use crate::{
+    plugins::{
+        delayed_state_switch_plugin::StateSetCommand, net_plugin::NetSession,
+        ui_interaction_plugin::UiButton,
+    },
+    resources::{
+        fonts::Fonts,
+        game_rng::GameRng,
+        player_input::PlayerInputs,
+        replay::{Replay, LAST_REPLAY_PATH},
+        run_settings::RunSettings,
+    },
+    AppState, Headless,
+};
+
+use super::game_plugin::{SimulationSet, SimulationStage};
+use super::movement_plugin::GameClock;
+
+const PLAYBACK_SPEEDS: [(KeyCode, u32); 3] =
+    [(KeyCode::Key1, 1), (KeyCode::Key2, 2), (KeyCode::Key3, 4)];
+const PLAYBACK_PAUSE_KEY: KeyCode = KeyCode::Space;
+const SEEK_BACK_KEY: KeyCode = KeyCode::Left;
+const SEEK_FORWARD_KEY: KeyCode = KeyCode::Right;
+const SEEK_STEP_S: f32 = 5.;
+/// Simulation steps run per frame while seeking.
+const SEEK_STEPS_PER_FRAME: u64 = 240;
+const REPLAY_HUD_FONT_SIZE: f32 = 20.;
+
+/// Present while a recorded run is being played back instead of read from the keyboard.
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    pub replay: Replay,
+    /// Settings of the player's own runs, put back once the playback ends.
+    restore_settings: RunSettings,
+    speed: u32,
+    seek_to: Option<u64>,
+    /// Set while the run restarts to seek backwards.
+    restarting: bool,
+}
+
+impl ReplayPlayback {
+    /// Switches the run settings to the recorded ones for the playback.
+    pub fn new(replay: Replay, settings: &mut RunSettings) -> Self {
+        let restore_settings = std::mem::replace(settings, replay.settings);
+        Self {
+            replay,
+            restore_settings,
+            speed: 1,
+            seek_to: None,
+            restarting: false,
+        }
+    }
+
+    fn len(&self) -> u64 {
+        self.replay.ticks() as u64
+    }
+}
+
+/// Inputs of the run being played, kept around to watch it from the game over screen.
+#[derive(Resource, Default)]
+pub struct LastRun(pub Replay);
+
+#[derive(Clone)]
+pub enum ReplaySource {
+    LastRun,
+    File(String),
+}
+
+/// Button starting the playback of a replay.
+#[derive(Component, Clone)]
+pub struct ReplayButton(pub ReplaySource);
+
+#[derive(Component)]
+struct ReplayHud;
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LastRun>()
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                replay_controls_system.after(InputSystem),
+            )
+            .add_system(replay_button_system)
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(setup_system))
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationSet::ReplayInput
+                    .system_set()
+                    .with_system(replay_input_system),
+            )
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationSet::Record
+                    .system_set()
+                    .with_system(replay_record_system),
+            )
+            .add_system_set(SystemSet::on_update(AppState::Playing).with_system(replay_hud_system))
+            .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(cleanup_system))
+            .add_system_set(
+                SystemSet::on_enter(AppState::GameOver).with_system(playback_end_system),
+            )
+            .add_system_set(
+                SystemSet::on_enter(AppState::MainMenu).with_system(playback_end_system),
+            );
+    }
+}
+
+/// Run criteria of the simulation systems, repeating them for fast forward and seeking.
+pub fn simulation_steps_criteria(
+    mut steps_left: Local<u64>,
+    state: Res<State<AppState>>,
+    clock: Res<GameClock>,
+    playback: Option<ResMut<ReplayPlayback>>,
+    net: Option<Res<NetSession>>,
+) -> ShouldRun {
+    if *steps_left == 0 {
+        if *state.current() != AppState::Playing {
+            return ShouldRun::No;
+        }
+        *steps_left = match (playback, net) {
+            // online runs step as far as the inputs of both players allow
+            (_, Some(net)) => net.steps(),
+            (Some(mut playback), _) if !playback.restarting => match playback.seek_to {
+                Some(target) if target > clock.tick => {
+                    (target - clock.tick).min(SEEK_STEPS_PER_FRAME)
+                }
+                Some(_) => {
+                    playback.seek_to = None;
+                    playback.speed as u64
+                }
+                None => playback.speed as u64,
+            },
+            _ => 1,
+        };
+        if *steps_left == 0 {
+            return ShouldRun::No;
+        }
+    }
+    *steps_left -= 1;
+    match *steps_left {
+        0 => ShouldRun::Yes,
+        _ => ShouldRun::YesAndCheckAgain,
+    }
+}
+
+fn setup_system(
+    mut commands: Commands,
+    fonts: Res<Fonts>,
+    mut last_run: ResMut<LastRun>,
+    settings: Res<RunSettings>,
+    playback: Option<ResMut<ReplayPlayback>>,
+) {
+    let mut playback = match playback {
+        Some(playback) => playback,
+        None => {
+            // the seed is only known once the run's rng is in place
+            last_run.0 = Replay::new(0, *settings);
+            return;
+        }
+    };
+    playback.restarting = false;
+    commands
+        .spawn(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    right: Val::Px(10.),
+                    top: Val::Px(10.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::from_sections([
+                TextSection::new(
+                    "",
+                    TextStyle {
+                        font: fonts.bold.clone(),
+                        font_size: REPLAY_HUD_FONT_SIZE,
+                        color: Color::ORANGE,
+                    },
+                ),
+                TextSection::new(
+                    "\nSpace pause, 1/2/3 speed, Left/Right seek",
+                    TextStyle {
+                        font: fonts.italic.clone(),
+                        font_size: REPLAY_HUD_FONT_SIZE * 0.8,
+                        color: Color::WHITE,
+                    },
+                ),
+            ])
+            .with_alignment(TextAlignment::TOP_RIGHT),
+            ..Default::default()
+        })
+        .insert(ReplayHud);
+}
+
+fn cleanup_system(
+    mut commands: Commands,
+    mut last_run: ResMut<LastRun>,
+    rng: Res<GameRng>,
+    (playback, headless): (Option<Res<ReplayPlayback>>, Option<Res<Headless>>),
+    hud_query: Query<Entity, With<ReplayHud>>,
+) {
+    for e in hud_query.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+    if playback.is_some() || headless.is_some() || last_run.0.inputs.is_empty() {
+        return;
+    }
+    last_run.0.seed = rng.seed();
+    if let Err(err) = last_run.0.save(LAST_REPLAY_PATH) {
+        warn!("Failed to save the replay: {}", err);
+    }
+}
+
+fn playback_end_system(
+    mut commands: Commands,
+    mut settings: ResMut<RunSettings>,
+    playback: Option<Res<ReplayPlayback>>,
+) {
+    if let Some(playback) = playback {
+        *settings = playback.restore_settings;
+        commands.remove_resource::<ReplayPlayback>();
+    }
+}
+
+fn replay_record_system(
+    clock: Res<GameClock>,
+    input: Res<PlayerInputs>,
+    playback: Option<Res<ReplayPlayback>>,
+    mut last_run: ResMut<LastRun>,
+) {
+    if playback.is_none() {
+        let actions = input.current();
+        // a rewound run records its steps again
+        let recorded = (clock.tick as usize - 1) * actions.len();
+        last_run.0.inputs.truncate(recorded);
+        last_run.0.inputs.extend(actions);
+    }
+}
+
+fn replay_input_system(
+    clock: Res<GameClock>,
+    playback: Option<Res<ReplayPlayback>>,
+    mut state: ResMut<State<AppState>>,
+    mut input: ResMut<PlayerInputs>,
+) {
+    let playback = match playback {
+        Some(playback) => playback,
+        None => return,
+    };
+    match playback.replay.step(clock.tick as usize - 1) {
+        Some(actions) => input.push(actions),
+        // the recording is over, so is the run
+        None => {
+            let _ = state.set(AppState::GameOver);
+        }
+    }
+}
+
+fn replay_controls_system(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    clock: Res<GameClock>,
+    mut state: ResMut<State<AppState>>,
+    playback: Option<ResMut<ReplayPlayback>>,
+    delayed_query: Query<Entity, With<StateSetCommand>>,
+) {
+    let mut playback = match playback {
+        Some(playback) => playback,
+        None => return,
+    };
+    let playing = *state.current() == AppState::Playing;
+    if keys.just_pressed(PLAYBACK_PAUSE_KEY) {
+        let _ = match playing {
+            true => state.push(AppState::Paused),
+            false if *state.current() == AppState::Paused => state.pop(),
+            false => Ok(()),
+        };
+    }
+    for (key, speed) in PLAYBACK_SPEEDS {
+        if keys.just_pressed(key) {
+            playback.speed = speed;
+        }
+    }
+    if !playing || playback.restarting {
+        return;
+    }
+
+    let seek_step = (SEEK_STEP_S / clock.step_s) as u64;
+    let current = playback.seek_to.unwrap_or(clock.tick);
+    if keys.just_pressed(SEEK_FORWARD_KEY) {
+        playback.seek_to = Some((current + seek_step).min(playback.len()));
+    }
+    if keys.just_pressed(SEEK_BACK_KEY) {
+        // the simulation only runs forward, so rewinding replays the run from its start
+        playback.seek_to = Some(current.saturating_sub(seek_step));
+        playback.restarting = state.restart().is_ok();
+        for e in delayed_query.iter() {
+            commands.entity(e).despawn();
+        }
+    }
+}
+
+fn replay_hud_system(
+    clock: Res<GameClock>,
+    playback: Option<Res<ReplayPlayback>>,
+    mut query: Query<&mut Text, With<ReplayHud>>,
+) {
+    let playback = match playback {
+        Some(playback) => playback,
+        None => return,
+    };
+    let format_time = |tick: u64| {
+        let time = Duration::from_secs_f32(tick as f32 * clock.step_s).as_secs();
+        format!("{:02}:{:02}", time / 60, time % 60)
+    };
+    for mut text in query.iter_mut() {
+        text.sections[0].value = format!(
+            "REPLAY {}x  {} / {}",
+            playback.speed,
+            format_time(clock.tick),
+            format_time(playback.len())
+        );
+    }
+}
+
+fn replay_button_system(
+    mut commands: Commands,
+    last_run: Res<LastRun>,
+    mut settings: ResMut<RunSettings>,
+    mut query: Query<(&Interaction, &ReplayButton, &mut UiButton), Changed<Interaction>>,
+) {
+    for (interaction, button, mut ui_button) in query.iter_mut() {
+        if *interaction == Interaction::Clicked {
+            ui_button.clicked = true;
+            continue;
+        }
+        if !ui_button.clicked {
+            continue;
+        }
+        ui_button.clicked = false;
+        let replay = match &button.0 {
+            ReplaySource::LastRun => Ok(last_run.0.clone()),
+            ReplaySource::File(path) => Replay::load(path),
+        };
+        match replay {
+            Ok(replay) => {
+                commands.insert_resource(ReplayPlayback::new(replay, &mut settings));
+                commands.spawn_empty().insert(StateSetCommand {
+                    target: AppState::Playing,
+                    delay: Timer::new(Duration::from_millis(30), TimerMode::Once),
+                });
+            }
+            Err(err) => warn!("Failed to load the replay: {}", err),
+        }
+    }
+}
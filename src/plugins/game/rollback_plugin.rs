@@ -0,0 +1,355 @@
+use std::{any::Any, collections::HashSet};
+
+use bevy::{
+    ecs::{
+        component::ComponentId,
+        entity::{EntityMap, MapEntities},
+    },
+    prelude::*,
+    text::{Text2dBounds, Text2dSize},
+};
+
+use crate::{
+    components::{
+        damage::Damage,
+        health::Health,
+        movable::Movable,
+        sizeable::Sizeable,
+        velocity::{AngleVelocity, Velocity},
+    },
+    resources::{game_rng::GameRng, score::Score},
+};
+
+use super::{
+    bullet_emitter_plugin::EnemyProjectile,
+    enemy_plugin::Enemy,
+    movement_plugin::GameClock,
+    pickup_plugin::Pickup,
+    player_plugin::{Lives, Player, PlayerProjectile, PlayerScores},
+};
+
+type Saved = Box<dyn Any + Send + Sync>;
+
+/// Marks entities while a snapshot is put back, keeping them out of the
+/// archetypes the game uses until all their components are in place.
+#[derive(Component)]
+struct Restoring;
+
+struct ComponentEntry {
+    id: ComponentId,
+    /// Whether having the component makes an entity part of the run.
+    marks: bool,
+    save: fn(&World, &[Entity]) -> Saved,
+    load: fn(&mut World, &Saved, &EntityMap),
+}
+
+struct ResourceEntry {
+    save: fn(&World) -> Saved,
+    load: fn(&mut World, &Saved),
+}
+
+/// Components and resources making up the state of a run, registered by the
+/// plugins owning them through `RollbackApp`.
+#[derive(Resource, Default)]
+pub struct RollbackRegistry {
+    components: Vec<ComponentEntry>,
+    resources: Vec<ResourceEntry>,
+}
+
+/// Registration of the state a run is rewound with.
+pub trait RollbackApp {
+    /// Saves `T` with the run, entities having it are part of the run.
+    fn rollback_component<T: Component + Clone>(&mut self) -> &mut Self;
+    /// Saves `T` on entities that are part of the run for their other components.
+    fn rollback_attached_component<T: Component + Clone>(&mut self) -> &mut Self;
+    /// Saves `T` like `rollback_component`, pointing the entities it refers to
+    /// at wherever they are restored.
+    fn rollback_mapped_component<T: Component + Clone + MapEntities>(&mut self) -> &mut Self;
+    /// Saves `R` with the run, including whether it's there at all.
+    fn rollback_resource<R: Resource + Clone>(&mut self) -> &mut Self;
+}
+
+impl RollbackApp for App {
+    fn rollback_component<T: Component + Clone>(&mut self) -> &mut Self {
+        add_component::<T>(self, true, load_component::<T>)
+    }
+
+    fn rollback_attached_component<T: Component + Clone>(&mut self) -> &mut Self {
+        add_component::<T>(self, false, load_component::<T>)
+    }
+
+    fn rollback_mapped_component<T: Component + Clone + MapEntities>(&mut self) -> &mut Self {
+        add_component::<T>(self, true, load_mapped_component::<T>)
+    }
+
+    fn rollback_resource<R: Resource + Clone>(&mut self) -> &mut Self {
+        self.init_resource::<RollbackRegistry>();
+        let mut registry = self.world.resource_mut::<RollbackRegistry>();
+        registry.resources.push(ResourceEntry {
+            save: save_resource::<R>,
+            load: load_resource::<R>,
+        });
+        self
+    }
+}
+
+fn add_component<T: Component + Clone>(
+    app: &mut App,
+    marks: bool,
+    load: fn(&mut World, &Saved, &EntityMap),
+) -> &mut App {
+    app.init_resource::<RollbackRegistry>();
+    let id = app.world.init_component::<T>();
+    let mut registry = app.world.resource_mut::<RollbackRegistry>();
+    if registry.components.iter().all(|entry| entry.id != id) {
+        registry.components.push(ComponentEntry {
+            id,
+            marks,
+            save: save_component::<T>,
+            load,
+        });
+    }
+    app
+}
+
+fn save_component<T: Component + Clone>(world: &World, entities: &[Entity]) -> Saved {
+    let saved: Vec<(Entity, T)> = entities
+        .iter()
+        .filter_map(|&e| world.get::<T>(e).map(|component| (e, component.clone())))
+        .collect();
+    Box::new(saved)
+}
+
+fn load_component<T: Component + Clone>(world: &mut World, saved: &Saved, entities: &EntityMap) {
+    let saved = saved.downcast_ref::<Vec<(Entity, T)>>().unwrap();
+    for (e, component) in saved {
+        world
+            .entity_mut(entities.get(*e).unwrap())
+            .insert(component.clone());
+    }
+}
+
+fn load_mapped_component<T: Component + Clone + MapEntities>(
+    world: &mut World,
+    saved: &Saved,
+    entities: &EntityMap,
+) {
+    let saved = saved.downcast_ref::<Vec<(Entity, T)>>().unwrap();
+    for (e, component) in saved {
+        let mut component = component.clone();
+        // entities gone before the snapshot keep their stale ids
+        let _ = component.map_entities(entities);
+        world
+            .entity_mut(entities.get(*e).unwrap())
+            .insert(component);
+    }
+}
+
+fn save_resource<R: Resource + Clone>(world: &World) -> Saved {
+    Box::new(world.get_resource::<R>().cloned())
+}
+
+fn load_resource<R: Resource + Clone>(world: &mut World, saved: &Saved) {
+    match saved.downcast_ref::<Option<R>>().unwrap() {
+        Some(resource) => world.insert_resource(resource.clone()),
+        None => {
+            world.remove_resource::<R>();
+        }
+    }
+}
+
+/// The state of a run at one point, to rewind it there.
+pub struct WorldSnapshot {
+    entities: Vec<Entity>,
+    components: Vec<Saved>,
+    hierarchy: Vec<(Entity, Vec<Entity>)>,
+    resources: Vec<Saved>,
+}
+
+/// Saves the registered resources and the entities having a registered
+/// component, along with their descendants.
+pub fn save_world(world: &mut World) -> WorldSnapshot {
+    world.resource_scope(|world, registry: Mut<RollbackRegistry>| {
+        let entities = members(world, &registry);
+        WorldSnapshot {
+            components: registry
+                .components
+                .iter()
+                .map(|entry| (entry.save)(world, &entities))
+                .collect(),
+            hierarchy: entities
+                .iter()
+                .filter_map(|&e| world.get::<Children>(e).map(|c| (e, c.to_vec())))
+                .collect(),
+            resources: registry
+                .resources
+                .iter()
+                .map(|entry| (entry.save)(world))
+                .collect(),
+            entities,
+        }
+    })
+}
+
+/// Puts the world back to the snapshot, in the same storage order so the run
+/// carries on exactly as it did from there. Entities keep their ids unless
+/// something spawned since took them over.
+pub fn load_world(world: &mut World, snapshot: &WorldSnapshot) {
+    world.resource_scope(|world, registry: Mut<RollbackRegistry>| {
+        for e in members(world, &registry) {
+            world.despawn(e);
+        }
+        let mut entities = EntityMap::default();
+        for &e in &snapshot.entities {
+            let restored = world
+                .get_or_spawn(e)
+                .map(|entity| entity.id())
+                // an effect spawned since took the id over
+                .unwrap_or_else(|| world.spawn_empty().id());
+            world.entity_mut(restored).insert(Restoring);
+            entities.insert(e, restored);
+        }
+        for (entry, saved) in registry.components.iter().zip(&snapshot.components) {
+            (entry.load)(world, saved, &entities);
+        }
+        for (parent, children) in &snapshot.hierarchy {
+            let children: Vec<Entity> = children
+                .iter()
+                .map(|&child| entities.get(child).unwrap())
+                .collect();
+            world
+                .entity_mut(entities.get(*parent).unwrap())
+                .push_children(&children);
+        }
+        // entities move to their archetypes in the order they were saved in
+        for &e in &snapshot.entities {
+            world
+                .entity_mut(entities.get(e).unwrap())
+                .remove::<Restoring>();
+        }
+        for (entry, saved) in registry.resources.iter().zip(&snapshot.resources) {
+            (entry.load)(world, saved);
+        }
+    });
+}
+
+/// Entities of the run in storage order.
+fn members(world: &World, registry: &RollbackRegistry) -> Vec<Entity> {
+    let mut marked: HashSet<Entity> = HashSet::new();
+    let mut pending: Vec<Entity> = world
+        .archetypes()
+        .iter()
+        .filter(|archetype| {
+            registry
+                .components
+                .iter()
+                .any(|entry| entry.marks && archetype.contains(entry.id))
+        })
+        .flat_map(|archetype| archetype.entities().iter().map(|e| e.entity()))
+        .collect();
+    while let Some(e) = pending.pop() {
+        if marked.insert(e) {
+            if let Some(children) = world.get::<Children>(e) {
+                pending.extend(children.iter());
+            }
+        }
+    }
+    world
+        .archetypes()
+        .iter()
+        .flat_map(|archetype| archetype.entities().iter().map(|e| e.entity()))
+        .filter(|e| marked.contains(e))
+        .collect()
+}
+
+/// Hash of what the players see of the run, equal on every peer simulating
+/// the same run. Entity order doesn't matter.
+pub fn world_checksum(world: &mut World) -> u64 {
+    let mut query = world.query_filtered::<(&Transform, Option<&Health>, Option<&Lives>), Or<(
+        With<Player>,
+        With<Enemy>,
+        With<PlayerProjectile>,
+        With<EnemyProjectile>,
+        With<Pickup>,
+    )>>();
+    let mut sum = 0u64;
+    for (transform, health, lives) in query.iter(world) {
+        let mut hash = Fnv::default();
+        hash.write_f32(transform.translation.x);
+        hash.write_f32(transform.translation.y);
+        hash.write_f32(transform.rotation.z);
+        hash.write_f32(transform.rotation.w);
+        hash.write_f32(health.map_or(0., |health| health.current));
+        hash.write_u64(lives.map_or(0, |lives| lives.0 as u64));
+        sum = sum.wrapping_add(hash.0);
+    }
+
+    let mut hash = Fnv::default();
+    hash.write_u64(sum);
+    hash.write_u64(world.resource::<GameClock>().tick);
+    hash.write_u64(
+        world
+            .get_resource::<Score>()
+            .map_or(0, |score| score.0 as u64),
+    );
+    for score in &world.resource::<PlayerScores>().0 {
+        hash.write_u64(*score as u64);
+    }
+    for position in world.resource::<GameRng>().positions() {
+        hash.write_u64(position as u64);
+        hash.write_u64((position >> 64) as u64);
+    }
+    hash.0
+}
+
+/// 64 bit FNV-1a, stable across builds and platforms unlike the std hasher.
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_f32(&mut self, value: f32) {
+        self.write(&value.to_bits().to_le_bytes());
+    }
+}
+
+/// Rewinding of runs: registers the shared gameplay components and the
+/// engine ones gameplay entities carry.
+pub struct RollbackPlugin;
+
+impl Plugin for RollbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.rollback_component::<Health>()
+            .rollback_component::<Damage>()
+            .rollback_component::<Movable>()
+            .rollback_component::<Sizeable>()
+            .rollback_component::<Velocity>()
+            .rollback_component::<AngleVelocity>()
+            .rollback_attached_component::<Transform>()
+            .rollback_attached_component::<GlobalTransform>()
+            .rollback_attached_component::<Visibility>()
+            .rollback_attached_component::<ComputedVisibility>()
+            .rollback_attached_component::<Sprite>()
+            .rollback_attached_component::<Handle<Image>>()
+            .rollback_attached_component::<TextureAtlasSprite>()
+            .rollback_attached_component::<Handle<TextureAtlas>>()
+            .rollback_attached_component::<Text>()
+            .rollback_attached_component::<Text2dSize>()
+            .rollback_attached_component::<Text2dBounds>();
+    }
+}
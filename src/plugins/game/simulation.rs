@@ -0,0 +1,286 @@
+use bevy::{
+    ecs::{query::ReadOnlyWorldQuery, system::CommandQueue},
+    prelude::*,
+};
+
+use crate::{
+    build_headless_app,
+    components::velocity::{AngleVelocity, Velocity},
+    net::{SessionStart, Transport},
+    plugins::net_plugin::NetSession,
+    resources::{
+        enemy_archetypes::EnemyArchetypes,
+        game_tuning::GameTuning,
+        launch_options::LaunchOptions,
+        player_input::{InputActions, ScriptedInput},
+        run_settings::RunSettings,
+        score::Score,
+        textures::Textures,
+    },
+    AppState,
+};
+
+use super::bullet_emitter_plugin::EnemyProjectile;
+use super::enemy_plugin::{spawn_enemy, Enemy};
+use super::game_mode_plugin::RunOutcome;
+use super::movement_plugin::GameClock;
+use super::pickup_plugin::Pickup;
+use super::player_plugin::{DeathCause, Lives, Player, PlayerId, PlayerProjectile, PlayerScores};
+use super::rollback_plugin::{load_world, save_world, world_checksum, WorldSnapshot};
+
+/// Position and velocity of an entity, in pixels and pixels per second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EntityState {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    /// Counterclockwise, in radians, zero facing up.
+    pub rotation: f32,
+}
+
+/// Everything an automated player gets to see of a single simulation step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Observation {
+    pub tick: u64,
+    /// The first player still flying.
+    pub player: Option<EntityState>,
+    pub players: Vec<(PlayerId, EntityState)>,
+    pub enemies: Vec<EntityState>,
+    pub player_projectiles: Vec<EntityState>,
+    pub enemy_projectiles: Vec<EntityState>,
+    pub pickups: Vec<EntityState>,
+    pub score: u32,
+    pub lives: u32,
+}
+
+type EntityStateQuery<'a> = (
+    &'a Transform,
+    Option<&'a Velocity>,
+    Option<&'a AngleVelocity>,
+    Option<&'a Enemy>,
+);
+
+/// A headless run stepped one fixed tick at a time, driven by scripted inputs.
+pub struct Simulation {
+    app: App,
+}
+
+impl Simulation {
+    /// Starts a run from the seed, the first step is already taken.
+    pub fn new(seed: u64) -> Self {
+        Self::with_settings(seed, RunSettings::default())
+    }
+
+    pub fn with_settings(seed: u64, settings: RunSettings) -> Self {
+        Self::with_options(LaunchOptions {
+            seed: Some(seed),
+            run: settings,
+            ..Default::default()
+        })
+    }
+
+    /// Starts a run the way the command line would, for a seed or tuning of its own.
+    pub fn with_options(options: LaunchOptions) -> Self {
+        let mut app = build_headless_app(options);
+        app.init_resource::<ScriptedInput>();
+        app.update();
+        Self { app }
+    }
+
+    /// Starts the online run agreed on with a peer. Each step is a frame of
+    /// its own: it may run several simulation steps after a rollback, or none
+    /// while waiting for the peer's inputs. The actions are the local player's.
+    pub fn online(
+        options: LaunchOptions,
+        transport: Box<dyn Transport>,
+        start: SessionStart,
+    ) -> Self {
+        let mut app = build_headless_app(options);
+        let mut settings = *app.world.resource::<RunSettings>();
+        let session = NetSession::new(transport, start, &mut settings);
+        app.insert_resource(settings)
+            .insert_resource(session)
+            .init_resource::<ScriptedInput>();
+        app.update();
+        Self { app }
+    }
+
+    /// The session of an online run, `None` offline or once the run is over.
+    pub fn net_session(&self) -> Option<&NetSession> {
+        self.app.world.get_resource::<NetSession>()
+    }
+
+    pub fn step(&mut self, actions: InputActions) {
+        self.step_players(&[actions]);
+    }
+
+    /// Steps with the actions of each player in turn, the missing ones idle.
+    pub fn step_players(&mut self, actions: &[InputActions]) {
+        self.app.world.resource_mut::<ScriptedInput>().0 = actions.to_vec();
+        self.app.update();
+    }
+
+    pub fn run(&mut self, ticks: u64, actions: InputActions) {
+        for _ in 0..ticks {
+            self.step(actions);
+        }
+    }
+
+    /// Holds the key for the next step, for the controls outside of the player actions.
+    pub fn press_key(&mut self, key: KeyCode) {
+        self.app.world.resource_mut::<Input<KeyCode>>().press(key);
+    }
+
+    pub fn state(&self) -> AppState {
+        *self.app.world.resource::<State<AppState>>().current()
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.app.world.resource::<GameClock>().tick
+    }
+
+    pub fn score(&self) -> u32 {
+        self.app
+            .world
+            .get_resource::<Score>()
+            .map_or(0, |score| **score)
+    }
+
+    /// Lives left to all the players together.
+    pub fn lives(&mut self) -> u32 {
+        self.app
+            .world
+            .query_filtered::<&Lives, With<Player>>()
+            .iter(&self.app.world)
+            .map(|lives| **lives)
+            .sum()
+    }
+
+    /// Points scored by each player, the run score also counts the rest.
+    pub fn player_scores(&self) -> Vec<u32> {
+        self.app
+            .world
+            .get_resource::<PlayerScores>()
+            .map_or_else(Vec::new, |scores| scores.0.clone())
+    }
+
+    /// What took the last life, `None` while the player is alive.
+    pub fn death_cause(&self) -> Option<String> {
+        self.app
+            .world
+            .get_resource::<DeathCause>()
+            .map(|cause| cause.0.to_string())
+    }
+
+    /// How the run ended, `None` while it goes on.
+    pub fn outcome(&self) -> Option<RunOutcome> {
+        self.app.world.get_resource::<RunOutcome>().copied()
+    }
+
+    pub fn observe(&mut self) -> Observation {
+        let mut players = self
+            .app
+            .world
+            .query::<(Entity, &PlayerId)>()
+            .iter(&self.app.world)
+            .map(|(entity, id)| (entity, *id))
+            .collect::<Vec<_>>();
+        players.sort_by_key(|(_, id)| *id);
+        let players = players
+            .into_iter()
+            .filter_map(|(entity, id)| Some((id, self.entity_state(entity)?)))
+            .collect::<Vec<_>>();
+        Observation {
+            tick: self.tick(),
+            player: players.first().map(|(_, state)| *state),
+            players,
+            enemies: self.entity_states::<With<Enemy>>(),
+            player_projectiles: self.entity_states::<With<PlayerProjectile>>(),
+            enemy_projectiles: self.entity_states::<With<EnemyProjectile>>(),
+            pickups: self.entity_states::<With<Pickup>>(),
+            score: self.score(),
+            lives: self.lives(),
+        }
+    }
+
+    /// Places an enemy right away, skipping the spawn telegraph. `false` if there is no such archetype.
+    pub fn spawn_enemy(&mut self, archetype: &str, position: Vec2) -> bool {
+        let world = &mut self.app.world;
+        let mut queue = CommandQueue::default();
+        let archetype = match world.resource::<EnemyArchetypes>().get(archetype) {
+            Some(archetype) => archetype,
+            None => return false,
+        };
+        let mut commands = Commands::new(&mut queue, world);
+        spawn_enemy(
+            &mut commands,
+            world.resource::<Textures>(),
+            archetype,
+            position.extend(10.),
+        );
+        queue.apply(world);
+        true
+    }
+
+    /// The state of the run as it is now, to go back to with `load`.
+    pub fn save(&mut self) -> WorldSnapshot {
+        save_world(&mut self.app.world)
+    }
+
+    pub fn load(&mut self, snapshot: &WorldSnapshot) {
+        load_world(&mut self.app.world, snapshot);
+    }
+
+    /// Hash of the run's state, equal for runs that went the same way.
+    pub fn checksum(&mut self) -> u64 {
+        world_checksum(&mut self.app.world)
+    }
+
+    pub fn app_mut(&mut self) -> &mut App {
+        &mut self.app
+    }
+
+    fn entity_states<F: ReadOnlyWorldQuery>(&mut self) -> Vec<EntityState> {
+        let tuning = *self.app.world.resource::<GameTuning>();
+        self.app
+            .world
+            .query_filtered::<EntityStateQuery, F>()
+            .iter(&self.app.world)
+            .map(|item| to_entity_state(&tuning, item))
+            .collect()
+    }
+
+    fn entity_state(&mut self, entity: Entity) -> Option<EntityState> {
+        let tuning = *self.app.world.resource::<GameTuning>();
+        self.app
+            .world
+            .query::<EntityStateQuery>()
+            .get(&self.app.world, entity)
+            .ok()
+            .map(|item| to_entity_state(&tuning, item))
+    }
+}
+
+fn to_entity_state(
+    tuning: &GameTuning,
+    (trans, velocity, angle_velocity, enemy): (
+        &Transform,
+        Option<&Velocity>,
+        Option<&AngleVelocity>,
+        Option<&Enemy>,
+    ),
+) -> EntityState {
+    let speed = match enemy {
+        Some(_) => tuning.base_speed * tuning.enemy_speed_scale,
+        None => tuning.base_speed,
+    };
+    let velocity = match (velocity, angle_velocity) {
+        (Some(velocity), Some(_)) => *velocity.rotate(trans.rotation),
+        (Some(velocity), None) => **velocity,
+        (None, _) => Vec3::ZERO,
+    };
+    EntityState {
+        position: trans.translation.truncate(),
+        velocity: velocity.truncate() * speed,
+        rotation: trans.rotation.to_euler(EulerRot::ZYX).0,
+    }
+}
@@ -0,0 +1,230 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    resources::{enemy_archetypes::EnemyArchetypes, game_tuning::GameTuning, textures::Textures},
+    AppState, ViewportSize,
+};
+
+use super::enemy_plugin::spawn_enemy;
+use super::game_plugin::{SimulationSet, SimulationStage};
+use super::movement_plugin::GameClock;
+use super::rollback_plugin::RollbackApp;
+
+const SPAWN_ENTRY_SPEED: f32 = 500.;
+const TELEGRAPH_BLINK_S: f32 = 0.15;
+const TELEGRAPH_COLOR: Color = Color::rgba(1., 0.2, 0.2, 0.5);
+
+/// Rules for picking where new enemies appear.
+#[derive(Resource)]
+pub struct SpawnPlacement {
+    pub min_player_distance: f32,
+    pub min_enemy_distance: f32,
+    /// Random candidates tried before settling for the best one seen.
+    pub max_attempts: u32,
+    /// Enemies fly in from the closest screen edge instead of appearing in place.
+    pub offscreen_entry: bool,
+    pub telegraph_s: f32,
+}
+
+impl Default for SpawnPlacement {
+    fn default() -> Self {
+        Self {
+            min_player_distance: 250.,
+            min_enemy_distance: 90.,
+            max_attempts: 24,
+            offscreen_entry: true,
+            telegraph_s: 0.8,
+        }
+    }
+}
+
+impl SpawnPlacement {
+    /// Random position inside the viewport keeping away from the player and `others`.
+    /// Candidates are bounded by `max_attempts`, if none satisfies both distances the
+    /// one furthest from its closest obstacle is used.
+    pub fn find_position(
+        &self,
+        rand: &mut impl Rng,
+        viewport_size: &ViewportSize,
+        half_size: Vec2,
+        players: &[Vec2],
+        others: &[Vec2],
+    ) -> Vec2 {
+        let span = (Vec2::new(viewport_size.w, viewport_size.h) / 2. - half_size).max(Vec2::ONE);
+        // how far a candidate is from violating a constraint, positive means valid
+        let clearance = |candidate: Vec2| -> f32 {
+            let players = players
+                .iter()
+                .map(|player| candidate.distance(*player) - self.min_player_distance);
+            others
+                .iter()
+                .map(|other| candidate.distance(*other) - self.min_enemy_distance)
+                .chain(players)
+                .fold(f32::INFINITY, f32::min)
+        };
+
+        let mut best = (Vec2::ZERO, f32::NEG_INFINITY);
+        for _ in 0..self.max_attempts.max(1) {
+            let candidate = Vec2::new(
+                rand.gen_range(-span.x..span.x),
+                rand.gen_range(-span.y..span.y),
+            );
+            let candidate_clearance = clearance(candidate);
+            if candidate_clearance >= 0. {
+                return candidate;
+            }
+            if candidate_clearance > best.1 {
+                best = (candidate, candidate_clearance);
+            }
+        }
+        best.0
+    }
+}
+
+/// Warning shown where an enemy is about to appear.
+#[derive(Component, Clone)]
+pub struct SpawnTelegraph {
+    archetype: String,
+    timer: Timer,
+    blink: Timer,
+}
+
+/// Moves a freshly spawned enemy from off-screen to its spawn position.
+#[derive(Component, Clone)]
+pub struct SpawnEntry {
+    target: Vec2,
+}
+
+pub struct SpawnPlugin;
+
+impl Plugin for SpawnPlugin {
+    fn build(&self, app: &mut App) {
+        app.rollback_component::<SpawnTelegraph>()
+            .rollback_component::<SpawnEntry>()
+            .init_resource::<SpawnPlacement>()
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationSet::Telegraphs
+                    .system_set()
+                    .with_system(spawn_telegraph_system)
+                    .with_system(spawn_entry_system.after(spawn_telegraph_system)),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(cleanup_system));
+    }
+}
+
+fn cleanup_system(mut commands: Commands, query: Query<Entity, With<SpawnTelegraph>>) {
+    for e in query.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
+
+/// Telegraphs the spawn of `archetype` at `position`, the enemy appears once the warning ends.
+pub fn telegraph_spawn(
+    commands: &mut Commands,
+    textures: &Textures,
+    archetypes: &EnemyArchetypes,
+    placement: &SpawnPlacement,
+    archetype: &str,
+    position: Vec2,
+) {
+    let (image, scale) = match archetypes.get(archetype) {
+        Some(a) => (
+            textures.image(&a.sprite).unwrap_or(&textures.enemy),
+            textures.sprite_scale * a.scale,
+        ),
+        None => return,
+    };
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: TELEGRAPH_COLOR,
+                ..Default::default()
+            },
+            texture: image.image.clone(),
+            transform: Transform {
+                translation: position.extend(9.),
+                scale: Vec3::new(scale, scale, 1.),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        SpawnTelegraph {
+            archetype: archetype.to_string(),
+            timer: Timer::from_seconds(placement.telegraph_s, TimerMode::Once),
+            blink: Timer::from_seconds(TELEGRAPH_BLINK_S, TimerMode::Repeating),
+        },
+    ));
+}
+
+fn spawn_telegraph_system(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    (textures, archetypes, placement): (Res<Textures>, Res<EnemyArchetypes>, Res<SpawnPlacement>),
+    viewport_size: Res<ViewportSize>,
+    mut query: Query<(Entity, &mut SpawnTelegraph, &Transform, &mut Visibility)>,
+) {
+    for (entity, mut telegraph, trans, mut visibility) in query.iter_mut() {
+        telegraph.timer.tick(clock.delta());
+        telegraph.blink.tick(clock.delta());
+        if telegraph.blink.just_finished() {
+            visibility.is_visible = !visibility.is_visible;
+        }
+        if !telegraph.timer.finished() {
+            continue;
+        }
+        commands.entity(entity).despawn_recursive();
+        let archetype = match archetypes.get(&telegraph.archetype) {
+            Some(archetype) => archetype,
+            None => continue,
+        };
+        let target = trans.translation.truncate();
+        if !placement.offscreen_entry {
+            spawn_enemy(&mut commands, &textures, archetype, target.extend(10.));
+            continue;
+        }
+        let image = textures.image(&archetype.sprite).unwrap_or(&textures.enemy);
+        let half_size = image.size_px * trans.scale.truncate() / 2.;
+        let entry = offscreen_point(target, &viewport_size, half_size);
+        let enemy = spawn_enemy(&mut commands, &textures, archetype, entry.extend(10.));
+        commands.entity(enemy).insert(SpawnEntry { target });
+    }
+}
+
+/// Point just past the viewport edge closest to `target`.
+fn offscreen_point(target: Vec2, viewport_size: &ViewportSize, half_size: Vec2) -> Vec2 {
+    let half = Vec2::new(viewport_size.w, viewport_size.h) / 2.;
+    let gaps = [
+        half.x - target.x,
+        half.x + target.x,
+        half.y - target.y,
+        half.y + target.y,
+    ];
+    let closest = (0..gaps.len())
+        .min_by(|a, b| gaps[*a].total_cmp(&gaps[*b]))
+        .unwrap_or(2);
+    match closest {
+        0 => Vec2::new(half.x + half_size.x, target.y),
+        1 => Vec2::new(-half.x - half_size.x, target.y),
+        2 => Vec2::new(target.x, half.y + half_size.y),
+        _ => Vec2::new(target.x, -half.y - half_size.y),
+    }
+}
+
+fn spawn_entry_system(
+    mut commands: Commands,
+    tuning: Res<GameTuning>,
+    mut query: Query<(Entity, &SpawnEntry, &mut Transform)>,
+) {
+    let step = SPAWN_ENTRY_SPEED * tuning.time_step;
+    for (entity, entry, mut trans) in query.iter_mut() {
+        let offset = entry.target - trans.translation.truncate();
+        if offset.length() <= step {
+            trans.translation = entry.target.extend(trans.translation.z);
+            commands.entity(entity).remove::<SpawnEntry>();
+        } else {
+            trans.translation += (offset.normalize() * step).extend(0.);
+        }
+    }
+}
@@ -0,0 +1,578 @@
+use bevy::prelude::*;
+
+use crate::{
+    components::{
+        damage::Damage,
+        movable::{Movable, MovementOptions, MovementViewportBehavior},
+        sizeable::Sizeable,
+        velocity::Velocity,
+    },
+    resources::{game_tuning::GameTuning, textures::Textures},
+};
+
+use super::beam_plugin::BeamStats;
+use super::game_plugin::{SimulationSet, SimulationStage};
+use super::homing_plugin::{Homing, HomingStats};
+use super::pickup_plugin::{PickupKind, PowerUps};
+use super::player_plugin::{PlayerId, PlayerProjectile};
+use super::rollback_plugin::RollbackApp;
+
+const RAPID_FIRE_COOLDOWN_MULTIPLIER: f32 = 0.4;
+const SPREAD_SHOT_ANGLE: f32 = 0.25;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WeaponKind {
+    TwinCannon,
+    Spread,
+    Burst,
+    ChargeShot,
+    Laser,
+    HomingMissile,
+}
+
+impl WeaponKind {
+    pub const ALL: [WeaponKind; 6] = [
+        WeaponKind::TwinCannon,
+        WeaponKind::Spread,
+        WeaponKind::Burst,
+        WeaponKind::ChargeShot,
+        WeaponKind::Laser,
+        WeaponKind::HomingMissile,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            WeaponKind::TwinCannon => "Twin cannon",
+            WeaponKind::Spread => "Spread",
+            WeaponKind::Burst => "Burst",
+            WeaponKind::ChargeShot => "Charge shot",
+            WeaponKind::Laser => "Laser",
+            WeaponKind::HomingMissile => "Homing missile",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|k| k == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub fn build(&self) -> Weapon {
+        let behavior: Box<dyn WeaponBehavior> = match self {
+            WeaponKind::TwinCannon => Box::new(TwinCannon::default()),
+            WeaponKind::Spread => Box::new(SpreadCannon::default()),
+            WeaponKind::Burst => Box::new(BurstCannon::default()),
+            WeaponKind::ChargeShot => Box::new(ChargeCannon::default()),
+            WeaponKind::Laser => Box::new(LaserCannon::default()),
+            WeaponKind::HomingMissile => Box::new(MissileLauncher::default()),
+        };
+        Weapon(behavior)
+    }
+}
+
+/// Static characteristics of a weapon, muzzle offsets are in shooter sprite pixels.
+#[derive(Clone)]
+pub struct WeaponStats {
+    pub cooldown_s: f32,
+    pub speed: f32,
+    pub damage: f32,
+    /// Name of the projectile texture, see `Textures::image`.
+    pub sprite: &'static str,
+    pub color: Color,
+    pub scale: f32,
+    pub muzzles: Vec<Vec2>,
+    pub homing: Option<HomingStats>,
+    /// Continuous beam weapons deal `damage` per second instead of firing shots.
+    pub beam: Option<BeamStats>,
+}
+
+/// Single projectile requested by a weapon, relative to the shooter.
+#[derive(Clone, Copy)]
+pub struct Shot {
+    pub muzzle: Vec2,
+    pub angle: f32,
+    pub damage: f32,
+    pub scale: f32,
+}
+
+impl Shot {
+    fn from_stats(stats: &WeaponStats, muzzle: Vec2, angle: f32) -> Self {
+        Self {
+            muzzle,
+            angle,
+            damage: stats.damage,
+            scale: stats.scale,
+        }
+    }
+}
+
+#[derive(Component, Clone, Default)]
+pub struct WeaponTrigger {
+    pub held: bool,
+    pub released: bool,
+}
+
+pub trait WeaponBehavior: Send + Sync {
+    fn kind(&self) -> WeaponKind;
+
+    fn stats(&self) -> &WeaponStats;
+
+    /// Advances the weapon by `dt` seconds and returns shots fired during this step.
+    fn update(&mut self, trigger: &WeaponTrigger, dt: f32) -> Vec<Shot>;
+
+    /// A copy of the weapon in its current state, for rewinding a run.
+    fn clone_box(&self) -> Box<dyn WeaponBehavior>;
+}
+
+#[derive(Component, Deref, DerefMut)]
+pub struct Weapon(pub Box<dyn WeaponBehavior>);
+
+impl Clone for Weapon {
+    fn clone(&self) -> Self {
+        Weapon(self.0.clone_box())
+    }
+}
+
+impl Default for Weapon {
+    fn default() -> Self {
+        WeaponKind::TwinCannon.build()
+    }
+}
+
+#[derive(Clone)]
+pub struct TwinCannon {
+    stats: WeaponStats,
+    cooldown: f32,
+}
+
+impl Default for TwinCannon {
+    fn default() -> Self {
+        Self {
+            stats: WeaponStats {
+                cooldown_s: 0.25,
+                speed: 1.5,
+                damage: 1.,
+                sprite: "player_fire",
+                color: Color::WHITE,
+                scale: 1.,
+                muzzles: vec![Vec2::new(-90., 50.), Vec2::new(90., 50.)],
+                homing: None,
+                beam: None,
+            },
+            cooldown: 0.,
+        }
+    }
+}
+
+impl WeaponBehavior for TwinCannon {
+    fn kind(&self) -> WeaponKind {
+        WeaponKind::TwinCannon
+    }
+
+    fn stats(&self) -> &WeaponStats {
+        &self.stats
+    }
+
+    fn clone_box(&self) -> Box<dyn WeaponBehavior> {
+        Box::new(self.clone())
+    }
+
+    fn update(&mut self, trigger: &WeaponTrigger, dt: f32) -> Vec<Shot> {
+        self.cooldown -= dt;
+        if !trigger.held || self.cooldown > 0. {
+            return Vec::new();
+        }
+        self.cooldown = self.stats.cooldown_s;
+        self.stats
+            .muzzles
+            .iter()
+            .map(|muzzle| Shot::from_stats(&self.stats, *muzzle, 0.))
+            .collect()
+    }
+}
+
+#[derive(Clone)]
+pub struct SpreadCannon {
+    stats: WeaponStats,
+    cooldown: f32,
+    count: usize,
+    arc: f32,
+}
+
+impl Default for SpreadCannon {
+    fn default() -> Self {
+        Self {
+            stats: WeaponStats {
+                cooldown_s: 0.45,
+                speed: 1.3,
+                damage: 1.,
+                sprite: "spread_fire",
+                color: Color::YELLOW_GREEN,
+                scale: 0.8,
+                muzzles: vec![Vec2::new(0., 80.)],
+                homing: None,
+                beam: None,
+            },
+            cooldown: 0.,
+            count: 5,
+            arc: 0.8,
+        }
+    }
+}
+
+impl WeaponBehavior for SpreadCannon {
+    fn kind(&self) -> WeaponKind {
+        WeaponKind::Spread
+    }
+
+    fn stats(&self) -> &WeaponStats {
+        &self.stats
+    }
+
+    fn clone_box(&self) -> Box<dyn WeaponBehavior> {
+        Box::new(self.clone())
+    }
+
+    fn update(&mut self, trigger: &WeaponTrigger, dt: f32) -> Vec<Shot> {
+        self.cooldown -= dt;
+        if !trigger.held || self.cooldown > 0. {
+            return Vec::new();
+        }
+        self.cooldown = self.stats.cooldown_s;
+        let step = self.arc / (self.count - 1) as f32;
+        (0..self.count)
+            .map(|i| {
+                let angle = -self.arc / 2. + step * i as f32;
+                Shot::from_stats(&self.stats, self.stats.muzzles[0], angle)
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone)]
+pub struct BurstCannon {
+    stats: WeaponStats,
+    cooldown: f32,
+    burst_size: u32,
+    burst_interval_s: f32,
+    burst_remaining: u32,
+    burst_timer: f32,
+}
+
+impl Default for BurstCannon {
+    fn default() -> Self {
+        Self {
+            stats: WeaponStats {
+                cooldown_s: 0.6,
+                speed: 2.,
+                damage: 1.,
+                sprite: "burst_fire",
+                color: Color::ORANGE,
+                scale: 0.8,
+                muzzles: vec![Vec2::new(0., 80.)],
+                homing: None,
+                beam: None,
+            },
+            cooldown: 0.,
+            burst_size: 4,
+            burst_interval_s: 0.06,
+            burst_remaining: 0,
+            burst_timer: 0.,
+        }
+    }
+}
+
+impl WeaponBehavior for BurstCannon {
+    fn kind(&self) -> WeaponKind {
+        WeaponKind::Burst
+    }
+
+    fn stats(&self) -> &WeaponStats {
+        &self.stats
+    }
+
+    fn clone_box(&self) -> Box<dyn WeaponBehavior> {
+        Box::new(self.clone())
+    }
+
+    fn update(&mut self, trigger: &WeaponTrigger, dt: f32) -> Vec<Shot> {
+        self.cooldown -= dt;
+        self.burst_timer -= dt;
+        if self.burst_remaining == 0 {
+            if !trigger.held || self.cooldown > 0. {
+                return Vec::new();
+            }
+            self.burst_remaining = self.burst_size;
+            self.burst_timer = 0.;
+            self.cooldown = self.stats.cooldown_s;
+        }
+        if self.burst_timer > 0. {
+            return Vec::new();
+        }
+        self.burst_remaining -= 1;
+        self.burst_timer = self.burst_interval_s;
+        vec![Shot::from_stats(&self.stats, self.stats.muzzles[0], 0.)]
+    }
+}
+
+#[derive(Clone)]
+pub struct ChargeCannon {
+    stats: WeaponStats,
+    cooldown: f32,
+    charge: f32,
+    max_charge_s: f32,
+}
+
+impl Default for ChargeCannon {
+    fn default() -> Self {
+        Self {
+            stats: WeaponStats {
+                cooldown_s: 0.2,
+                speed: 1.2,
+                damage: 1.,
+                sprite: "charge_fire",
+                color: Color::FUCHSIA,
+                scale: 1.,
+                muzzles: vec![Vec2::new(0., 80.)],
+                homing: None,
+                beam: None,
+            },
+            cooldown: 0.,
+            charge: 0.,
+            max_charge_s: 1.5,
+        }
+    }
+}
+
+impl ChargeCannon {
+    /// Charge level in `0..=1` range.
+    pub fn charge_level(&self) -> f32 {
+        self.charge / self.max_charge_s
+    }
+}
+
+impl WeaponBehavior for ChargeCannon {
+    fn kind(&self) -> WeaponKind {
+        WeaponKind::ChargeShot
+    }
+
+    fn stats(&self) -> &WeaponStats {
+        &self.stats
+    }
+
+    fn clone_box(&self) -> Box<dyn WeaponBehavior> {
+        Box::new(self.clone())
+    }
+
+    fn update(&mut self, trigger: &WeaponTrigger, dt: f32) -> Vec<Shot> {
+        self.cooldown -= dt;
+        if self.cooldown > 0. {
+            return Vec::new();
+        }
+        if trigger.held {
+            self.charge = (self.charge + dt).min(self.max_charge_s);
+            return Vec::new();
+        }
+        if !trigger.released || self.charge <= 0. {
+            return Vec::new();
+        }
+        let level = self.charge_level();
+        self.charge = 0.;
+        self.cooldown = self.stats.cooldown_s;
+        vec![Shot {
+            muzzle: self.stats.muzzles[0],
+            angle: 0.,
+            damage: self.stats.damage + 4. * level,
+            scale: self.stats.scale + 1.5 * level,
+        }]
+    }
+}
+
+#[derive(Clone)]
+pub struct LaserCannon {
+    stats: WeaponStats,
+}
+
+impl Default for LaserCannon {
+    fn default() -> Self {
+        Self {
+            stats: WeaponStats {
+                cooldown_s: 0.,
+                speed: 0.,
+                damage: 6.,
+                sprite: "player_fire",
+                color: Color::rgba(1., 0.2, 0.2, 0.85),
+                scale: 1.,
+                muzzles: vec![Vec2::new(0., 90.)],
+                homing: None,
+                beam: Some(BeamStats {
+                    width: 10.,
+                    max_length: 1200.,
+                }),
+            },
+        }
+    }
+}
+
+impl WeaponBehavior for LaserCannon {
+    fn kind(&self) -> WeaponKind {
+        WeaponKind::Laser
+    }
+
+    fn stats(&self) -> &WeaponStats {
+        &self.stats
+    }
+
+    fn clone_box(&self) -> Box<dyn WeaponBehavior> {
+        Box::new(self.clone())
+    }
+
+    fn update(&mut self, _trigger: &WeaponTrigger, _dt: f32) -> Vec<Shot> {
+        // damage is dealt continuously by the beam plugin
+        Vec::new()
+    }
+}
+
+#[derive(Clone)]
+pub struct MissileLauncher {
+    stats: WeaponStats,
+    cooldown: f32,
+    next_muzzle: usize,
+}
+
+impl Default for MissileLauncher {
+    fn default() -> Self {
+        Self {
+            stats: WeaponStats {
+                cooldown_s: 0.5,
+                speed: 0.9,
+                damage: 2.,
+                sprite: "missile",
+                color: Color::GOLD,
+                scale: 1.2,
+                muzzles: vec![Vec2::new(-90., 20.), Vec2::new(90., 20.)],
+                homing: Some(HomingStats {
+                    turn_rate: 4.,
+                    cone: 1.2,
+                    lifetime_s: 3.,
+                }),
+                beam: None,
+            },
+            cooldown: 0.,
+            next_muzzle: 0,
+        }
+    }
+}
+
+impl WeaponBehavior for MissileLauncher {
+    fn kind(&self) -> WeaponKind {
+        WeaponKind::HomingMissile
+    }
+
+    fn stats(&self) -> &WeaponStats {
+        &self.stats
+    }
+
+    fn clone_box(&self) -> Box<dyn WeaponBehavior> {
+        Box::new(self.clone())
+    }
+
+    fn update(&mut self, trigger: &WeaponTrigger, dt: f32) -> Vec<Shot> {
+        self.cooldown -= dt;
+        if !trigger.held || self.cooldown > 0. {
+            return Vec::new();
+        }
+        self.cooldown = self.stats.cooldown_s;
+        let muzzle = self.stats.muzzles[self.next_muzzle];
+        self.next_muzzle = (self.next_muzzle + 1) % self.stats.muzzles.len();
+        vec![Shot::from_stats(&self.stats, muzzle, 0.)]
+    }
+}
+
+pub struct WeaponPlugin;
+
+impl Plugin for WeaponPlugin {
+    fn build(&self, app: &mut App) {
+        app.rollback_component::<Weapon>()
+            .rollback_component::<WeaponTrigger>()
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationSet::Weapons
+                    .system_set()
+                    .with_system(weapon_fire_system),
+            );
+    }
+}
+
+type WeaponQuery<'a> = (
+    &'a mut Weapon,
+    &'a WeaponTrigger,
+    &'a Transform,
+    Option<&'a PowerUps>,
+    Option<&'a PlayerId>,
+);
+
+fn weapon_fire_system(
+    mut commands: Commands,
+    tuning: Res<GameTuning>,
+    textures: Res<Textures>,
+    mut query: Query<WeaponQuery>,
+) {
+    // a longer cooldown is the same as weapon time running slower
+    let step = tuning.time_step / tuning.fire_cooldown_scale;
+    for (mut weapon, trigger, shooter_trans, power_ups, player) in query.iter_mut() {
+        let is_active = |kind| power_ups.is_some_and(|p| p.is_active(kind));
+        let dt = match is_active(PickupKind::RapidFire) {
+            true => step / RAPID_FIRE_COOLDOWN_MULTIPLIER,
+            false => step,
+        };
+        let mut shots = weapon.update(trigger, dt);
+        if is_active(PickupKind::SpreadShot) {
+            let spread = shots
+                .iter()
+                .flat_map(|shot| {
+                    [-SPREAD_SHOT_ANGLE, SPREAD_SHOT_ANGLE].map(|angle| Shot {
+                        angle: shot.angle + angle,
+                        ..*shot
+                    })
+                })
+                .collect::<Vec<_>>();
+            shots.extend(spread);
+        }
+
+        let stats = weapon.stats();
+        let image = textures
+            .image(stats.sprite)
+            .unwrap_or(&textures.player_fire);
+        for shot in shots {
+            let offset = *Velocity((shot.muzzle * shooter_trans.scale.truncate()).extend(0.))
+                .rotate(shooter_trans.rotation);
+            let rotation = shooter_trans.rotation * Quat::from_rotation_z(shot.angle);
+            let mut projectile = commands.spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: stats.color,
+                    ..Default::default()
+                },
+                texture: image.image.clone(),
+                transform: Transform {
+                    translation: shooter_trans.translation + offset,
+                    scale: shooter_trans.scale * Vec3::new(shot.scale, shot.scale, 1.),
+                    rotation,
+                },
+                ..Default::default()
+            });
+            projectile
+                .insert(Velocity(Vec3::new(0., stats.speed, 0.)).rotate(rotation))
+                .insert(Damage(shot.damage))
+                .insert(Sizeable(image.size_px))
+                .insert(Movable(MovementOptions {
+                    viewport_behavior: MovementViewportBehavior::DespawnOnLeave,
+                }));
+            if let Some(homing) = stats.homing {
+                projectile.insert(Homing::from(homing));
+            }
+            if let Some(player) = player {
+                projectile.insert(PlayerProjectile(*player));
+            }
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use bevy::prelude::*;
+
+use crate::{
+    components::root_node::RootNode,
+    plugins::{
+        delayed_state_switch_plugin::StateActionButton,
+        game::{
+            daily_challenge_plugin::DailyAttempt,
+            game_mode_plugin::RunOutcome,
+            player_plugin::{PlayerId, PlayerScores},
+            replay_plugin::{LastRun, ReplayButton, ReplayPlayback, ReplaySource},
+        },
+        ui_interaction_plugin::{UiButton, UiButtonColors},
+    },
+    resources::{
+        daily_challenge::{DailyChallenge, DailyResults},
+        fonts::Fonts,
+        game_rng::GameRng,
+        high_scores::{HighScoreEntry, HighScores},
+        replay::REPLAY_DIR,
+        run_settings::RunSettings,
+        score::Score,
+    },
+    AppState,
+};
+
+pub struct GameOverPlugin;
+
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::GameOver).with_system(setup_system))
+            .add_system_set(SystemSet::on_exit(AppState::GameOver).with_system(cleanup_system));
+    }
+}
+
+fn setup_system(
+    mut commands: Commands,
+    fonts: Res<Fonts>,
+    (score, player_scores, rng): (Res<Score>, Res<PlayerScores>, Res<GameRng>),
+    (last_run, playback): (Res<LastRun>, Option<Res<ReplayPlayback>>),
+    (settings, outcome): (Res<RunSettings>, Option<Res<RunOutcome>>),
+    mut high_scores: ResMut<HighScores>,
+    (daily, attempt, mut daily_results): (
+        Option<Res<DailyChallenge>>,
+        Option<Res<DailyAttempt>>,
+        ResMut<DailyResults>,
+    ),
+) {
+    let colors = UiButtonColors::default();
+    let outcome = outcome.map_or(RunOutcome::Defeat, |outcome| *outcome);
+    let title = match playback {
+        Some(_) => "REPLAY FINISHED",
+        None => outcome.title(),
+    };
+    let background = match outcome {
+        RunOutcome::Victory => Color::rgb(0.05, 0.15, 0.08),
+        RunOutcome::Defeat | RunOutcome::TimeUp | RunOutcome::Disconnected => {
+            Color::rgb(0.15, 0.05, 0.08)
+        }
+    };
+    // watched replays are not runs of their own, daily challenges are kept apart
+    let record = match (&playback, attempt.as_deref()) {
+        (Some(_), _) => None,
+        (None, Some(DailyAttempt::Scored)) => daily.map(|daily| {
+            let path = format!("{}/daily-{}.replay", REPLAY_DIR, daily.date());
+            let replay = save_replay(&last_run, path);
+            daily_results.finish_attempt(&daily, **score, replay);
+            daily_results.save();
+            format!("Daily challenge {} recorded", daily.date())
+        }),
+        (None, Some(DailyAttempt::Practice)) => {
+            Some("Practice run, today's attempt is used up".to_string())
+        }
+        (None, None) if high_scores.qualifies(settings.mode, **score) => {
+            let path = format!("{}/run-{}-{}.replay", REPLAY_DIR, rng.seed(), **score);
+            let replay = save_replay(&last_run, path);
+            let rank = high_scores.insert(
+                settings.mode,
+                HighScoreEntry {
+                    score: **score,
+                    seed: rng.seed(),
+                    replay,
+                },
+            );
+            high_scores.save();
+            rank.map(|rank| format!("New high score! #{}", rank + 1))
+        }
+        (None, None) => None,
+    };
+
+    commands.spawn(Camera2dBundle::default());
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            background_color: background.into(),
+            ..Default::default()
+        })
+        .insert(RootNode)
+        .with_children(|parent| {
+            parent.spawn(get_text(title, fonts.bold.clone(), 80.));
+            parent.spawn(get_text(
+                format!(
+                    "{} - {}",
+                    settings.mode.label(),
+                    settings.difficulty.label()
+                ),
+                fonts.italic.clone(),
+                30.,
+            ));
+            parent.spawn(get_text(
+                format!("Score: {}", **score),
+                fonts.regular.clone(),
+                40.,
+            ));
+            if settings.players > 1 {
+                for (index, points) in player_scores.iter().enumerate() {
+                    let id = PlayerId(index as u8);
+                    parent.spawn(get_text(
+                        format!("{}: {}", id.label(), points),
+                        fonts.regular.clone(),
+                        30.,
+                    ));
+                }
+            }
+            parent.spawn(get_text(
+                format!("Seed: {}", rng.seed()),
+                fonts.italic.clone(),
+                24.,
+            ));
+            if let Some(record) = record {
+                parent.spawn(get_text(record, fonts.bold.clone(), 30.));
+            }
+        })
+        .with_children(add_button(
+            StateActionButton::StartGame,
+            UiButton::new("Play again", colors),
+            &fonts,
+        ))
+        .with_children(add_button(
+            ReplayButton(ReplaySource::LastRun),
+            UiButton::new("Watch replay", colors),
+            &fonts,
+        ))
+        .with_children(add_button(
+            StateActionButton::MainMenu,
+            UiButton::new("Main menu", colors),
+            &fonts,
+        ));
+}
+
+/// The replay of the run at `path`, `None` if it couldn't be saved.
+fn save_replay(last_run: &LastRun, path: String) -> Option<String> {
+    match last_run.0.save(&path) {
+        Ok(_) => Some(path),
+        Err(err) => {
+            warn!("Failed to save the replay of the run: {}", err);
+            None
+        }
+    }
+}
+
+fn cleanup_system(
+    mut commands: Commands,
+    root_query: Query<Entity, With<RootNode>>,
+    camera_query: Query<Entity, With<Camera2d>>,
+) {
+    for cam in camera_query.iter() {
+        commands.entity(cam).despawn();
+    }
+    if let Ok(root) = root_query.get_single() {
+        commands.entity(root).despawn_recursive();
+    }
+}
+
+fn add_button(
+    button: impl Component + Clone,
+    menu_button: UiButton,
+    fonts: &Res<Fonts>,
+) -> impl Fn(&mut ChildBuilder) {
+    let font = fonts.regular.clone();
+    move |parent| {
+        parent
+            .spawn(ButtonBundle {
+                style: Style {
+                    size: Size::new(Val::Px(250.), Val::Px(70.)),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    margin: UiRect::all(Val::Px(10.)),
+                    ..Default::default()
+                },
+                background_color: menu_button.colors.none_color.into(),
+                ..Default::default()
+            })
+            .insert(menu_button.clone())
+            .insert(button.clone())
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    menu_button.text.clone(),
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 30.,
+                        color: Color::BLACK,
+                    },
+                ));
+            });
+    }
+}
+
+fn get_text(text: impl Into<String>, font: Handle<Font>, font_size: f32) -> TextBundle {
+    TextBundle::from_section(
+        text,
+        TextStyle {
+            font,
+            font_size,
+            color: Color::WHITE,
+        },
+    )
+    .with_style(Style {
+        margin: UiRect::all(Val::Px(8.)),
+        ..Default::default()
+    })
+}
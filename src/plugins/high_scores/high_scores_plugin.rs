@@ -0,0 +1,224 @@
+use bevy::prelude::*;
+
+use crate::{
+    components::root_node::RootNode,
+    plugins::{
+        delayed_state_switch_plugin::StateActionButton,
+        game::replay_plugin::{ReplayButton, ReplaySource},
+        ui_interaction_plugin::{UiButton, UiButtonColors},
+    },
+    resources::{
+        daily_challenge::DailyResults,
+        fonts::Fonts,
+        high_scores::HighScores,
+        run_settings::{GameMode, RunSettings},
+    },
+    AppState,
+};
+
+/// Days of daily challenge results listed, most recent first.
+const DAILY_ROWS: usize = 10;
+
+/// Game mode whose table is on screen.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HighScoresTable(pub GameMode);
+
+/// Switches the screen to the table of the next game mode.
+#[derive(Component)]
+struct TableModeButton(GameMode);
+
+pub struct HighScoresPlugin;
+
+impl Plugin for HighScoresPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::HighScores).with_system(setup_system))
+            .add_system_set(
+                SystemSet::on_update(AppState::HighScores).with_system(table_mode_button_system),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::HighScores).with_system(cleanup_system));
+    }
+}
+
+/// Opens on the table of the mode played last.
+fn setup_system(
+    mut commands: Commands,
+    fonts: Res<Fonts>,
+    (high_scores, daily_results): (Res<HighScores>, Res<DailyResults>),
+    settings: Res<RunSettings>,
+) {
+    commands.spawn(Camera2dBundle::default());
+    let rows = table_rows(&high_scores, &daily_results, settings.mode);
+    spawn_table(&mut commands, &fonts, settings.mode, rows);
+    commands.insert_resource(HighScoresTable(settings.mode));
+}
+
+fn table_mode_button_system(
+    mut commands: Commands,
+    (fonts, high_scores, daily_results): (Res<Fonts>, Res<HighScores>, Res<DailyResults>),
+    mut query: Query<(&Interaction, &TableModeButton, &mut UiButton), Changed<Interaction>>,
+    root_query: Query<Entity, With<RootNode>>,
+) {
+    for (interaction, button, mut ui_button) in query.iter_mut() {
+        if *interaction == Interaction::Clicked {
+            ui_button.clicked = true;
+            continue;
+        }
+        if !ui_button.clicked {
+            continue;
+        }
+        ui_button.clicked = false;
+        if let Ok(root) = root_query.get_single() {
+            commands.entity(root).despawn_recursive();
+        }
+        let next = GameMode::ALL[(button.0 as usize + 1) % GameMode::ALL.len()];
+        let rows = table_rows(&high_scores, &daily_results, next);
+        spawn_table(&mut commands, &fonts, next, rows);
+        commands.insert_resource(HighScoresTable(next));
+    }
+}
+
+/// Text and replay of every row, daily challenges list their days instead of ranks.
+fn table_rows(
+    high_scores: &HighScores,
+    daily_results: &DailyResults,
+    mode: GameMode,
+) -> Vec<(String, Option<String>)> {
+    match mode {
+        GameMode::DailyChallenge => daily_results
+            .recent()
+            .take(DAILY_ROWS)
+            .map(|(date, result)| {
+                let text = format!("{} {:>8}   seed {}", date, result.score, result.seed);
+                (text, result.replay.clone())
+            })
+            .collect(),
+        _ => high_scores
+            .entries(mode)
+            .iter()
+            .enumerate()
+            .map(|(rank, entry)| {
+                let text = format!("#{:<2} {:>8}   seed {}", rank + 1, entry.score, entry.seed);
+                (text, entry.replay.clone())
+            })
+            .collect(),
+    }
+}
+
+fn spawn_table(
+    commands: &mut Commands,
+    fonts: &Fonts,
+    mode: GameMode,
+    rows: Vec<(String, Option<String>)>,
+) {
+    let colors = UiButtonColors::default();
+    let mode_text = format!("Mode: {}", mode.label());
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            background_color: Color::GRAY.into(),
+            ..Default::default()
+        })
+        .insert(RootNode)
+        .with_children(|parent| {
+            parent.spawn(get_text("High scores", fonts.bold.clone(), 50.));
+            parent
+                .spawn(get_button(colors.none_color))
+                .insert(UiButton::new(mode_text.clone(), colors))
+                .insert(TableModeButton(mode))
+                .with_children(|button| {
+                    button.spawn(get_button_text(mode_text.clone(), fonts.regular.clone()));
+                });
+            if rows.is_empty() {
+                parent.spawn(get_text("No runs yet", fonts.italic.clone(), 30.));
+            }
+            for (text, replay) in rows {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .with_children(|row| {
+                        row.spawn(get_text(text, fonts.regular.clone(), 26.));
+                        if let Some(path) = replay {
+                            row.spawn(get_button(colors.none_color))
+                                .insert(UiButton::new("Watch", colors))
+                                .insert(ReplayButton(ReplaySource::File(path)))
+                                .with_children(|button| {
+                                    button.spawn(get_button_text("Watch", fonts.regular.clone()));
+                                });
+                        }
+                    });
+            }
+            parent
+                .spawn(get_button(colors.none_color))
+                .insert(UiButton::new("Back", colors))
+                .insert(StateActionButton::MainMenu)
+                .with_children(|button| {
+                    button.spawn(get_button_text("Back", fonts.regular.clone()));
+                });
+        });
+}
+
+fn cleanup_system(
+    mut commands: Commands,
+    root_query: Query<Entity, With<RootNode>>,
+    camera_query: Query<Entity, With<Camera2d>>,
+) {
+    commands.remove_resource::<HighScoresTable>();
+    for cam in camera_query.iter() {
+        commands.entity(cam).despawn();
+    }
+    if let Ok(root) = root_query.get_single() {
+        commands.entity(root).despawn_recursive();
+    }
+}
+
+fn get_text(text: impl Into<String>, font: Handle<Font>, font_size: f32) -> TextBundle {
+    TextBundle::from_section(
+        text,
+        TextStyle {
+            font,
+            font_size,
+            color: Color::WHITE,
+        },
+    )
+    .with_style(Style {
+        margin: UiRect::all(Val::Px(6.)),
+        ..Default::default()
+    })
+}
+
+fn get_button_text(text: impl Into<String>, font: Handle<Font>) -> TextBundle {
+    TextBundle::from_section(
+        text,
+        TextStyle {
+            font,
+            font_size: 24.,
+            color: Color::BLACK,
+        },
+    )
+}
+
+fn get_button(background_color: Color) -> ButtonBundle {
+    ButtonBundle {
+        style: Style {
+            padding: UiRect::new(Val::Px(16.), Val::Px(16.), Val::Px(6.), Val::Px(6.)),
+            margin: UiRect::all(Val::Px(6.)),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..Default::default()
+        },
+        background_color: background_color.into(),
+        ..Default::default()
+    }
+}
@@ -0,0 +1 @@
+pub mod high_scores_plugin;
@@ -0,0 +1,424 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    leaderboard::{
+        board_name, Delivery, LeaderboardEntry, LeaderboardSettings, Submission, SubmissionQueue,
+        Verification,
+    },
+    plugins::{
+        game::{
+            daily_challenge_plugin::DailyAttempt,
+            replay_plugin::{LastRun, ReplayPlayback},
+        },
+        high_scores::high_scores_plugin::HighScoresTable,
+    },
+    resources::{
+        daily_challenge::DailyChallenge, fonts::Fonts, game_rng::GameRng,
+        run_settings::RunSettings, score::Score,
+    },
+    AppState, Headless,
+};
+
+const TOP_ROWS: usize = 5;
+/// Ranks listed above and below the player's best.
+const AROUND_ROWS: usize = 2;
+const FONT_SIZE: f32 = 22.;
+
+/// What became of the run submitted last.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SubmissionStatus {
+    Sending,
+    /// Kept on disk until the leaderboard can be reached.
+    Queued,
+    Ranked(u32),
+    Rejected(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BoardScores {
+    pub top: Vec<LeaderboardEntry>,
+    pub around: Vec<LeaderboardEntry>,
+}
+
+/// Latest answers of the leaderboard, filled in as the worker gets them.
+#[derive(Resource, Default)]
+pub struct LeaderboardStatus {
+    pub submission: Option<(Submission, SubmissionStatus)>,
+    /// By board, `None` while it can't be reached.
+    pub boards: HashMap<String, Option<BoardScores>>,
+}
+
+enum Job {
+    Submit(Submission),
+    Fetch(String),
+}
+
+enum WorkerEvent {
+    Delivered(Delivery),
+    Queued,
+    Scores(String, Option<BoardScores>),
+}
+
+/// Channels to the thread talking to the leaderboard, so frames never wait on the network.
+#[derive(Resource)]
+struct LeaderboardWorker {
+    jobs: Sender<Job>,
+    events: Mutex<Receiver<WorkerEvent>>,
+}
+
+#[derive(Component)]
+struct LeaderboardNode;
+
+/// Submits finished runs when a leaderboard is set and shows the online
+/// scores next to the high score tables.
+pub struct LeaderboardPlugin;
+
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LeaderboardStatus>()
+            .add_startup_system(setup_system)
+            .add_system(worker_event_system)
+            .add_system_set(SystemSet::on_enter(AppState::GameOver).with_system(submit_system))
+            .add_system_set(
+                SystemSet::on_update(AppState::GameOver).with_system(submission_text_system),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::GameOver).with_system(cleanup_system))
+            .add_system_set(
+                SystemSet::on_update(AppState::HighScores)
+                    .with_system(fetch_system)
+                    .with_system(board_panel_system.after(fetch_system)),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::HighScores).with_system(cleanup_system));
+    }
+}
+
+fn setup_system(
+    mut commands: Commands,
+    settings: Option<Res<LeaderboardSettings>>,
+    headless: Option<Res<Headless>>,
+) {
+    let settings = match settings {
+        Some(settings) => settings.clone(),
+        None => return,
+    };
+    let persist = headless.is_none();
+    let (jobs, job_receiver) = mpsc::channel();
+    let (event_sender, events) = mpsc::channel();
+    thread::spawn(move || run_worker(settings, persist, job_receiver, event_sender));
+    commands.insert_resource(LeaderboardWorker {
+        jobs,
+        events: Mutex::new(events),
+    });
+}
+
+/// Sends submissions as they come and retries the queued ones once their backoff is over.
+fn run_worker(
+    settings: LeaderboardSettings,
+    persist: bool,
+    jobs: Receiver<Job>,
+    events: Sender<WorkerEvent>,
+) {
+    let mut queue = match persist {
+        true => SubmissionQueue::load(),
+        false => SubmissionQueue::default(),
+    };
+    loop {
+        let job = match queue.retry_in(Instant::now()) {
+            Some(wait) => match jobs.recv_timeout(wait) {
+                Ok(job) => Some(job),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => return,
+            },
+            None => match jobs.recv() {
+                Ok(job) => Some(job),
+                Err(_) => return,
+            },
+        };
+        match job {
+            Some(Job::Submit(submission)) => queue.push(submission),
+            Some(Job::Fetch(board)) => {
+                let client = &settings.client;
+                let scores = client.top(&board, TOP_ROWS).and_then(|top| {
+                    let around = client.around(&board, &settings.player, AROUND_ROWS)?;
+                    Ok(BoardScores { top, around })
+                });
+                if let Err(err) = &scores {
+                    warn!("Failed to fetch the {} leaderboard: {}", board, err);
+                }
+                let _ = events.send(WorkerEvent::Scores(board, scores.ok()));
+                continue;
+            }
+            None => {}
+        }
+        let deliveries = queue.flush(&settings.client, Instant::now());
+        if persist {
+            queue.save();
+        }
+        for delivery in deliveries {
+            if let Delivery::Rejected(_, reason) = &delivery {
+                warn!("Leaderboard submission rejected: {}", reason);
+            }
+            let _ = events.send(WorkerEvent::Delivered(delivery));
+        }
+        if !queue.is_empty() {
+            let _ = events.send(WorkerEvent::Queued);
+        }
+    }
+}
+
+fn worker_event_system(
+    worker: Option<Res<LeaderboardWorker>>,
+    mut status: ResMut<LeaderboardStatus>,
+) {
+    let worker = match worker {
+        Some(worker) => worker,
+        None => return,
+    };
+    let events = worker.events.lock().unwrap();
+    for event in events.try_iter() {
+        let (submission, update) = match event {
+            WorkerEvent::Scores(board, scores) => {
+                status.boards.insert(board, scores);
+                continue;
+            }
+            WorkerEvent::Queued => (None, SubmissionStatus::Queued),
+            WorkerEvent::Delivered(Delivery::Submitted(submission, response)) => {
+                (Some(submission), SubmissionStatus::Ranked(response.rank))
+            }
+            WorkerEvent::Delivered(Delivery::Rejected(submission, reason)) => {
+                (Some(submission), SubmissionStatus::Rejected(reason))
+            }
+        };
+        // only the run submitted last is shown, older ones leaving the queue don't count
+        if let Some((last, last_status)) = &mut status.submission {
+            match submission {
+                Some(submission) if submission == *last => *last_status = update,
+                None if *last_status == SubmissionStatus::Sending => *last_status = update,
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Sends the run just finished, replays and practice runs of the daily challenge aren't ranked.
+fn submit_system(
+    mut commands: Commands,
+    fonts: Res<Fonts>,
+    (worker, leaderboard): (
+        Option<Res<LeaderboardWorker>>,
+        Option<Res<LeaderboardSettings>>,
+    ),
+    (score, rng, last_run): (Res<Score>, Res<GameRng>, Res<LastRun>),
+    (settings, daily): (Res<RunSettings>, Option<Res<DailyChallenge>>),
+    (playback, attempt): (Option<Res<ReplayPlayback>>, Option<Res<DailyAttempt>>),
+    mut status: ResMut<LeaderboardStatus>,
+) {
+    let (worker, leaderboard) = match (worker, leaderboard) {
+        (Some(worker), Some(leaderboard)) => (worker, leaderboard),
+        _ => return,
+    };
+    if playback.is_some() || attempt.as_deref() == Some(&DailyAttempt::Practice) {
+        return;
+    }
+    let board = match board_name(settings.mode, daily.as_deref()) {
+        Some(board) => board,
+        None => return,
+    };
+    let mut replay = last_run.0.clone();
+    replay.seed = rng.seed();
+    let submission = Submission {
+        board,
+        player: leaderboard.player.clone(),
+        score: **score,
+        seed: rng.seed(),
+        replay: None,
+    }
+    .with_replay(&replay);
+    status.submission = Some((submission.clone(), SubmissionStatus::Sending));
+    let _ = worker.jobs.send(Job::Submit(submission));
+
+    commands
+        .spawn(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(10.),
+                    bottom: Val::Px(10.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font: fonts.italic.clone(),
+                    font_size: FONT_SIZE,
+                    color: Color::WHITE,
+                },
+            ),
+            ..Default::default()
+        })
+        .insert(LeaderboardNode);
+}
+
+fn submission_text_system(
+    status: Res<LeaderboardStatus>,
+    mut query: Query<&mut Text, With<LeaderboardNode>>,
+) {
+    if !status.is_changed() {
+        return;
+    }
+    let text = match &status.submission {
+        Some((_, SubmissionStatus::Sending)) => "Submitting to the leaderboard...".to_string(),
+        Some((_, SubmissionStatus::Queued)) => {
+            "Leaderboard offline, the score is queued and will be retried".to_string()
+        }
+        Some((_, SubmissionStatus::Ranked(rank))) => format!("Online rank #{}", rank),
+        Some((_, SubmissionStatus::Rejected(reason))) => {
+            format!("Leaderboard refused the score: {}", reason)
+        }
+        None => String::new(),
+    };
+    for mut node_text in query.iter_mut() {
+        node_text.sections[0].value = text.clone();
+    }
+}
+
+/// Asks for the board of the table on screen whenever it changes.
+fn fetch_system(
+    worker: Option<Res<LeaderboardWorker>>,
+    table: Option<Res<HighScoresTable>>,
+    daily: Option<Res<DailyChallenge>>,
+    mut status: ResMut<LeaderboardStatus>,
+) {
+    // the daily board is known a frame after its table comes up
+    let daily_added = daily.as_ref().is_some_and(|daily| daily.is_added());
+    let (worker, table) = match (worker, table) {
+        (Some(worker), Some(table)) if table.is_changed() || daily_added => (worker, table),
+        _ => return,
+    };
+    let board = match board_name(table.0, daily.as_deref()) {
+        Some(board) => board,
+        None => return,
+    };
+    // the board shows as loading until the answer comes in
+    status.boards.remove(&board);
+    let _ = worker.jobs.send(Job::Fetch(board));
+}
+
+fn board_panel_system(
+    mut commands: Commands,
+    fonts: Res<Fonts>,
+    (leaderboard, table): (
+        Option<Res<LeaderboardSettings>>,
+        Option<Res<HighScoresTable>>,
+    ),
+    daily: Option<Res<DailyChallenge>>,
+    status: Res<LeaderboardStatus>,
+    node_query: Query<Entity, With<LeaderboardNode>>,
+) {
+    let (leaderboard, table) = match (leaderboard, table) {
+        (Some(leaderboard), Some(table)) => (leaderboard, table),
+        _ => return,
+    };
+    if !status.is_changed() && !table.is_changed() {
+        return;
+    }
+    for node in node_query.iter() {
+        commands.entity(node).despawn_recursive();
+    }
+
+    let board = match board_name(table.0, daily.as_deref()) {
+        Some(board) => board,
+        None => return,
+    };
+    let mut lines = vec![(format!("Online - {}", board), Color::WHITE)];
+    let add_entries = |lines: &mut Vec<_>, entries: &[LeaderboardEntry]| {
+        for entry in entries {
+            let color = match entry.player == leaderboard.player {
+                true => Color::YELLOW,
+                false => Color::WHITE,
+            };
+            lines.push((entry_line(entry), color));
+        }
+    };
+    match status.boards.get(&board) {
+        None => lines.push(("Loading...".to_string(), Color::WHITE)),
+        Some(None) => lines.push(("Leaderboard unavailable".to_string(), Color::WHITE)),
+        Some(Some(scores)) if scores.top.is_empty() => {
+            lines.push(("No scores yet".to_string(), Color::WHITE))
+        }
+        Some(Some(scores)) => {
+            add_entries(&mut lines, &scores.top);
+            // around the player only adds something below the top rows
+            let below_top: Vec<_> = scores
+                .around
+                .iter()
+                .filter(|entry| entry.rank as usize > TOP_ROWS)
+                .cloned()
+                .collect();
+            if !below_top.is_empty() {
+                lines.push(("...".to_string(), Color::WHITE));
+                add_entries(&mut lines, &below_top);
+            }
+        }
+    }
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    right: Val::Px(10.),
+                    top: Val::Px(10.),
+                    ..Default::default()
+                },
+                flex_direction: FlexDirection::Column,
+                ..Default::default()
+            },
+            background_color: Color::rgba(0., 0., 0., 0.4).into(),
+            ..Default::default()
+        })
+        .insert(LeaderboardNode)
+        .with_children(|parent| {
+            for (line, color) in lines {
+                parent.spawn(TextBundle::from_section(
+                    line,
+                    TextStyle {
+                        font: fonts.regular.clone(),
+                        font_size: FONT_SIZE,
+                        color,
+                    },
+                ));
+            }
+        });
+}
+
+fn entry_line(entry: &LeaderboardEntry) -> String {
+    let check = match entry.verification {
+        Verification::Verified => "",
+        Verification::Pending => " (checking)",
+        Verification::Unverified => " (no replay)",
+        Verification::Mismatch => " (rejected)",
+    };
+    format!(
+        "#{:<3} {:<12} {:>8}{}",
+        entry.rank, entry.player, entry.score, check
+    )
+}
+
+fn cleanup_system(mut commands: Commands, query: Query<Entity, With<LeaderboardNode>>) {
+    for e in query.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
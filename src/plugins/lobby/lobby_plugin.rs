@@ -0,0 +1,321 @@
+use bevy::prelude::*;
+use instant::Instant;
+
+use crate::{
+    components::root_node::RootNode,
+    net::{Handshake, UdpTransport, DEFAULT_PORT},
+    plugins::{
+        delayed_state_switch_plugin::StateActionButton,
+        net_plugin::NetSession,
+        ui_interaction_plugin::{UiButton, UiButtonColors},
+    },
+    resources::{
+        daily_challenge::DailyChallenge,
+        fonts::Fonts,
+        game_rng::SeedSettings,
+        launch_options::{LaunchOptions, LobbyRole},
+        run_settings::{GameMode, RunSettings},
+    },
+    AppState,
+};
+
+/// Host the address points at until another one is typed in.
+const DEFAULT_HOST: &str = "127.0.0.1";
+/// Longest address that can be typed in.
+const MAX_ADDRESS_LEN: usize = 64;
+
+/// The address typed in and the connection being set up, if any.
+#[derive(Resource)]
+struct Lobby {
+    address: String,
+    connecting: Option<(UdpTransport, Handshake)>,
+    status: String,
+}
+
+#[derive(Component, Clone, Copy)]
+enum LobbyButton {
+    Host,
+    Join,
+}
+
+#[derive(Component)]
+struct AddressText;
+
+#[derive(Component)]
+struct LobbyStatusText;
+
+/// Screen to host an online co-op run or join one by its address.
+pub struct LobbyPlugin;
+
+impl Plugin for LobbyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::Lobby).with_system(setup_system))
+            .add_system_set(
+                SystemSet::on_update(AppState::Lobby)
+                    .with_system(address_input_system)
+                    .with_system(lobby_button_system)
+                    .with_system(handshake_system.after(lobby_button_system))
+                    .with_system(lobby_text_system.after(handshake_system)),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Lobby).with_system(cleanup_system));
+    }
+}
+
+/// Hosts or joins right away when the command line asked for it, once.
+fn setup_system(
+    mut commands: Commands,
+    fonts: Res<Fonts>,
+    mut options: ResMut<LaunchOptions>,
+    (settings, seed_settings, daily): (
+        Res<RunSettings>,
+        Res<SeedSettings>,
+        Option<Res<DailyChallenge>>,
+    ),
+) {
+    let mut lobby = Lobby {
+        address: format!("{}:{}", DEFAULT_HOST, DEFAULT_PORT),
+        connecting: None,
+        status: "Host a run on the address' port or join the run hosted there".to_string(),
+    };
+    match options.lobby.take() {
+        Some(LobbyRole::Host(port)) => {
+            lobby.address = format!("{}:{}", DEFAULT_HOST, port);
+            lobby.host(&settings, &seed_settings, daily.as_deref());
+        }
+        Some(LobbyRole::Join(address)) => {
+            lobby.address = address;
+            lobby.join();
+        }
+        None => (),
+    }
+    commands.spawn(Camera2dBundle::default());
+    spawn_screen(&mut commands, &fonts, &lobby);
+    commands.insert_resource(lobby);
+}
+
+impl Lobby {
+    fn host(
+        &mut self,
+        settings: &RunSettings,
+        seed_settings: &SeedSettings,
+        daily: Option<&DailyChallenge>,
+    ) {
+        let port = self
+            .address
+            .rsplit_once(':')
+            .and_then(|(_, port)| port.parse().ok())
+            .unwrap_or(DEFAULT_PORT);
+        let seed = match (settings.mode, daily) {
+            (GameMode::DailyChallenge, Some(daily)) => daily.seed,
+            _ => seed_settings.next_seed(),
+        };
+        let settings = RunSettings {
+            players: 2,
+            ..*settings
+        };
+        match UdpTransport::host(port) {
+            Ok(transport) => {
+                self.connecting = Some((transport, Handshake::host(seed, settings)));
+                self.status = format!("Waiting for the other player on port {}...", port);
+            }
+            Err(err) => self.fail(format!("Can't host on port {}: {}", port, err)),
+        }
+    }
+
+    fn join(&mut self) {
+        match UdpTransport::join(&self.address) {
+            Ok(transport) => {
+                self.connecting = Some((transport, Handshake::join(Instant::now())));
+                self.status = format!("Joining {}...", self.address);
+            }
+            Err(err) => self.fail(format!("Can't join {}: {}", self.address, err)),
+        }
+    }
+
+    fn fail(&mut self, status: String) {
+        self.connecting = None;
+        self.status = status;
+    }
+}
+
+fn address_input_system(
+    mut lobby: ResMut<Lobby>,
+    mut characters: EventReader<ReceivedCharacter>,
+    keys: Res<Input<KeyCode>>,
+) {
+    for event in characters.iter() {
+        if event.char.is_ascii_graphic() && lobby.address.len() < MAX_ADDRESS_LEN {
+            lobby.address.push(event.char);
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        lobby.address.pop();
+    }
+}
+
+fn lobby_button_system(
+    mut lobby: ResMut<Lobby>,
+    (settings, seed_settings, daily): (
+        Res<RunSettings>,
+        Res<SeedSettings>,
+        Option<Res<DailyChallenge>>,
+    ),
+    mut query: Query<(&Interaction, &LobbyButton, &mut UiButton), Changed<Interaction>>,
+) {
+    for (interaction, button, mut ui_button) in query.iter_mut() {
+        if *interaction == Interaction::Clicked {
+            ui_button.clicked = true;
+            continue;
+        }
+        if !ui_button.clicked {
+            continue;
+        }
+        ui_button.clicked = false;
+        // a click starts over, dropping the connection being set up
+        lobby.connecting = None;
+        match button {
+            LobbyButton::Host => lobby.host(&settings, &seed_settings, daily.as_deref()),
+            LobbyButton::Join => lobby.join(),
+        }
+    }
+}
+
+/// Starts the run once the peers agree on it.
+fn handshake_system(
+    mut commands: Commands,
+    mut lobby: ResMut<Lobby>,
+    mut settings: ResMut<RunSettings>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    let (transport, handshake) = match &mut lobby.connecting {
+        Some(connecting) => connecting,
+        None => return,
+    };
+    match handshake.poll(transport, Instant::now()) {
+        Ok(Some(start)) => {
+            let (transport, _) = lobby.connecting.take().unwrap();
+            commands.insert_resource(NetSession::new(Box::new(transport), start, &mut settings));
+            app_state.set(AppState::Playing).unwrap();
+        }
+        Ok(None) => (),
+        Err(err) => lobby.fail(format!("Connection failed: {}", err)),
+    }
+}
+
+fn lobby_text_system(
+    lobby: Res<Lobby>,
+    mut address_query: Query<&mut Text, (With<AddressText>, Without<LobbyStatusText>)>,
+    mut status_query: Query<&mut Text, (With<LobbyStatusText>, Without<AddressText>)>,
+) {
+    if !lobby.is_changed() {
+        return;
+    }
+    for mut text in address_query.iter_mut() {
+        text.sections[0].value = address_text(&lobby);
+    }
+    for mut text in status_query.iter_mut() {
+        text.sections[0].value = lobby.status.clone();
+    }
+}
+
+fn address_text(lobby: &Lobby) -> String {
+    format!("Address: {}_", lobby.address)
+}
+
+fn cleanup_system(
+    mut commands: Commands,
+    root_query: Query<Entity, With<RootNode>>,
+    camera_query: Query<Entity, With<Camera2d>>,
+) {
+    commands.remove_resource::<Lobby>();
+    for cam in camera_query.iter() {
+        commands.entity(cam).despawn();
+    }
+    if let Ok(root) = root_query.get_single() {
+        commands.entity(root).despawn_recursive();
+    }
+}
+
+fn spawn_screen(commands: &mut Commands, fonts: &Fonts, lobby: &Lobby) {
+    let colors = UiButtonColors::default();
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            background_color: Color::GRAY.into(),
+            ..Default::default()
+        })
+        .insert(RootNode)
+        .with_children(|parent| {
+            parent.spawn(get_text("Online co-op", fonts.bold.clone(), 50.));
+            parent
+                .spawn(get_text(address_text(lobby), fonts.regular.clone(), 30.))
+                .insert(AddressText);
+            parent.spawn(NodeBundle::default()).with_children(|row| {
+                for (button, text) in [(LobbyButton::Host, "Host"), (LobbyButton::Join, "Join")] {
+                    row.spawn(get_button(colors.none_color))
+                        .insert(UiButton::new(text, colors))
+                        .insert(button)
+                        .with_children(|button| {
+                            button.spawn(get_button_text(text, fonts.regular.clone()));
+                        });
+                }
+            });
+            parent
+                .spawn(get_text(lobby.status.clone(), fonts.italic.clone(), 24.))
+                .insert(LobbyStatusText);
+            parent
+                .spawn(get_button(colors.none_color))
+                .insert(UiButton::new("Back", colors))
+                .insert(StateActionButton::MainMenu)
+                .with_children(|button| {
+                    button.spawn(get_button_text("Back", fonts.regular.clone()));
+                });
+        });
+}
+
+fn get_text(text: impl Into<String>, font: Handle<Font>, font_size: f32) -> TextBundle {
+    TextBundle::from_section(
+        text,
+        TextStyle {
+            font,
+            font_size,
+            color: Color::WHITE,
+        },
+    )
+    .with_style(Style {
+        margin: UiRect::all(Val::Px(6.)),
+        ..Default::default()
+    })
+}
+
+fn get_button_text(text: impl Into<String>, font: Handle<Font>) -> TextBundle {
+    TextBundle::from_section(
+        text,
+        TextStyle {
+            font,
+            font_size: 24.,
+            color: Color::BLACK,
+        },
+    )
+}
+
+fn get_button(background_color: Color) -> ButtonBundle {
+    ButtonBundle {
+        style: Style {
+            padding: UiRect::new(Val::Px(16.), Val::Px(16.), Val::Px(6.), Val::Px(6.)),
+            margin: UiRect::all(Val::Px(6.)),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..Default::default()
+        },
+        background_color: background_color.into(),
+        ..Default::default()
+    }
+}
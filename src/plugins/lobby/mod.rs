@@ -0,0 +1 @@
+pub mod lobby_plugin;
@@ -1,5 +1,11 @@
+pub mod bevy_game_plugins;
 pub mod delayed_state_switch_plugin;
 pub mod game;
+pub mod game_over;
+pub mod high_scores;
+pub mod leaderboard_plugin;
+pub mod lobby;
+pub mod net_plugin;
 pub mod resources_plugin;
 pub mod sprite_animation_plugin;
 pub mod start_menu;
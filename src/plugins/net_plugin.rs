@@ -0,0 +1,295 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use instant::Instant;
+
+use crate::{
+    net::{Message, RollbackSession, SessionStart, Transport, MAX_PREDICTION},
+    plugins::game::{
+        game_mode_plugin::RunOutcome,
+        game_plugin::{SimulationSet, SimulationStage, SimulationSteps},
+        movement_plugin::GameClock,
+        player_plugin::PlayerBindings,
+        rollback_plugin::{load_world, save_world, world_checksum, WorldSnapshot},
+    },
+    resources::{
+        fonts::Fonts,
+        player_input::{InputActions, PlayerInputs, ScriptedInput},
+        run_settings::RunSettings,
+    },
+    AppState,
+};
+
+/// Times the goodbye is sent, in case some get lost.
+const LEAVE_PACKETS: usize = 3;
+const STATUS_FONT_SIZE: f32 = 24.;
+
+/// Present while the run is played online, the other player's ship is
+/// simulated from the inputs the peer sends.
+#[derive(Resource)]
+pub struct NetSession {
+    transport: Box<dyn Transport>,
+    session: RollbackSession,
+    start: SessionStart,
+    /// Settings of the player's own runs, put back once the online run is over.
+    restore_settings: RunSettings,
+    bindings: PlayerBindings,
+    /// State after each recent step, to roll back to.
+    snapshots: BTreeMap<u64, WorldSnapshot>,
+    /// Simulation steps to run this frame.
+    steps: u64,
+}
+
+impl NetSession {
+    /// Switches the run settings to the ones agreed on for the session.
+    pub fn new(
+        transport: Box<dyn Transport>,
+        start: SessionStart,
+        settings: &mut RunSettings,
+    ) -> Self {
+        let restore_settings = std::mem::replace(settings, start.settings);
+        Self {
+            transport,
+            session: RollbackSession::new(start.player, Instant::now()),
+            start,
+            restore_settings,
+            // the local player has the whole keyboard
+            bindings: PlayerBindings::new(1),
+            snapshots: BTreeMap::new(),
+            steps: 0,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.start.seed
+    }
+
+    pub fn session(&self) -> &RollbackSession {
+        &self.session
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    fn poll(&mut self, now: Instant) {
+        loop {
+            let packet = match self.transport.recv() {
+                Ok(Some(packet)) => packet,
+                Ok(None) => break,
+                Err(err) => {
+                    warn!("Failed to receive from the other player: {}", err);
+                    break;
+                }
+            };
+            match Message::decode(&packet) {
+                // the welcome got lost, the host says it again
+                Ok(Message::Hello) if self.start.player.0 == 0 => {
+                    self.send(&Message::Welcome {
+                        seed: self.start.seed,
+                        settings: self.start.settings,
+                    });
+                }
+                Ok(message) => self.session.receive(message, now),
+                Err(err) => warn!("Dropped a packet from the other player: {}", err),
+            }
+        }
+    }
+
+    fn send(&mut self, message: &Message) {
+        if let Err(err) = self.transport.send(&message.encode()) {
+            warn!("Failed to send to the other player: {}", err);
+        }
+    }
+}
+
+/// Settings of the player's own runs, waiting for the game over screen of
+/// the online run to close.
+#[derive(Resource)]
+struct RestoreSettings(RunSettings);
+
+#[derive(Component)]
+struct NetStatusText;
+
+/// Online runs: exchanges inputs with the other peer every frame, rolls the
+/// run back when the guess of the peer's inputs was wrong and runs the steps
+/// the session allows. Does nothing without a `NetSession`.
+pub struct NetPlugin;
+
+impl Plugin for NetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(CoreStage::PreUpdate, net_sync_system.at_end())
+            .add_system_to_stage(CoreStage::PostUpdate, net_status_system)
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(setup_system))
+            .add_system_set_to_stage(
+                SimulationStage,
+                SystemSet::new()
+                    .with_run_criteria(SimulationSteps)
+                    .with_system(net_snapshot_system.at_start()),
+            )
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationSet::NetInput
+                    .system_set()
+                    .with_system(net_input_system),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(cleanup_system))
+            .add_system_set(
+                SystemSet::on_exit(AppState::GameOver).with_system(restore_settings_system),
+            );
+    }
+}
+
+fn setup_system(mut commands: Commands, fonts: Res<Fonts>, net: Option<Res<NetSession>>) {
+    if net.is_none() {
+        return;
+    }
+    commands
+        .spawn(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: fonts.bold.clone(),
+                    font_size: STATUS_FONT_SIZE,
+                    color: Color::ORANGE,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(10.),
+                    bottom: Val::Px(10.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }),
+        )
+        .insert(NetStatusText);
+}
+
+fn cleanup_system(
+    mut commands: Commands,
+    net: Option<ResMut<NetSession>>,
+    query: Query<Entity, With<NetStatusText>>,
+) {
+    for e in query.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+    if let Some(mut net) = net {
+        for _ in 0..LEAVE_PACKETS {
+            net.send(&Message::Leave);
+        }
+        // the game over screen still shows the online run
+        commands.insert_resource(RestoreSettings(net.restore_settings));
+        commands.remove_resource::<NetSession>();
+    }
+}
+
+fn restore_settings_system(
+    mut commands: Commands,
+    restore: Option<Res<RestoreSettings>>,
+    mut settings: ResMut<RunSettings>,
+) {
+    if let Some(restore) = restore {
+        *settings = restore.0;
+        commands.remove_resource::<RestoreSettings>();
+    }
+}
+
+/// Takes in the peer's inputs, rolls back when needed and sends the local
+/// inputs of the next step, once per frame before the steps run.
+fn net_sync_system(world: &mut World) {
+    if !world.contains_resource::<NetSession>()
+        || *world.resource::<State<AppState>>().current() != AppState::Playing
+    {
+        return;
+    }
+    world.resource_scope(|world, mut net: Mut<NetSession>| {
+        let now = Instant::now();
+        net.poll(now);
+        let tick = world.resource::<GameClock>().tick;
+        net.steps = 0;
+        if let Some(from) = net.session.take_rollback() {
+            match net.snapshots.get(&(from - 1)) {
+                Some(snapshot) => {
+                    load_world(world, snapshot);
+                    net.snapshots.split_off(&from);
+                    net.steps = tick - (from - 1);
+                }
+                None => error!("No state to roll back to before step {}", from),
+            }
+        }
+
+        if net.session.is_disconnected(now) {
+            if !world.contains_resource::<RunOutcome>() {
+                world.insert_resource(RunOutcome::Disconnected);
+                let _ = world
+                    .resource_mut::<State<AppState>>()
+                    .set(AppState::GameOver);
+            }
+        } else if net.session.can_advance(tick) {
+            let actions = local_actions(world, &net.bindings);
+            net.session.add_local(tick, actions);
+            net.steps += 1;
+        }
+        let message = net.session.message();
+        net.send(&message);
+    });
+}
+
+fn local_actions(world: &World, bindings: &PlayerBindings) -> InputActions {
+    if let Some(scripted) = world.get_resource::<ScriptedInput>() {
+        return scripted.0.first().copied().unwrap_or_default();
+    }
+    bindings.actions(
+        world.resource(),
+        world.resource(),
+        (world.resource(), world.resource()),
+    )[0]
+}
+
+/// Saves the state at the start of every step, along with its hash for the peer to compare.
+fn net_snapshot_system(world: &mut World) {
+    if !world.contains_resource::<NetSession>() {
+        return;
+    }
+    let tick = world.resource::<GameClock>().tick;
+    let snapshot = save_world(world);
+    let checksum = world_checksum(world);
+    let mut net = world.resource_mut::<NetSession>();
+    net.session.record_checksum(tick, checksum);
+    net.snapshots.insert(tick, snapshot);
+    // rollbacks never go further back than the steps simulated on a guess
+    let oldest = tick.saturating_sub(MAX_PREDICTION + 1);
+    net.snapshots = net.snapshots.split_off(&oldest);
+}
+
+fn net_input_system(
+    clock: Res<GameClock>,
+    net: Option<ResMut<NetSession>>,
+    mut input: ResMut<PlayerInputs>,
+) {
+    if let Some(mut net) = net {
+        input.push(&net.session.inputs(clock.tick));
+    }
+}
+
+fn net_status_system(
+    net: Option<Res<NetSession>>,
+    mut query: Query<&mut Text, With<NetStatusText>>,
+) {
+    let net = match net {
+        Some(net) => net,
+        None => return,
+    };
+    let status = match (net.session.desync(), net.steps) {
+        (Some(tick), _) => format!("Out of sync with the other player since step {}", tick),
+        (None, 0) => "Waiting for the other player...".to_string(),
+        (None, _) => String::new(),
+    };
+    for mut text in query.iter_mut() {
+        if text.sections[0].value != status {
+            text.sections[0].value = status.clone();
+        }
+    }
+}
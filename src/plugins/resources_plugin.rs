@@ -1,12 +1,18 @@
 use bevy::prelude::*;
 
-use crate::resources::{fonts::Fonts, textures::Textures, viewport_size::ViewportSize};
+use crate::resources::{
+    bullet_patterns::BulletPatterns, daily_challenge::DailyResults,
+    enemy_archetypes::EnemyArchetypes, fonts::Fonts, formations::Formations,
+    game_tuning::GameTuning, high_scores::HighScores, textures::Textures,
+    viewport_size::ViewportSize,
+};
 
 pub struct ResourcePlugin;
 
 impl Plugin for ResourcePlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(create_required_resources);
+        app.init_resource::<ViewportSize>()
+            .add_startup_system(create_required_resources);
     }
 }
 
@@ -14,14 +20,18 @@ fn create_required_resources(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     texture_atlases: ResMut<Assets<TextureAtlas>>,
-    mut windows: ResMut<Windows>,
+    tuning: Res<GameTuning>,
 ) {
-    // windows
-    let windows = windows.get_primary_mut().unwrap();
-    windows.set_resolution(1000., 600.);
-
     // insert resources
-    commands.insert_resource(ViewportSize { w: 1000., h: 600. });
-    commands.insert_resource(Textures::init(&asset_server, texture_atlases));
+    commands.insert_resource(Textures::init(
+        &asset_server,
+        texture_atlases,
+        tuning.sprite_scale,
+    ));
     commands.insert_resource(Fonts::init(&asset_server));
+    commands.insert_resource(EnemyArchetypes::init());
+    commands.insert_resource(BulletPatterns::init());
+    commands.insert_resource(Formations::init());
+    commands.insert_resource(HighScores::load());
+    commands.insert_resource(DailyResults::load());
 }
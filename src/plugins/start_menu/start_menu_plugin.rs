@@ -6,20 +6,67 @@ use crate::{
         delayed_state_switch_plugin::StateActionButton,
         ui_interaction_plugin::{UiButton, UiButtonColors},
     },
-    resources::fonts::Fonts,
+    resources::{
+        fonts::Fonts,
+        player_input::MAX_PLAYERS,
+        run_settings::{Difficulty, GameMode, RunSettings},
+    },
     AppState,
 };
 
+/// Menu buttons cycling through the settings of the next run.
+#[derive(Component, Clone, Copy)]
+enum RunSettingButton {
+    Mode,
+    Difficulty,
+    Adaptive,
+    Players,
+}
+
+impl RunSettingButton {
+    fn text(&self, settings: &RunSettings) -> String {
+        match self {
+            RunSettingButton::Mode => settings.mode.label().to_string(),
+            RunSettingButton::Difficulty => settings.difficulty.label().to_string(),
+            RunSettingButton::Adaptive => match settings.adaptive {
+                true => "Adaptive: on".to_string(),
+                false => "Adaptive: off".to_string(),
+            },
+            RunSettingButton::Players => format!("Players: {}", settings.players),
+        }
+    }
+
+    fn apply(&self, settings: &mut RunSettings) {
+        match self {
+            RunSettingButton::Mode => {
+                let index = settings.mode as usize + 1;
+                settings.mode = GameMode::ALL[index % GameMode::ALL.len()];
+            }
+            RunSettingButton::Difficulty => {
+                let index = settings.difficulty as usize + 1;
+                settings.difficulty = Difficulty::ALL[index % Difficulty::ALL.len()];
+            }
+            RunSettingButton::Adaptive => settings.adaptive = !settings.adaptive,
+            RunSettingButton::Players => {
+                settings.players = settings.players % MAX_PLAYERS as u8 + 1;
+            }
+        }
+    }
+}
+
 pub struct StartMenuPlugin;
 
 impl Plugin for StartMenuPlugin {
     fn build(&self, app: &mut App) {
         app.add_system_set(SystemSet::on_enter(AppState::MainMenu).with_system(setup_system))
+            .add_system_set(
+                SystemSet::on_update(AppState::MainMenu).with_system(run_setting_button_system),
+            )
             .add_system_set(SystemSet::on_exit(AppState::MainMenu).with_system(cleanup_system));
     }
 }
 
-fn setup_system(mut commands: Commands, fonts: Res<Fonts>) {
+fn setup_system(mut commands: Commands, fonts: Res<Fonts>, settings: Res<RunSettings>) {
     let colors = UiButtonColors::default();
     commands.spawn(Camera2dBundle::default());
     commands
@@ -34,6 +81,36 @@ fn setup_system(mut commands: Commands, fonts: Res<Fonts>) {
                     UiButton::new("Start game!", colors),
                     &fonts,
                 ))
+                .with_children(add_button(
+                    StateActionButton::Lobby,
+                    UiButton::new("Online co-op", colors),
+                    &fonts,
+                ))
+                .with_children(add_button(
+                    RunSettingButton::Mode,
+                    UiButton::new(RunSettingButton::Mode.text(&settings), colors),
+                    &fonts,
+                ))
+                .with_children(add_button(
+                    RunSettingButton::Difficulty,
+                    UiButton::new(RunSettingButton::Difficulty.text(&settings), colors),
+                    &fonts,
+                ))
+                .with_children(add_button(
+                    RunSettingButton::Adaptive,
+                    UiButton::new(RunSettingButton::Adaptive.text(&settings), colors),
+                    &fonts,
+                ))
+                .with_children(add_button(
+                    RunSettingButton::Players,
+                    UiButton::new(RunSettingButton::Players.text(&settings), colors),
+                    &fonts,
+                ))
+                .with_children(add_button(
+                    StateActionButton::HighScores,
+                    UiButton::new("High scores", colors),
+                    &fonts,
+                ))
                 .with_children(add_button(
                     StateActionButton::Exit,
                     UiButton::new("Exit to OS", colors),
@@ -55,8 +132,34 @@ fn cleanup_system(
     }
 }
 
+fn run_setting_button_system(
+    mut settings: ResMut<RunSettings>,
+    mut query: Query<
+        (&Interaction, &RunSettingButton, &mut UiButton, &Children),
+        Changed<Interaction>,
+    >,
+    mut text_query: Query<&mut Text>,
+) {
+    for (interaction, button, mut ui_button, children) in query.iter_mut() {
+        if *interaction == Interaction::Clicked {
+            ui_button.clicked = true;
+            continue;
+        }
+        if !ui_button.clicked {
+            continue;
+        }
+        ui_button.clicked = false;
+        button.apply(&mut settings);
+        ui_button.text = button.text(&settings);
+        let mut texts = text_query.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.sections[0].value = ui_button.text.clone();
+        }
+    }
+}
+
 fn add_button(
-    button: StateActionButton,
+    button: impl Component + Copy,
     menu_button: UiButton,
     fonts: &Res<Fonts>,
 ) -> impl Fn(&mut ChildBuilder) {
@@ -100,7 +203,7 @@ fn get_button_text(text: impl Into<String>, font: Handle<Font>) -> TextBundle {
 fn get_button(background_color: Color) -> ButtonBundle {
     ButtonBundle {
         style: Style {
-            size: Size::new(Val::Px(250.), Val::Px(80.)),
+            size: Size::new(Val::Px(250.), Val::Px(60.)),
             align_items: AlignItems::Center,
             justify_content: JustifyContent::Center,
             ..Default::default()
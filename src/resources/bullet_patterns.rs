@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+const BULLET_PATTERNS: &str = include_str!("../../assets/data/patterns.ron");
+
+#[derive(Deserialize, Clone)]
+pub enum EmitterPattern {
+    /// Evenly spaced shots around the emitter.
+    Radial { count: u32 },
+    /// Radial burst whose base angle keeps rotating.
+    Spiral { arms: u32, angular_speed: f32 },
+    /// Shots spread over `arc` centered on the player.
+    AimedFan { count: u32, arc: f32 },
+    /// Downward shots sweeping back and forth over `arc`.
+    Wave {
+        count: u32,
+        arc: f32,
+        frequency: f32,
+    },
+}
+
+#[derive(Deserialize, Clone)]
+pub struct SubEmitterDef {
+    pub pattern: String,
+    pub delay_s: f32,
+    pub bursts: u32,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct BulletPattern {
+    pub pattern: EmitterPattern,
+    pub interval_s: f32,
+    pub projectile_speed: f32,
+    pub damage: f32,
+    pub scale: f32,
+    pub sub_emitter: Option<SubEmitterDef>,
+}
+
+#[derive(Resource, Deref)]
+pub struct BulletPatterns(pub HashMap<String, BulletPattern>);
+
+impl BulletPatterns {
+    pub fn init() -> Self {
+        Self(ron::from_str(BULLET_PATTERNS).expect("invalid bullet pattern data"))
+    }
+}
@@ -0,0 +1,133 @@
+use std::{collections::BTreeMap, fs};
+
+use bevy::prelude::*;
+use instant::SystemTime;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use super::run_settings::Modifiers;
+
+const DAILY_RESULTS_PATH: &str = "daily.ron";
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+/// Keeps the challenge seeds apart from runs seeded with the plain day number.
+const DAILY_SEED_STREAM: u64 = 0xda11;
+
+/// Seed and modifiers of a day's challenge, the same for everyone playing on that date.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DailyChallenge {
+    /// Days since 1970-01-01, in UTC.
+    pub day: u64,
+    pub seed: u64,
+    pub modifiers: Modifiers,
+}
+
+impl DailyChallenge {
+    pub fn for_day(day: u64) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(day);
+        rng.set_stream(DAILY_SEED_STREAM);
+        let seed = rng.gen::<u32>() as u64;
+        // every day gets at least one modifier
+        let bits = rng.gen_range(1..=Modifiers::all().bits());
+        Self {
+            day,
+            seed,
+            modifiers: Modifiers::from_bits_truncate(bits),
+        }
+    }
+
+    pub fn today() -> Self {
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        Self::for_day(now.as_secs() / SECONDS_PER_DAY)
+    }
+
+    /// The day as `YYYY-MM-DD`.
+    pub fn date(&self) -> String {
+        // days to civil date, after Howard Hinnant's `civil_from_days`
+        let z = self.day as i64 + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DailyResult {
+    pub score: u32,
+    pub seed: u64,
+    /// Path of the replay file recorded for the run.
+    pub replay: Option<String>,
+}
+
+/// The scored attempt of every day played, kept apart from the high scores.
+#[derive(Resource, Serialize, Deserialize, Default)]
+pub struct DailyResults {
+    /// By `YYYY-MM-DD` date, so the oldest day comes first.
+    results: BTreeMap<String, DailyResult>,
+}
+
+impl DailyResults {
+    /// Reads the saved results, a missing or broken file starts empty.
+    pub fn load() -> Self {
+        fs::read_to_string(DAILY_RESULTS_PATH)
+            .ok()
+            .and_then(|data| ron::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        let data = match ron::ser::to_string_pretty(self, Default::default()) {
+            Ok(data) => data,
+            Err(err) => return warn!("Failed to serialize daily results: {}", err),
+        };
+        if let Err(err) = fs::write(DAILY_RESULTS_PATH, data) {
+            warn!("Failed to save daily results: {}", err);
+        }
+    }
+
+    pub fn get(&self, challenge: &DailyChallenge) -> Option<&DailyResult> {
+        self.results.get(&challenge.date())
+    }
+
+    /// Most recent day first.
+    pub fn recent(&self) -> impl Iterator<Item = (&String, &DailyResult)> {
+        self.results.iter().rev()
+    }
+
+    /// Takes the day's attempt, starting with no score so quitting still uses it
+    /// up. `false` if the day was played already.
+    pub fn start_attempt(&mut self, challenge: &DailyChallenge) -> bool {
+        if self.get(challenge).is_some() {
+            return false;
+        }
+        self.results.insert(
+            challenge.date(),
+            DailyResult {
+                score: 0,
+                seed: challenge.seed,
+                replay: None,
+            },
+        );
+        true
+    }
+
+    pub fn finish_attempt(
+        &mut self,
+        challenge: &DailyChallenge,
+        score: u32,
+        replay: Option<String>,
+    ) {
+        if let Some(result) = self.results.get_mut(&challenge.date()) {
+            result.score = score;
+            result.replay = replay;
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+const ENEMY_ARCHETYPES: &str = include_str!("../../assets/data/enemies.ron");
+
+#[derive(Component, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EnemyBehavior {
+    /// Stays where it spawned.
+    Drifter,
+    /// Flies straight into the player.
+    Kamikaze,
+    /// Stays in place, rotates to track the player and fires.
+    Turret,
+    /// Slowly approaches the player while firing.
+    Tank,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct EnemySplit {
+    pub into: String,
+    pub count: u32,
+}
+
+/// State machine tuning, distances are in pixels and speeds relative to the
+/// base movement speed.
+#[derive(Deserialize, Clone, Copy)]
+pub struct AiProfile {
+    pub idle_s: f32,
+    pub patrol_speed: f32,
+    pub sight_range: f32,
+    pub attack_range: f32,
+    pub attack_speed: f32,
+    /// Health fraction at which the enemy flees once, zero never flees.
+    pub flee_health: f32,
+    pub flee_s: f32,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct EnemyArchetype {
+    pub name: String,
+    pub sprite: String,
+    pub color: (f32, f32, f32),
+    pub scale: f32,
+    pub health: f32,
+    pub speed: f32,
+    pub score: u32,
+    pub spawn_weight: u32,
+    pub behavior: EnemyBehavior,
+    /// Name of the bullet pattern fired by this enemy.
+    pub emitter: Option<String>,
+    pub split: Option<EnemySplit>,
+    pub ai: Option<AiProfile>,
+}
+
+impl EnemyArchetype {
+    pub fn color(&self) -> Color {
+        Color::rgb(self.color.0, self.color.1, self.color.2)
+    }
+
+    pub fn respawns(&self) -> bool {
+        self.spawn_weight > 0
+    }
+}
+
+#[derive(Resource, Deref)]
+pub struct EnemyArchetypes(pub Vec<EnemyArchetype>);
+
+impl EnemyArchetypes {
+    pub fn init() -> Self {
+        Self(ron::from_str(ENEMY_ARCHETYPES).expect("invalid enemy archetype data"))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&EnemyArchetype> {
+        self.iter().find(|a| a.name == name)
+    }
+
+    pub fn pick(&self, rand: &mut impl Rng) -> &EnemyArchetype {
+        let total: u32 = self.iter().map(|a| a.spawn_weight).sum();
+        let mut roll = rand.gen_range(0..total.max(1));
+        for archetype in self.iter() {
+            if roll < archetype.spawn_weight {
+                return archetype;
+            }
+            roll -= archetype.spawn_weight;
+        }
+        &self[0]
+    }
+}
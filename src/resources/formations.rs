@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+const FORMATIONS: &str = include_str!("../../assets/data/formations.ron");
+
+/// Samples per curve segment used to estimate its length.
+const SEGMENT_LENGTH_SAMPLES: u32 = 16;
+
+#[derive(Deserialize, Clone)]
+pub enum PathCurve {
+    /// Cubic Bezier segments sharing end points, `3n + 1` points.
+    Bezier(Vec<(f32, f32)>),
+    /// Catmull-Rom spline passing through every point.
+    CatmullRom(Vec<(f32, f32)>),
+}
+
+#[derive(Deserialize, Clone, Copy)]
+pub enum FormationShape {
+    V { count: u32, spacing: f32 },
+    Line { count: u32, spacing: f32 },
+    Grid { rows: u32, cols: u32, spacing: f32 },
+}
+
+impl FormationShape {
+    /// Slot offsets from the formation anchor, in pixels.
+    pub fn slots(&self) -> Vec<Vec2> {
+        match *self {
+            FormationShape::V { count, spacing } => (0..count)
+                .map(|i| {
+                    let rank = i.div_ceil(2) as f32;
+                    let side = if i % 2 == 0 { 1. } else { -1. };
+                    Vec2::new(side * rank * spacing, rank * spacing * 0.6)
+                })
+                .collect(),
+            FormationShape::Line { count, spacing } => (0..count)
+                .map(|i| Vec2::new((i as f32 - (count - 1) as f32 / 2.) * spacing, 0.))
+                .collect(),
+            FormationShape::Grid {
+                rows,
+                cols,
+                spacing,
+            } => (0..rows * cols)
+                .map(|i| {
+                    let (row, col) = ((i / cols) as f32, (i % cols) as f32);
+                    Vec2::new(
+                        (col - (cols - 1) as f32 / 2.) * spacing,
+                        (row - (rows - 1) as f32 / 2.) * spacing,
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct FormationDef {
+    pub shape: FormationShape,
+    pub archetype: String,
+    /// Entry path, members on odd slots fly it mirrored when `mirror` is set.
+    pub path: String,
+    pub mirror: bool,
+    /// Speed of the members along the path, relative to the base movement speed.
+    pub speed: f32,
+    pub spawn_interval_s: f32,
+    /// Vertical anchor position, -1 bottom to 1 top of the viewport.
+    pub anchor_y: f32,
+    pub sway_speed: f32,
+    pub sway_amplitude: f32,
+}
+
+#[derive(Deserialize)]
+struct FormationData {
+    paths: HashMap<String, PathCurve>,
+    formations: HashMap<String, FormationDef>,
+}
+
+/// Flight path in viewport units, -1 to 1 on both axes, made of cubic Bezier segments.
+pub struct FlightPath {
+    segments: Vec<[Vec2; 4]>,
+    lengths: Vec<f32>,
+}
+
+impl FlightPath {
+    fn new(curve: &PathCurve) -> Self {
+        let to_vec = |points: &Vec<(f32, f32)>| -> Vec<Vec2> {
+            points.iter().map(|(x, y)| Vec2::new(*x, *y)).collect()
+        };
+        let segments: Vec<[Vec2; 4]> = match curve {
+            PathCurve::Bezier(points) => to_vec(points)
+                .windows(4)
+                .step_by(3)
+                .map(|p| [p[0], p[1], p[2], p[3]])
+                .collect(),
+            PathCurve::CatmullRom(points) => {
+                let p = to_vec(points);
+                (0..p.len().saturating_sub(1))
+                    .map(|i| {
+                        let prev = p[i.saturating_sub(1)];
+                        let next = p[(i + 2).min(p.len() - 1)];
+                        [
+                            p[i],
+                            p[i] + (p[i + 1] - prev) / 6.,
+                            p[i + 1] - (next - p[i]) / 6.,
+                            p[i + 1],
+                        ]
+                    })
+                    .collect()
+            }
+        };
+        let lengths = segments
+            .iter()
+            .map(|segment| {
+                (1..=SEGMENT_LENGTH_SAMPLES)
+                    .map(|i| {
+                        let t = i as f32 / SEGMENT_LENGTH_SAMPLES as f32;
+                        let dt = 1. / SEGMENT_LENGTH_SAMPLES as f32;
+                        bezier(segment, t).distance(bezier(segment, t - dt))
+                    })
+                    .sum()
+            })
+            .collect();
+        Self { segments, lengths }
+    }
+
+    pub fn length(&self) -> f32 {
+        self.lengths.iter().sum()
+    }
+
+    /// Point at `distance` along the path, clamped to its ends.
+    pub fn point_at(&self, distance: f32) -> Vec2 {
+        let mut distance = distance.max(0.);
+        for (segment, length) in self.segments.iter().zip(self.lengths.iter()) {
+            if distance <= *length {
+                return bezier(segment, distance / length.max(f32::EPSILON));
+            }
+            distance -= length;
+        }
+        self.segments
+            .last()
+            .map_or(Vec2::ZERO, |segment| segment[3])
+    }
+}
+
+fn bezier(p: &[Vec2; 4], t: f32) -> Vec2 {
+    let u = 1. - t;
+    p[0] * u * u * u + p[1] * 3. * u * u * t + p[2] * 3. * u * t * t + p[3] * t * t * t
+}
+
+#[derive(Resource)]
+pub struct Formations {
+    pub paths: HashMap<String, FlightPath>,
+    pub formations: Vec<(String, FormationDef)>,
+}
+
+impl Formations {
+    pub fn init() -> Self {
+        let data: FormationData = ron::from_str(FORMATIONS).expect("invalid formation data");
+        let mut formations: Vec<_> = data.formations.into_iter().collect();
+        formations.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Self {
+            paths: data
+                .paths
+                .iter()
+                .map(|(name, curve)| (name.clone(), FlightPath::new(curve)))
+                .collect(),
+            formations,
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use bevy::prelude::*;
+use bitflags::bitflags;
+
+use crate::AppState;
+
+use super::{game_tuning::TuningPresets, viewport_size::ViewportSize};
+
+bitflags! {
+    /// Optional parts of the game, everything else is always part of a run.
+    pub struct Subsystems: u8 {
+        /// A window and rendering, without it the clock advances one fixed step per update.
+        const WINDOW = 1 << 0;
+        /// Start menu, game over and high score screens.
+        const MENUS = 1 << 1;
+        /// Score, lives and boss health overlays.
+        const HUD = 1 << 2;
+        const PAUSE_MENU = 1 << 3;
+    }
+}
+
+/// How the game is put together, passed to `BevyGamePlugins` and kept as a resource.
+///
+/// ```no_run
+/// use bevy::prelude::*;
+/// use bevy_game::{build_game_app, AppState, GameConfig, Subsystems};
+///
+/// let config = GameConfig::default()
+///     .with_viewport(800., 600.)
+///     .without(Subsystems::MENUS)
+///     .with_start_state(AppState::Playing);
+/// build_game_app(config)
+///     .add_system_set(SystemSet::on_update(AppState::Playing).with_system(|| {}))
+///     .run();
+/// ```
+#[derive(Resource, Debug, Clone)]
+pub struct GameConfig {
+    pub viewport: ViewportSize,
+    pub fullscreen: bool,
+    /// Base tuning and difficulty overlays runs are played with.
+    pub tuning: TuningPresets,
+    pub subsystems: Subsystems,
+    pub start_state: AppState,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            viewport: ViewportSize::default(),
+            fullscreen: false,
+            tuning: TuningPresets::default(),
+            subsystems: Subsystems::all(),
+            start_state: AppState::MainMenu,
+        }
+    }
+}
+
+impl GameConfig {
+    /// No window or menus, starting straight in a run.
+    pub fn headless() -> Self {
+        Self::default()
+            .without(Subsystems::WINDOW | Subsystems::MENUS)
+            .with_start_state(AppState::Playing)
+    }
+
+    pub fn with_viewport(mut self, w: f32, h: f32) -> Self {
+        self.viewport = ViewportSize { w, h };
+        self
+    }
+
+    pub fn with_fullscreen(mut self, fullscreen: bool) -> Self {
+        self.fullscreen = fullscreen;
+        self
+    }
+
+    pub fn with_sprite_scale(mut self, sprite_scale: f32) -> Self {
+        self.tuning.base.sprite_scale = sprite_scale;
+        self
+    }
+
+    pub fn with_tuning(mut self, tuning: TuningPresets) -> Self {
+        self.tuning = tuning;
+        self
+    }
+
+    pub fn with_subsystems(mut self, subsystems: Subsystems) -> Self {
+        self.subsystems = subsystems;
+        self
+    }
+
+    pub fn without(mut self, subsystems: Subsystems) -> Self {
+        self.subsystems.remove(subsystems);
+        self
+    }
+
+    pub fn with_start_state(mut self, start_state: AppState) -> Self {
+        self.start_state = start_state;
+        self
+    }
+}
@@ -0,0 +1,78 @@
+use bevy::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+/// Independent random sequences, so e.g. extra drop rolls don't shift enemy spawns.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RngStream {
+    Spawn,
+    Drops,
+    Ai,
+    /// Purely visual randomness that never feeds back into the simulation.
+    Effects,
+}
+
+impl RngStream {
+    const ALL: [RngStream; 4] = [
+        RngStream::Spawn,
+        RngStream::Drops,
+        RngStream::Ai,
+        RngStream::Effects,
+    ];
+}
+
+/// Seed source for new runs, a fixed seed makes every run replay the same way.
+#[derive(Resource, Default)]
+pub struct SeedSettings {
+    pub fixed_seed: Option<u64>,
+}
+
+impl SeedSettings {
+    pub fn next_seed(&self) -> u64 {
+        self.fixed_seed
+            .unwrap_or_else(|| rand::random::<u32>() as u64)
+    }
+}
+
+/// Seeded random number generator of the current run.
+#[derive(Resource, Clone)]
+pub struct GameRng {
+    seed: u64,
+    streams: Vec<ChaCha8Rng>,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        let streams = RngStream::ALL
+            .iter()
+            .map(|stream| {
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                rng.set_stream(*stream as u64);
+                rng
+            })
+            .collect();
+        Self { seed, streams }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn stream(&mut self, stream: RngStream) -> &mut ChaCha8Rng {
+        &mut self.streams[stream as usize]
+    }
+
+    /// How far each stream feeding the simulation has advanced, the effects one left out.
+    pub fn positions(&self) -> impl Iterator<Item = u128> + '_ {
+        RngStream::ALL
+            .iter()
+            .filter(|stream| **stream != RngStream::Effects)
+            .map(|stream| self.streams[*stream as usize].get_word_pos())
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
@@ -0,0 +1,288 @@
+use std::{collections::HashMap, fmt, fs, io, path::Path};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::run_settings::{Difficulty, GameMode, Modifiers};
+
+pub const TUNING_PATH: &str = "assets/data/tuning.ron";
+const TUNING: &str = include_str!("../../assets/data/tuning.ron");
+
+/// Balance values of a run, the base tuning with the difficulty overlay applied.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GameTuning {
+    /// Scale every sprite is drawn at, collision sizes follow it.
+    pub sprite_scale: f32,
+    /// Pixels per second covered at a velocity of one.
+    pub base_speed: f32,
+    /// Seconds of a fixed simulation step.
+    pub time_step: f32,
+    /// Multiplier on the cooldown of every player weapon.
+    pub fire_cooldown_scale: f32,
+    pub player_lives: u32,
+    /// Enemies queued at the start of a run.
+    pub initial_enemies: u16,
+    pub enemy_respawn_delay_s: f32,
+    /// Multiplier on how fast enemy ships fly.
+    pub enemy_speed_scale: f32,
+    /// Multiplier on how often enemy bullet patterns fire.
+    pub enemy_fire_rate_scale: f32,
+    pub enemy_projectile_speed_scale: f32,
+    /// Simulation steps each explosion frame is shown for.
+    pub explosion_frames_per_step: u32,
+    pub director: DirectorTuning,
+    pub modes: ModeTuning,
+}
+
+/// Bounds of the adaptive difficulty director. Spawn pressure divides the
+/// enemy respawn delay, it rises while the player kills faster than the
+/// target and drops with every hit the player takes.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DirectorTuning {
+    pub min_pressure: f32,
+    pub max_pressure: f32,
+    /// Seconds of play the recent performance is judged over.
+    pub window_s: f32,
+    /// Kills within the window that keep the pressure where it is.
+    pub target_kills: f32,
+    /// Pressure taken off the target per hit within the window.
+    pub hit_penalty: f32,
+    /// Largest pressure change per second.
+    pub adjust_per_s: f32,
+}
+
+impl Default for DirectorTuning {
+    fn default() -> Self {
+        Self {
+            min_pressure: 0.7,
+            max_pressure: 1.6,
+            window_s: 20.,
+            target_kills: 8.,
+            hit_penalty: 0.25,
+            adjust_per_s: 0.05,
+        }
+    }
+}
+
+/// Limits of the game modes with an end other than the last life.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModeTuning {
+    /// Seconds a time attack run lasts.
+    pub time_attack_s: f32,
+    /// Formation waves to clear in wave survival.
+    pub survival_waves: u32,
+    /// Seconds between the waves of wave survival.
+    pub survival_wave_interval_s: f32,
+}
+
+impl Default for ModeTuning {
+    fn default() -> Self {
+        Self {
+            time_attack_s: 180.,
+            survival_waves: 6,
+            survival_wave_interval_s: 4.,
+        }
+    }
+}
+
+/// Fills in values a tuning file leaves out, matches the base of the shipped file.
+impl Default for GameTuning {
+    fn default() -> Self {
+        Self {
+            sprite_scale: 0.5,
+            base_speed: 250.,
+            time_step: 1. / 60.,
+            fire_cooldown_scale: 1.,
+            player_lives: 1,
+            initial_enemies: 5,
+            enemy_respawn_delay_s: 2.,
+            enemy_speed_scale: 1.,
+            enemy_fire_rate_scale: 1.,
+            enemy_projectile_speed_scale: 1.,
+            explosion_frames_per_step: 2,
+            director: DirectorTuning::default(),
+            modes: ModeTuning::default(),
+        }
+    }
+}
+
+impl GameTuning {
+    fn validate(&self) -> Result<(), String> {
+        let positive = [
+            ("sprite_scale", self.sprite_scale),
+            ("base_speed", self.base_speed),
+            ("time_step", self.time_step),
+            ("fire_cooldown_scale", self.fire_cooldown_scale),
+            ("enemy_speed_scale", self.enemy_speed_scale),
+            ("enemy_fire_rate_scale", self.enemy_fire_rate_scale),
+            (
+                "enemy_projectile_speed_scale",
+                self.enemy_projectile_speed_scale,
+            ),
+            ("director.min_pressure", self.director.min_pressure),
+            ("director.window_s", self.director.window_s),
+            ("director.target_kills", self.director.target_kills),
+            ("modes.time_attack_s", self.modes.time_attack_s),
+            (
+                "modes.survival_wave_interval_s",
+                self.modes.survival_wave_interval_s,
+            ),
+        ];
+        if let Some((name, _)) = positive.iter().find(|(_, v)| !v.is_finite() || *v <= 0.) {
+            return Err(format!("{} must be positive", name));
+        }
+        if self.time_step > 0.1 {
+            return Err("time_step above 0.1 s lets projectiles skip past ships".to_string());
+        }
+        if !self.enemy_respawn_delay_s.is_finite() || self.enemy_respawn_delay_s < 0. {
+            return Err("enemy_respawn_delay_s can't be negative".to_string());
+        }
+        if self.player_lives == 0 {
+            return Err("player_lives must be at least 1".to_string());
+        }
+        let director = &self.director;
+        if !(director.min_pressure..=director.max_pressure).contains(&1.) {
+            return Err("director pressure bounds must include 1".to_string());
+        }
+        if director.hit_penalty < 0. || director.adjust_per_s < 0. {
+            return Err("director hit_penalty and adjust_per_s can't be negative".to_string());
+        }
+        if self.modes.survival_waves == 0 {
+            return Err("modes.survival_waves must be at least 1".to_string());
+        }
+        if self.explosion_frames_per_step == 0 {
+            return Err("explosion_frames_per_step must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    fn with_overlay(mut self, overlay: &TuningOverlay) -> Self {
+        if let Some(value) = overlay.fire_cooldown_scale {
+            self.fire_cooldown_scale = value;
+        }
+        if let Some(value) = overlay.player_lives {
+            self.player_lives = value;
+        }
+        if let Some(value) = overlay.initial_enemies {
+            self.initial_enemies = value;
+        }
+        if let Some(value) = overlay.enemy_respawn_delay_s {
+            self.enemy_respawn_delay_s = value;
+        }
+        if let Some(value) = overlay.enemy_fire_rate_scale {
+            self.enemy_fire_rate_scale = value;
+        }
+        if let Some(value) = overlay.enemy_projectile_speed_scale {
+            self.enemy_projectile_speed_scale = value;
+        }
+        self
+    }
+
+    /// Hardcore runs get a single life, wave survival only the enemies of its waves.
+    pub fn with_mode(mut self, mode: GameMode) -> Self {
+        match mode {
+            GameMode::Hardcore => self.player_lives = 1,
+            GameMode::WaveSurvival => self.initial_enemies = 0,
+            GameMode::Endless | GameMode::TimeAttack | GameMode::DailyChallenge => (),
+        }
+        self
+    }
+
+    pub fn with_modifiers(mut self, modifiers: Modifiers) -> Self {
+        if modifiers.contains(Modifiers::DOUBLE_ENEMY_SPEED) {
+            self.enemy_speed_scale *= 2.;
+        }
+        self
+    }
+}
+
+/// Gameplay values a difficulty replaces, the rest comes from the base tuning.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TuningOverlay {
+    pub fire_cooldown_scale: Option<f32>,
+    pub player_lives: Option<u32>,
+    pub initial_enemies: Option<u16>,
+    pub enemy_respawn_delay_s: Option<f32>,
+    pub enemy_fire_rate_scale: Option<f32>,
+    pub enemy_projectile_speed_scale: Option<f32>,
+}
+
+/// Contents of the tuning file, the base tuning and an overlay per difficulty.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TuningPresets {
+    #[serde(default)]
+    pub base: GameTuning,
+    #[serde(default = "default_difficulties")]
+    pub difficulties: HashMap<Difficulty, TuningOverlay>,
+}
+
+/// The shipped tuning file, built in for when there's none to read.
+impl Default for TuningPresets {
+    fn default() -> Self {
+        ron::from_str(TUNING).expect("invalid tuning data")
+    }
+}
+
+fn default_difficulties() -> HashMap<Difficulty, TuningOverlay> {
+    TuningPresets::default().difficulties
+}
+
+#[derive(Debug)]
+pub enum TuningError {
+    Io(io::Error),
+    Parse(ron::error::SpannedError),
+    Invalid(String),
+}
+
+impl fmt::Display for TuningError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TuningError::Io(err) => write!(f, "tuning io error: {}", err),
+            TuningError::Parse(err) => write!(f, "invalid tuning: {}", err),
+            TuningError::Invalid(err) => write!(f, "invalid tuning: {}", err),
+        }
+    }
+}
+
+impl From<io::Error> for TuningError {
+    fn from(err: io::Error) -> Self {
+        TuningError::Io(err)
+    }
+}
+
+impl TuningPresets {
+    pub fn parse(data: &str) -> Result<Self, TuningError> {
+        let presets: Self = ron::from_str(data).map_err(TuningError::Parse)?;
+        presets.base.validate().map_err(TuningError::Invalid)?;
+        for difficulty in Difficulty::ALL {
+            presets.tuning(difficulty).validate().map_err(|err| {
+                TuningError::Invalid(format!("{} difficulty: {}", difficulty.label(), err))
+            })?;
+        }
+        Ok(presets)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TuningError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// The tuning file if there is one, the built in defaults otherwise.
+    pub fn load_or_default() -> Result<Self, TuningError> {
+        match Path::new(TUNING_PATH).exists() {
+            true => Self::load(TUNING_PATH),
+            false => Ok(Self::default()),
+        }
+    }
+
+    pub fn tuning(&self, difficulty: Difficulty) -> GameTuning {
+        match self.difficulties.get(&difficulty) {
+            Some(overlay) => self.base.with_overlay(overlay),
+            None => self.base,
+        }
+    }
+}
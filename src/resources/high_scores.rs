@@ -0,0 +1,81 @@
+use std::{collections::BTreeMap, fs};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::run_settings::GameMode;
+
+const HIGH_SCORES_PATH: &str = "highscores.ron";
+const HIGH_SCORES_LEN: usize = 10;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HighScoreEntry {
+    pub score: u32,
+    pub seed: u64,
+    /// Path of the replay file recorded for the run.
+    pub replay: Option<String>,
+}
+
+/// Best runs of every game mode, each in a table of its own.
+#[derive(Resource, Serialize, Deserialize, Default)]
+pub struct HighScores {
+    #[serde(default)]
+    tables: BTreeMap<GameMode, Vec<HighScoreEntry>>,
+    /// Table of files saved before game modes, all of them endless runs.
+    #[serde(default, skip_serializing)]
+    entries: Vec<HighScoreEntry>,
+}
+
+impl HighScores {
+    /// Reads the saved tables, a missing or broken file starts empty ones.
+    pub fn load() -> Self {
+        let mut high_scores: Self = fs::read_to_string(HIGH_SCORES_PATH)
+            .ok()
+            .and_then(|data| ron::from_str(&data).ok())
+            .unwrap_or_default();
+        let legacy = std::mem::take(&mut high_scores.entries);
+        if !legacy.is_empty() {
+            high_scores
+                .tables
+                .entry(GameMode::Endless)
+                .or_insert(legacy);
+        }
+        high_scores
+    }
+
+    pub fn save(&self) {
+        let data = match ron::ser::to_string_pretty(self, Default::default()) {
+            Ok(data) => data,
+            Err(err) => return warn!("Failed to serialize high scores: {}", err),
+        };
+        if let Err(err) = fs::write(HIGH_SCORES_PATH, data) {
+            warn!("Failed to save high scores: {}", err);
+        }
+    }
+
+    /// Best first.
+    pub fn entries(&self, mode: GameMode) -> &[HighScoreEntry] {
+        self.tables.get(&mode).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn qualifies(&self, mode: GameMode, score: u32) -> bool {
+        let entries = self.entries(mode);
+        score > 0
+            && (entries.len() < HIGH_SCORES_LEN || entries.iter().any(|entry| entry.score < score))
+    }
+
+    /// Inserts the entry keeping the table sorted, returns its zero based rank.
+    pub fn insert(&mut self, mode: GameMode, entry: HighScoreEntry) -> Option<usize> {
+        if !self.qualifies(mode, entry.score) {
+            return None;
+        }
+        let entries = self.tables.entry(mode).or_default();
+        let rank = entries
+            .iter()
+            .position(|e| e.score < entry.score)
+            .unwrap_or(entries.len());
+        entries.insert(rank, entry);
+        entries.truncate(HIGH_SCORES_LEN);
+        Some(rank)
+    }
+}
@@ -0,0 +1,179 @@
+use bevy::prelude::*;
+
+use crate::{leaderboard::LeaderboardSettings, net::DEFAULT_PORT, AppState};
+
+use super::{
+    daily_challenge::DailyChallenge,
+    game_config::GameConfig,
+    game_tuning::{TuningPresets, TUNING_PATH},
+    player_input::MAX_PLAYERS,
+    replay::Replay,
+    run_settings::{GameMode, RunSettings},
+    viewport_size::ViewportSize,
+};
+
+pub const USAGE: &str = "\
+Options:
+  --seed N                seed every run with N
+  --window WxH            window and playfield size, 1000x600 by default
+  --fullscreen            borderless fullscreen window
+  --state STATE           starting screen: menu, playing, high-scores or lobby
+  --mode MODE             endless, time-attack, wave-survival, hardcore or daily-challenge
+  --wave N                formation wave runs start at
+  --difficulty LEVEL      easy, normal, hard or insane
+  --adaptive              let the difficulty adapt to how the run goes
+  --players N             ships playing together, from 1 to 4
+  --replay FILE           play back a recorded run
+  --tuning FILE           gameplay tuning, assets/data/tuning.ron by default
+  --leaderboard URL       submit runs to the leaderboard at http://HOST:PORT
+  --player NAME           name on the leaderboard, the user name by default
+  --host [PORT]           host an online co-op run, on port 7777 by default
+  --join HOST:PORT        join the online co-op run hosted at HOST:PORT
+  --headless --ticks N    simulate N steps without a window and print the result";
+
+/// Launch configuration parsed from the command line.
+#[derive(Resource, Clone, Default)]
+pub struct LaunchOptions {
+    pub seed: Option<u64>,
+    pub viewport: ViewportSize,
+    pub fullscreen: bool,
+    /// Screen to start on, the main menu or the run itself by default.
+    pub start_state: Option<AppState>,
+    pub run: RunSettings,
+    pub replay: Option<Replay>,
+    pub tuning: TuningPresets,
+    pub leaderboard: Option<LeaderboardSettings>,
+    /// Online run to host or join right away from the lobby.
+    pub lobby: Option<LobbyRole>,
+    /// Steps to simulate without a window, `None` opens the window.
+    pub headless_ticks: Option<u64>,
+}
+
+/// Side taken in an online co-op run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LobbyRole {
+    /// Waits for the other player on a port.
+    Host(u16),
+    /// Connects to a host's `host:port` address.
+    Join(String),
+}
+
+impl LaunchOptions {
+    pub fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut args = args.peekable();
+        let mut options = Self::default();
+        let mut headless = false;
+        let mut tuning_path = None;
+        let (mut leaderboard_url, mut player) = (None, None);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+            match arg.as_str() {
+                "--seed" => options.seed = Some(parse_number(&value()?)?),
+                "--window" => options.viewport = parse_size(&value()?)?,
+                "--fullscreen" => options.fullscreen = true,
+                "--state" => {
+                    options.start_state = Some(match value()?.as_str() {
+                        "menu" => AppState::MainMenu,
+                        "playing" => AppState::Playing,
+                        "high-scores" => AppState::HighScores,
+                        "lobby" => AppState::Lobby,
+                        state => return Err(format!("unknown state {}", state)),
+                    })
+                }
+                "--mode" => options.run.mode = value()?.parse()?,
+                "--wave" => {
+                    options.run.start_wave = parse_number(&value()?)?;
+                    if options.run.start_wave == 0 {
+                        return Err("waves start at 1".to_string());
+                    }
+                }
+                "--difficulty" => options.run.difficulty = value()?.parse()?,
+                "--adaptive" => options.run.adaptive = true,
+                "--players" => {
+                    options.run.players = parse_number(&value()?)?;
+                    if !(1..=MAX_PLAYERS as u8).contains(&options.run.players) {
+                        return Err(format!("from 1 to {} players can play", MAX_PLAYERS));
+                    }
+                }
+                "--replay" => {
+                    let path = value()?;
+                    let replay = Replay::load(&path).map_err(|err| format!("{}: {}", path, err))?;
+                    options.replay = Some(replay);
+                }
+                "--tuning" => tuning_path = Some(value()?),
+                "--leaderboard" => leaderboard_url = Some(value()?),
+                "--player" => player = Some(value()?),
+                "--host" => {
+                    // the port is optional
+                    let port = match args.next_if(|next| !next.starts_with("--")) {
+                        Some(port) => parse_number(&port)?,
+                        None => DEFAULT_PORT,
+                    };
+                    options.lobby = Some(LobbyRole::Host(port));
+                }
+                "--join" => options.lobby = Some(LobbyRole::Join(value()?)),
+                "--headless" => headless = true,
+                "--ticks" => options.headless_ticks = Some(parse_number(&value()?)?),
+                _ => return Err(format!("unknown argument {}", arg)),
+            }
+        }
+        // the day decides the seed and modifiers of a daily challenge
+        if options.run.mode == GameMode::DailyChallenge {
+            options.run.modifiers = DailyChallenge::today().modifiers;
+        }
+        options.leaderboard = match (leaderboard_url, player) {
+            (Some(url), player) => Some(LeaderboardSettings::new(&url, player)?),
+            (None, Some(_)) => return Err("--player only applies to --leaderboard".to_string()),
+            (None, None) => None,
+        };
+        options.tuning = match &tuning_path {
+            Some(path) => TuningPresets::load(path),
+            None => TuningPresets::load_or_default(),
+        }
+        .map_err(|err| {
+            let path = tuning_path.as_deref().unwrap_or(TUNING_PATH);
+            format!("{}: {}", path, err)
+        })?;
+        if headless && options.lobby.is_some() {
+            return Err("online runs need a window".to_string());
+        }
+        match (headless, options.headless_ticks) {
+            (true, None) => Err("--headless needs --ticks".to_string()),
+            (false, Some(_)) => Err("--ticks only applies to --headless".to_string()),
+            _ => Ok(options),
+        }
+    }
+
+    /// The window, starting screen and tuning options over `config`, a replay
+    /// starts in the run and an online run in the lobby.
+    pub fn apply_to(&self, config: GameConfig) -> GameConfig {
+        let start_state = match (&self.replay, &self.lobby) {
+            (Some(_), _) => AppState::Playing,
+            (None, Some(_)) => AppState::Lobby,
+            (None, None) => self.start_state.unwrap_or(config.start_state),
+        };
+        config
+            .with_viewport(self.viewport.w, self.viewport.h)
+            .with_fullscreen(self.fullscreen)
+            .with_start_state(start_state)
+            .with_tuning(self.tuning.clone())
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("bad number {}", value))
+}
+
+fn parse_size(value: &str) -> Result<ViewportSize, String> {
+    let (w, h) = value
+        .split_once('x')
+        .ok_or(format!("bad window size {}, expected WxH", value))?;
+    let size = ViewportSize {
+        w: parse_number(w)?,
+        h: parse_number(h)?,
+    };
+    match size.w > 0. && size.h > 0. {
+        true => Ok(size),
+        false => Err(format!("bad window size {}", value)),
+    }
+}
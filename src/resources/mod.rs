@@ -1,3 +1,16 @@
+pub mod bullet_patterns;
+pub mod daily_challenge;
+pub mod enemy_archetypes;
 pub mod fonts;
+pub mod formations;
+pub mod game_config;
+pub mod game_rng;
+pub mod game_tuning;
+pub mod high_scores;
+pub mod launch_options;
+pub mod player_input;
+pub mod replay;
+pub mod run_settings;
+pub mod score;
 pub mod textures;
 pub mod viewport_size;
@@ -0,0 +1,96 @@
+use bevy::prelude::*;
+use bitflags::bitflags;
+
+bitflags! {
+    /// Player actions held during a single simulation step.
+    #[derive(Default)]
+    pub struct InputActions: u8 {
+        const UP = 1 << 0;
+        const DOWN = 1 << 1;
+        const LEFT = 1 << 2;
+        const RIGHT = 1 << 3;
+        const ROTATE_CW = 1 << 4;
+        const ROTATE_CCW = 1 << 5;
+        const FIRE = 1 << 6;
+        const NEXT_WEAPON = 1 << 7;
+    }
+}
+
+impl InputActions {
+    /// Action names in bit order, from the lowest bit.
+    pub const NAMES: [&'static str; 8] = [
+        "up",
+        "down",
+        "left",
+        "right",
+        "rotate_cw",
+        "rotate_ccw",
+        "fire",
+        "next_weapon",
+    ];
+}
+
+/// Most players a run can have, all on the same machine.
+pub const MAX_PLAYERS: usize = 4;
+
+/// Actions of the current and previous simulation step of a single player.
+#[derive(Clone, Copy, Default)]
+pub struct PlayerInput {
+    current: InputActions,
+    previous: InputActions,
+}
+
+/// Actions of every player in the run, the only input the simulation reads so
+/// a run can be replayed from its recorded actions.
+#[derive(Resource, Clone, Default)]
+pub struct PlayerInputs(Vec<PlayerInput>);
+
+/// Actions fed to the players instead of the keyboard and gamepads, set by
+/// headless simulations. Players past the end hold nothing.
+#[derive(Resource, Default)]
+pub struct ScriptedInput(pub Vec<InputActions>);
+
+impl PlayerInput {
+    pub fn push(&mut self, actions: InputActions) {
+        self.previous = self.current;
+        self.current = actions;
+    }
+
+    pub fn current(&self) -> InputActions {
+        self.current
+    }
+
+    pub fn pressed(&self, actions: InputActions) -> bool {
+        self.current.contains(actions)
+    }
+
+    pub fn just_pressed(&self, actions: InputActions) -> bool {
+        self.current.contains(actions) && !self.previous.contains(actions)
+    }
+
+    pub fn just_released(&self, actions: InputActions) -> bool {
+        self.previous.contains(actions) && !self.current.contains(actions)
+    }
+}
+
+impl PlayerInputs {
+    pub fn new(players: usize) -> Self {
+        Self(vec![PlayerInput::default(); players])
+    }
+
+    /// Starts the next step with the actions of each player in turn.
+    pub fn push(&mut self, actions: &[InputActions]) {
+        for (i, input) in self.0.iter_mut().enumerate() {
+            input.push(actions.get(i).copied().unwrap_or_default());
+        }
+    }
+
+    pub fn get(&self, player: usize) -> PlayerInput {
+        self.0.get(player).copied().unwrap_or_default()
+    }
+
+    /// This step's actions of every player, in player order.
+    pub fn current(&self) -> Vec<InputActions> {
+        self.0.iter().map(PlayerInput::current).collect()
+    }
+}
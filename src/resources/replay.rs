@@ -0,0 +1,175 @@
+use std::{fmt, fs, io, path::Path};
+
+use super::{
+    player_input::{InputActions, MAX_PLAYERS},
+    run_settings::{Difficulty, GameMode, Modifiers, RunSettings},
+};
+
+const REPLAY_MAGIC: &[u8; 4] = b"BGRP";
+const REPLAY_VERSION: u8 = 1;
+const FLAG_ADAPTIVE: u8 = 1 << 0;
+/// Most inputs a decoded replay may hold, four hours of every player at 60 steps a second.
+const MAX_REPLAY_INPUTS: u64 = MAX_PLAYERS as u64 * 4 * 60 * 60 * 60;
+pub const REPLAY_DIR: &str = "replays";
+pub const LAST_REPLAY_PATH: &str = "replays/last.replay";
+
+/// Seed, settings and per-step actions of a run, everything needed to simulate it again.
+#[derive(Clone, Default)]
+pub struct Replay {
+    pub seed: u64,
+    pub settings: RunSettings,
+    /// Actions of every player for each step in turn, `settings.players` per step.
+    pub inputs: Vec<InputActions>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Format(&'static str),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "replay io error: {}", err),
+            ReplayError::Format(err) => write!(f, "invalid replay: {}", err),
+        }
+    }
+}
+
+impl From<io::Error> for ReplayError {
+    fn from(err: io::Error) -> Self {
+        ReplayError::Io(err)
+    }
+}
+
+impl Replay {
+    pub fn new(seed: u64, settings: RunSettings) -> Self {
+        Self {
+            seed,
+            settings,
+            inputs: Vec::new(),
+        }
+    }
+
+    /// Steps recorded.
+    pub fn ticks(&self) -> usize {
+        self.inputs.len() / self.settings.players.max(1) as usize
+    }
+
+    /// Actions of every player at the step, counted from 0.
+    pub fn step(&self, tick: usize) -> Option<&[InputActions]> {
+        let players = self.settings.players.max(1) as usize;
+        self.inputs.get(tick * players..(tick + 1) * players)
+    }
+
+    /// Magic, version, seed, difficulty, start wave, flags, mode, modifiers and players followed by run-length
+    /// encoded actions, each run being the action bits and a varint repeat count.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = REPLAY_MAGIC.to_vec();
+        bytes.push(REPLAY_VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.push(self.settings.difficulty as u8);
+        bytes.extend_from_slice(&self.settings.start_wave.to_le_bytes());
+        bytes.push(match self.settings.adaptive {
+            true => FLAG_ADAPTIVE,
+            false => 0,
+        });
+        bytes.push(self.settings.mode as u8);
+        bytes.push(self.settings.modifiers.bits());
+        bytes.push(self.settings.players);
+        let mut inputs = self.inputs.iter().peekable();
+        while let Some(actions) = inputs.next() {
+            let mut count: u64 = 1;
+            while inputs.next_if_eq(&actions).is_some() {
+                count += 1;
+            }
+            bytes.push(actions.bits());
+            write_varint(&mut bytes, count);
+        }
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, ReplayError> {
+        let header_len = REPLAY_MAGIC.len() + 1 + 8;
+        if bytes.len() < header_len || &bytes[..REPLAY_MAGIC.len()] != REPLAY_MAGIC {
+            return Err(ReplayError::Format("missing replay header"));
+        }
+        if bytes[REPLAY_MAGIC.len()] != REPLAY_VERSION {
+            return Err(ReplayError::Format("unsupported replay version"));
+        }
+        let mut seed = [0; 8];
+        seed.copy_from_slice(&bytes[REPLAY_MAGIC.len() + 1..header_len]);
+        let rest = &bytes[header_len..];
+
+        // difficulty, start wave, flags, mode, modifiers and players
+        if rest.len() < 9 {
+            return Err(ReplayError::Format("missing run settings"));
+        }
+        let mut wave = [0; 4];
+        wave.copy_from_slice(&rest[1..5]);
+        let players = rest[8];
+        if !(1..=MAX_PLAYERS as u8).contains(&players) {
+            return Err(ReplayError::Format("unsupported player count"));
+        }
+        let settings = RunSettings {
+            difficulty: Difficulty::from_index(rest[0])
+                .ok_or(ReplayError::Format("unknown difficulty"))?,
+            start_wave: u32::from_le_bytes(wave),
+            adaptive: rest[5] & FLAG_ADAPTIVE != 0,
+            mode: GameMode::from_index(rest[6]).ok_or(ReplayError::Format("unknown game mode"))?,
+            modifiers: Modifiers::from_bits(rest[7])
+                .ok_or(ReplayError::Format("unknown modifiers"))?,
+            players,
+        };
+        let mut rest = &rest[9..];
+
+        let mut replay = Replay::new(u64::from_le_bytes(seed), settings);
+        let mut total: u64 = 0;
+        while let Some((bits, tail)) = rest.split_first() {
+            let actions = InputActions::from_bits(*bits)
+                .ok_or(ReplayError::Format("unknown input actions"))?;
+            let (count, tail) = read_varint(tail)?;
+            total = total.saturating_add(count);
+            if total > MAX_REPLAY_INPUTS {
+                return Err(ReplayError::Format("replay too long"));
+            }
+            replay
+                .inputs
+                .extend(std::iter::repeat_n(actions, count as usize));
+            rest = tail;
+        }
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ReplayError> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.encode())?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        Self::decode(&fs::read(path)?)
+    }
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn read_varint(bytes: &[u8]) -> Result<(u64, &[u8]), ReplayError> {
+    let mut value = 0;
+    for (i, byte) in bytes.iter().enumerate().take(10) {
+        value |= ((byte & 0x7f) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((value, &bytes[i + 1..]));
+        }
+    }
+    Err(ReplayError::Format("truncated run length"))
+}
@@ -0,0 +1,158 @@
+use std::str::FromStr;
+
+use bevy::prelude::*;
+use bitflags::bitflags;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Insane,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Normal,
+        Difficulty::Hard,
+        Difficulty::Insane,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+            Difficulty::Insane => "Insane",
+        }
+    }
+
+    pub fn from_index(index: u8) -> Option<Self> {
+        Self::ALL.get(index as usize).copied()
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|difficulty| difficulty.label().eq_ignore_ascii_case(s))
+            .ok_or(format!("unknown difficulty {}", s))
+    }
+}
+
+/// Rules a run is played by, each mode keeps its own high score table.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum GameMode {
+    /// Enemies keep coming until the last life is lost.
+    #[default]
+    Endless,
+    /// Highest score within the time limit.
+    TimeAttack,
+    /// A fixed number of formation waves, cleared for a victory.
+    WaveSurvival,
+    /// A single life and no extra lives or shields, whatever the difficulty.
+    Hardcore,
+    /// An endless run with the seed and modifiers of the day, scored once a day.
+    DailyChallenge,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 5] = [
+        GameMode::Endless,
+        GameMode::TimeAttack,
+        GameMode::WaveSurvival,
+        GameMode::Hardcore,
+        GameMode::DailyChallenge,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GameMode::Endless => "Endless",
+            GameMode::TimeAttack => "Time Attack",
+            GameMode::WaveSurvival => "Wave Survival",
+            GameMode::Hardcore => "Hardcore",
+            GameMode::DailyChallenge => "Daily Challenge",
+        }
+    }
+
+    pub fn from_index(index: u8) -> Option<Self> {
+        Self::ALL.get(index as usize).copied()
+    }
+
+    /// The label in lower case with dashes for spaces, `time-attack` for Time Attack.
+    pub fn slug(&self) -> String {
+        self.label().replace(' ', "-").to_lowercase()
+    }
+}
+
+impl FromStr for GameMode {
+    type Err = String;
+
+    /// Accepts the slug in any case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.slug().eq_ignore_ascii_case(s))
+            .ok_or(format!("unknown game mode {}", s))
+    }
+}
+
+bitflags! {
+    /// Rule changes a run is played with, daily challenges pick theirs by date.
+    #[derive(Default)]
+    pub struct Modifiers: u8 {
+        const DOUBLE_ENEMY_SPEED = 1 << 0;
+        const NO_PICKUPS = 1 << 1;
+        /// Left and right swap places.
+        const MIRRORED_CONTROLS = 1 << 2;
+    }
+}
+
+impl Modifiers {
+    pub fn labels(&self) -> Vec<&'static str> {
+        [
+            (Modifiers::DOUBLE_ENEMY_SPEED, "Double enemy speed"),
+            (Modifiers::NO_PICKUPS, "No pickups"),
+            (Modifiers::MIRRORED_CONTROLS, "Mirrored controls"),
+        ]
+        .into_iter()
+        .filter(|(modifier, _)| self.contains(*modifier))
+        .map(|(_, label)| label)
+        .collect()
+    }
+}
+
+/// Settings a run is started with, recorded in its replay to play it back the same way.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RunSettings {
+    pub mode: GameMode,
+    pub difficulty: Difficulty,
+    /// Formation wave the run opens with, starting at 1.
+    pub start_wave: u32,
+    /// Lets the difficulty director adjust spawn pressure to the player.
+    pub adaptive: bool,
+    pub modifiers: Modifiers,
+    /// Ships playing together on this machine, from 1 to `MAX_PLAYERS`.
+    pub players: u8,
+}
+
+impl Default for RunSettings {
+    fn default() -> Self {
+        Self {
+            mode: GameMode::default(),
+            difficulty: Difficulty::default(),
+            start_wave: 1,
+            adaptive: false,
+            modifiers: Modifiers::empty(),
+            players: 1,
+        }
+    }
+}
@@ -0,0 +1,4 @@
+use bevy::prelude::*;
+
+#[derive(Resource, Clone, Default, Deref, DerefMut)]
+pub struct Score(pub u32);
@@ -4,6 +4,12 @@ const PLAYER_SPRITE: &str = "player.png";
 const PLAYER_SPRITE_SIZE: (f32, f32) = (200., 200.);
 const PLAYER_FIRE_SPRITE: &str = "player_fire.png";
 const PLAYER_FIRE_SPRITE_SIZE: (f32, f32) = (100., 100.);
+const SPREAD_FIRE_SPRITE: &str = "spread_fire.png";
+const BURST_FIRE_SPRITE: &str = "burst_fire.png";
+const CHARGE_FIRE_SPRITE: &str = "charge_fire.png";
+const MISSILE_SPRITE: &str = "missile.png";
+/// Size of every weapon projectile sprite.
+const PROJECTILE_SPRITE_SIZE: (f32, f32) = (100., 100.);
 
 const ENEMY_SPRITE: &str = "enemy.png";
 const ENEMY_SPRITE_SIZE: (f32, f32) = (200., 200.);
@@ -31,9 +37,15 @@ pub struct TextureAtlasData {
 pub struct Textures {
     pub player: ImageData,
     pub player_fire: ImageData,
+    pub spread_fire: ImageData,
+    pub burst_fire: ImageData,
+    pub charge_fire: ImageData,
+    pub missile: ImageData,
     pub enemy: ImageData,
     pub enemy_fire: ImageData,
     pub boom: TextureAtlasData,
+    /// Scale every sprite is drawn at.
+    pub sprite_scale: f32,
 }
 
 fn tuple_into_vec(tuple: (f32, f32)) -> Vec2 {
@@ -47,6 +59,7 @@ impl Textures {
     pub fn init(
         asset_server: &Res<AssetServer>,
         mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+        sprite_scale: f32,
     ) -> Self {
         Self {
             player: ImageData {
@@ -57,6 +70,22 @@ impl Textures {
                 image: asset_server.load(PLAYER_FIRE_SPRITE),
                 size_px: tuple_into_vec(PLAYER_FIRE_SPRITE_SIZE),
             },
+            spread_fire: ImageData {
+                image: asset_server.load(SPREAD_FIRE_SPRITE),
+                size_px: tuple_into_vec(PROJECTILE_SPRITE_SIZE),
+            },
+            burst_fire: ImageData {
+                image: asset_server.load(BURST_FIRE_SPRITE),
+                size_px: tuple_into_vec(PROJECTILE_SPRITE_SIZE),
+            },
+            charge_fire: ImageData {
+                image: asset_server.load(CHARGE_FIRE_SPRITE),
+                size_px: tuple_into_vec(PROJECTILE_SPRITE_SIZE),
+            },
+            missile: ImageData {
+                image: asset_server.load(MISSILE_SPRITE),
+                size_px: tuple_into_vec(PROJECTILE_SPRITE_SIZE),
+            },
             enemy: ImageData {
                 image: asset_server.load(ENEMY_SPRITE),
                 size_px: tuple_into_vec(ENEMY_SPRITE_SIZE),
@@ -80,6 +109,21 @@ impl Textures {
                     y: BOOM_SHEET_GRID_SIZE.1.try_into().unwrap_or(1),
                 },
             },
+            sprite_scale,
+        }
+    }
+
+    pub fn image(&self, name: &str) -> Option<&ImageData> {
+        match name {
+            "player" => Some(&self.player),
+            "player_fire" => Some(&self.player_fire),
+            "spread_fire" => Some(&self.spread_fire),
+            "burst_fire" => Some(&self.burst_fire),
+            "charge_fire" => Some(&self.charge_fire),
+            "missile" => Some(&self.missile),
+            "enemy" => Some(&self.enemy),
+            "enemy_fire" => Some(&self.enemy_fire),
+            _ => None,
         }
     }
 }
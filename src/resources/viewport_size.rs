@@ -1,7 +1,13 @@
 use bevy::prelude::*;
 
-#[derive(Resource, Debug)]
+#[derive(Resource, Debug, Clone, Copy)]
 pub struct ViewportSize {
     pub w: f32,
     pub h: f32,
 }
+
+impl Default for ViewportSize {
+    fn default() -> Self {
+        Self { w: 1000., h: 600. }
+    }
+}
@@ -0,0 +1,148 @@
+use bevy::prelude::*;
+use bevy_game::{
+    plugins::game::{
+        game_mode_plugin::RunOutcome,
+        player_plugin::{nearest_player, PlayerId},
+        replay_plugin::LastRun,
+    },
+    resources::{launch_options::LaunchOptions, replay::Replay, run_settings::RunSettings},
+    AppState, InputActions, Simulation,
+};
+
+const SEED: u64 = 42;
+/// Fixed simulation steps per second.
+const TICKS_PER_S: u64 = 60;
+
+fn coop(players: u8) -> Simulation {
+    Simulation::with_settings(
+        SEED,
+        RunSettings {
+            players,
+            ..Default::default()
+        },
+    )
+}
+
+#[test]
+fn every_player_gets_a_ship_of_their_own() {
+    let mut sim = coop(4);
+    let players = sim.observe().players;
+
+    let ids: Vec<_> = players.iter().map(|(id, _)| *id).collect();
+    assert_eq!(ids, [PlayerId(0), PlayerId(1), PlayerId(2), PlayerId(3)]);
+    assert!(players
+        .windows(2)
+        .all(|pair| pair[0].1.position.x < pair[1].1.position.x));
+    assert_eq!(sim.lives(), 4);
+    assert_eq!(sim.player_scores(), [0, 0, 0, 0]);
+}
+
+#[test]
+fn each_ship_follows_its_own_player() {
+    let mut sim = coop(2);
+    let start = sim.observe().players;
+    for _ in 0..20 {
+        sim.step_players(&[InputActions::LEFT, InputActions::RIGHT]);
+    }
+    let end = sim.observe().players;
+
+    assert!(end[0].1.position.x < start[0].1.position.x);
+    assert!(end[1].1.position.x > start[1].1.position.x);
+}
+
+#[test]
+fn kills_are_credited_to_the_shooter() {
+    let mut sim = coop(2);
+    let second = sim.observe().players[1].1.position;
+    assert!(sim.spawn_enemy("drifter", Vec2::new(second.x, 0.)));
+    for _ in 0..2 * TICKS_PER_S {
+        sim.step_players(&[InputActions::empty(), InputActions::FIRE]);
+    }
+
+    let scores = sim.player_scores();
+    assert_eq!(scores[0], 0);
+    assert!(scores[1] > 0);
+    assert_eq!(sim.score(), scores[1]);
+}
+
+#[test]
+fn the_run_goes_on_while_someone_is_alive() {
+    let mut sim = coop(2);
+    let first = sim.observe().players[0].1.position;
+    sim.spawn_enemy("drifter", first);
+    sim.run(3 * TICKS_PER_S, InputActions::empty());
+
+    let players = sim.observe().players;
+    assert_eq!(players.len(), 1);
+    assert_eq!(players[0].0, PlayerId(1));
+    assert_eq!(sim.state(), AppState::Playing);
+    assert_eq!(sim.outcome(), None);
+
+    sim.spawn_enemy("drifter", players[0].1.position);
+    sim.run(3 * TICKS_PER_S, InputActions::empty());
+
+    assert_eq!(sim.lives(), 0);
+    assert_eq!(sim.outcome(), Some(RunOutcome::Defeat));
+    assert_eq!(sim.state(), AppState::GameOver);
+}
+
+#[test]
+fn enemies_go_for_the_nearest_player() {
+    let transforms = [
+        Transform::from_xyz(-200., 0., 0.),
+        Transform::from_xyz(150., 0., 0.),
+    ];
+
+    let nearest = nearest_player(&transforms, Vec3::new(100., 100., 0.)).unwrap();
+    assert_eq!(nearest.translation.x, 150.);
+    assert!(nearest_player(&[], Vec3::ZERO).is_none());
+}
+
+#[test]
+fn coop_replays_play_back_the_same_run() {
+    let script = |tick: u64| match (tick / 30) % 2 {
+        0 => [InputActions::FIRE | InputActions::LEFT, InputActions::UP],
+        _ => [InputActions::RIGHT, InputActions::FIRE | InputActions::LEFT],
+    };
+    let mut recorded = coop(2);
+    for tick in 0..3 * TICKS_PER_S {
+        recorded.step_players(&script(tick));
+    }
+    let mut replay = recorded.app_mut().world.resource::<LastRun>().0.clone();
+    // the seed is only filled in once the run is over
+    replay.seed = SEED;
+    let replay = Replay::decode(&replay.encode()).unwrap();
+    assert_eq!(replay.settings.players, 2);
+    assert_eq!(replay.ticks(), recorded.tick() as usize);
+
+    let mut played = Simulation::with_options(LaunchOptions {
+        replay: Some(replay),
+        ..Default::default()
+    });
+    played.run(3 * TICKS_PER_S, InputActions::empty());
+
+    assert_eq!(played.observe(), recorded.observe());
+    assert_eq!(played.player_scores(), recorded.player_scores());
+}
+
+#[test]
+fn replays_of_another_version_are_rejected() {
+    let mut replay = Replay::new(SEED, RunSettings::default());
+    replay.inputs = vec![InputActions::FIRE; 10];
+    let mut bytes = replay.encode();
+    assert_eq!(Replay::decode(&bytes).unwrap().inputs, replay.inputs);
+
+    bytes[4] += 1;
+    assert!(Replay::decode(&bytes).is_err());
+}
+
+#[test]
+fn replays_claiming_too_many_inputs_are_rejected() {
+    let mut replay = Replay::new(SEED, RunSettings::default());
+    replay.inputs = vec![InputActions::FIRE];
+    let mut bytes = replay.encode();
+    // a single run repeating the action 2^32 - 1 times
+    bytes.pop();
+    bytes.extend_from_slice(&[0xff, 0xff, 0xff, 0xff, 0x0f]);
+    assert!(Replay::decode(&bytes).is_err());
+}
@@ -0,0 +1,66 @@
+use bevy_game::{
+    resources::{
+        daily_challenge::{DailyChallenge, DailyResults},
+        game_rng::GameRng,
+        player_input::InputActions,
+        run_settings::{GameMode, Modifiers, RunSettings},
+    },
+    Simulation,
+};
+
+#[test]
+fn a_day_always_gets_the_same_challenge() {
+    assert_eq!(
+        DailyChallenge::for_day(20745),
+        DailyChallenge::for_day(20745)
+    );
+    assert_ne!(
+        DailyChallenge::for_day(20745).seed,
+        DailyChallenge::for_day(20746).seed
+    );
+    assert!((0..100).all(|day| !DailyChallenge::for_day(day).modifiers.is_empty()));
+    assert!((0..100).any(|day| DailyChallenge::for_day(day).modifiers == Modifiers::all()));
+}
+
+#[test]
+fn challenges_are_named_by_their_date() {
+    assert_eq!(DailyChallenge::for_day(0).date(), "1970-01-01");
+    assert_eq!(DailyChallenge::for_day(11016).date(), "2000-02-29");
+    assert_eq!(DailyChallenge::for_day(20745).date(), "2026-10-19");
+}
+
+#[test]
+fn only_the_first_attempt_of_a_day_is_scored() {
+    let mut results = DailyResults::default();
+    let today = DailyChallenge::for_day(20745);
+
+    assert!(results.start_attempt(&today));
+    results.finish_attempt(&today, 1200, None);
+    assert!(!results.start_attempt(&today));
+    // a day without a started attempt has nothing to finish
+    results.finish_attempt(&DailyChallenge::for_day(20744), 5000, None);
+
+    assert_eq!(results.get(&today).map(|result| result.score), Some(1200));
+    assert_eq!(results.recent().count(), 1);
+    assert!(results.start_attempt(&DailyChallenge::for_day(20746)));
+}
+
+#[test]
+fn the_day_is_only_worked_out_for_daily_runs() {
+    let mut endless = Simulation::new(7);
+    endless.step(InputActions::empty());
+    let world = &endless.app_mut().world;
+    assert!(!world.contains_resource::<DailyChallenge>());
+
+    let mut daily = Simulation::with_settings(
+        7,
+        RunSettings {
+            mode: GameMode::DailyChallenge,
+            ..Default::default()
+        },
+    );
+    daily.step(InputActions::empty());
+    let world = &daily.app_mut().world;
+    let challenge = world.resource::<DailyChallenge>();
+    assert_eq!(world.resource::<GameRng>().seed(), challenge.seed);
+}
@@ -0,0 +1,217 @@
+use std::{
+    io::{BufRead, BufReader},
+    net::TcpListener,
+    process::{Child, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use bevy_game::{
+    leaderboard::{
+        Delivery, LeaderboardClient, LeaderboardEntry, Submission, SubmissionQueue, Verification,
+    },
+    resources::{
+        replay::Replay,
+        run_settings::{GameMode, RunSettings},
+    },
+    InputActions,
+};
+
+/// The mock server on a free port, stopped when dropped.
+struct Server {
+    child: Child,
+    url: String,
+}
+
+impl Server {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_leaderboard_server"))
+            .args(["--addr", "127.0.0.1:0"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("leaderboard server starts");
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        let addr = line.trim().strip_prefix("listening on ").unwrap();
+        Self {
+            child,
+            url: format!("http://{}", addr),
+        }
+    }
+
+    fn client(&self) -> LeaderboardClient {
+        LeaderboardClient::new(&self.url).unwrap()
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn submission(player: &str, score: u32) -> Submission {
+    Submission {
+        board: "endless".to_string(),
+        player: player.to_string(),
+        score,
+        seed: 7,
+        replay: None,
+    }
+}
+
+fn players(entries: &[LeaderboardEntry]) -> Vec<(u32, &str)> {
+    entries
+        .iter()
+        .map(|entry| (entry.rank, entry.player.as_str()))
+        .collect()
+}
+
+#[test]
+fn boards_rank_the_best_scores_first() {
+    let server = Server::start();
+    let client = server.client();
+    for (player, score) in [("ann", 300), ("bob", 900), ("cy", 600), ("dee", 100)] {
+        client.submit(&submission(player, score)).unwrap();
+    }
+    let rank = client.submit(&submission("eve blue", 600)).unwrap().rank;
+    // ties go to the earlier submission
+    assert_eq!(rank, 3);
+
+    let top = client.top("endless", 3).unwrap();
+    assert_eq!(players(&top), [(1, "bob"), (2, "cy"), (3, "eve blue")]);
+    assert!(top
+        .iter()
+        .all(|entry| entry.verification == Verification::Unverified));
+
+    let around = client.around("endless", "ann", 1).unwrap();
+    assert_eq!(players(&around), [(3, "eve blue"), (4, "ann"), (5, "dee")]);
+    assert!(client.around("endless", "nobody", 1).unwrap().is_empty());
+    assert!(client.top("hardcore", 3).unwrap().is_empty());
+
+    // oversized row counts are clamped rather than overflowing
+    assert_eq!(client.top("endless", usize::MAX).unwrap().len(), 5);
+    assert_eq!(
+        client.around("endless", "ann", usize::MAX).unwrap().len(),
+        5
+    );
+}
+
+#[test]
+fn bad_submissions_are_rejected_for_good() {
+    let server = Server::start();
+    let mut queue = SubmissionQueue::default();
+    queue.push(submission("", 100));
+    let deliveries = queue.flush(&server.client(), Instant::now());
+
+    assert!(matches!(deliveries[..], [Delivery::Rejected(..)]));
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn queued_submissions_wait_for_the_service() {
+    // a port nothing listens on any more
+    let closed = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let offline = LeaderboardClient::new(&format!("http://{}", closed))
+        .unwrap()
+        .with_timeout(Duration::from_millis(500));
+    let mut queue = SubmissionQueue::default();
+    queue.push(submission("ann", 300));
+    queue.push(submission("bob", 200));
+
+    let now = Instant::now();
+    assert!(queue.flush(&offline, now).is_empty());
+    let first_wait = queue.retry_in(now).unwrap();
+    assert!(queue.flush(&offline, now).is_empty());
+    assert_eq!(queue.retry_in(now).unwrap(), first_wait * 2);
+    assert_eq!(queue.len(), 2);
+
+    let server = Server::start();
+    let deliveries = queue.flush(&server.client(), Instant::now());
+    assert_eq!(deliveries.len(), 2);
+    assert!(queue.retry_in(Instant::now()).is_none());
+    let top = server.client().top("endless", 10).unwrap();
+    assert_eq!(players(&top), [(1, "ann"), (2, "bob")]);
+}
+
+#[test]
+fn attached_replays_verify_the_score() {
+    let server = Server::start();
+    let client = server.client();
+    let mut replay = Replay::new(7, RunSettings::default());
+    replay.inputs = vec![InputActions::empty(); 60];
+    client
+        .submit(&submission("honest", 0).with_replay(&replay))
+        .unwrap();
+    client
+        .submit(&submission("cheater", 5000).with_replay(&replay))
+        .unwrap();
+    let mut wrong_seed = submission("lost", 10).with_replay(&replay);
+    wrong_seed.seed = 8;
+    assert!(client.submit(&wrong_seed).is_err());
+    // an hour of time attack, well past its time limit
+    let mut overlong = Replay::new(
+        7,
+        RunSettings {
+            mode: GameMode::TimeAttack,
+            ..Default::default()
+        },
+    );
+    overlong.inputs = vec![InputActions::empty(); 60 * 60 * 60];
+    assert!(client
+        .submit(&submission("stalled", 10).with_replay(&overlong))
+        .is_err());
+
+    let started = Instant::now();
+    let top = loop {
+        let top = client.top("endless", 10).unwrap();
+        let checked = top
+            .iter()
+            .all(|entry| entry.verification != Verification::Pending);
+        if checked || started.elapsed() > Duration::from_secs(60) {
+            break top;
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+    let verification = |player: &str| {
+        top.iter()
+            .find(|entry| entry.player == player)
+            .map(|entry| entry.verification)
+    };
+    assert_eq!(verification("honest"), Some(Verification::Verified));
+    assert_eq!(verification("cheater"), Some(Verification::Mismatch));
+    assert_eq!(verification("lost"), None);
+    assert_eq!(verification("stalled"), None);
+}
+
+#[test]
+fn replays_survive_the_trip() {
+    let mut replay = Replay::new(42, RunSettings::default());
+    replay.inputs = [
+        InputActions::FIRE,
+        InputActions::LEFT,
+        InputActions::empty(),
+    ]
+    .into_iter()
+    .cycle()
+    .take(100)
+    .collect();
+    // every base64 padding length
+    for len in [100, 99, 98] {
+        replay.inputs.truncate(len);
+        let decoded = submission("ann", 0)
+            .with_replay(&replay)
+            .decode_replay()
+            .unwrap()
+            .unwrap();
+        assert_eq!(decoded.seed, 42);
+        assert_eq!(decoded.inputs, replay.inputs);
+    }
+    assert!(submission("ann", 0).decode_replay().is_none());
+}
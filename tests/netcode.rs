@@ -0,0 +1,329 @@
+use std::{
+    collections::BTreeMap,
+    net::UdpSocket,
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use bevy_game::{
+    components::health::Health,
+    net::{
+        Handshake, LinkConditions, LoopbackNetwork, Message, SessionStart, Transport, UdpTransport,
+        MAX_PREDICTION,
+    },
+    plugins::game::{
+        player_plugin::{HitCause, HitPlayer, PlayerId},
+        replay_plugin::LastRun,
+        rollback_plugin::{load_world, save_world, RollbackApp, RollbackPlugin},
+    },
+    resources::{
+        launch_options::LaunchOptions, player_input::InputActions, run_settings::RunSettings,
+        score::Score,
+    },
+    Simulation,
+};
+
+const SEED: u64 = 42;
+/// Fixed simulation steps per second.
+const TICKS_PER_S: u64 = 60;
+const FRAME: Duration = Duration::from_micros(16_667);
+
+fn settings() -> RunSettings {
+    RunSettings {
+        players: 2,
+        ..Default::default()
+    }
+}
+
+fn coop() -> Simulation {
+    Simulation::with_settings(SEED, settings())
+}
+
+/// Both ships keep firing and sweep across the screen in opposite directions.
+fn script(tick: u64) -> [InputActions; 2] {
+    let (first, second) = match (tick / 40) % 2 {
+        0 => (InputActions::LEFT, InputActions::RIGHT),
+        _ => (InputActions::RIGHT, InputActions::LEFT),
+    };
+    [InputActions::FIRE | first, InputActions::FIRE | second]
+}
+
+/// A host and a joined peer connected through the loopback network.
+fn connect(network: &LoopbackNetwork) -> (Simulation, Simulation) {
+    let (mut host_end, mut join_end) = network.pair();
+    let mut host = Handshake::host(SEED, settings());
+    let mut join = Handshake::join(Instant::now());
+    let (mut host_start, mut join_start) = (None, None);
+    while host_start.is_none() || join_start.is_none() {
+        let now = Instant::now();
+        if host_start.is_none() {
+            host_start = host.poll(&mut host_end, now).unwrap();
+        }
+        if join_start.is_none() {
+            join_start = join.poll(&mut join_end, now).unwrap();
+        }
+        network.advance(FRAME);
+    }
+    let peer = |transport: Box<dyn Transport>, start: Option<SessionStart>| {
+        Simulation::online(LaunchOptions::default(), transport, start.unwrap())
+    };
+    (
+        peer(Box::new(host_end), host_start),
+        peer(Box::new(join_end), join_start),
+    )
+}
+
+/// Steps both peers a frame at a time, each pressing its own keys of the
+/// script. Returns the hashes of the confirmed steps each peer saw.
+fn play(
+    network: &LoopbackNetwork,
+    peers: &mut [Simulation; 2],
+    frames: u64,
+) -> [BTreeMap<u64, u64>; 2] {
+    let mut checksums = [BTreeMap::new(), BTreeMap::new()];
+    for _ in 0..frames {
+        for (index, peer) in peers.iter_mut().enumerate() {
+            // the local actions go to the step after the input delay, a
+            // keyboard held through it
+            let actions = script(peer.tick() + 1)[index];
+            peer.step(actions);
+            if let Some(net) = peer.net_session() {
+                checksums[index].extend(net.session().confirmed_checksums());
+            }
+        }
+        network.advance(FRAME);
+    }
+    checksums
+}
+
+#[test]
+fn loading_a_snapshot_rewinds_the_run() {
+    let mut expected = coop();
+    let mut rewound = coop();
+    for tick in 0..4 * TICKS_PER_S {
+        expected.step_players(&script(tick));
+        rewound.step_players(&script(tick));
+    }
+    let snapshot = rewound.save();
+    // a different future, taken back
+    for _ in 0..TICKS_PER_S {
+        rewound.step_players(&[InputActions::DOWN, InputActions::FIRE | InputActions::UP]);
+    }
+    rewound.load(&snapshot);
+
+    for tick in 4 * TICKS_PER_S..10 * TICKS_PER_S {
+        expected.step_players(&script(tick));
+        rewound.step_players(&script(tick));
+        assert_eq!(rewound.checksum(), expected.checksum(), "step {}", tick);
+    }
+    assert_eq!(rewound.observe(), expected.observe());
+    assert_eq!(rewound.player_scores(), expected.player_scores());
+}
+
+#[test]
+fn repeated_rollbacks_keep_the_run_on_course() {
+    let mut expected = coop();
+    let mut rewound = coop();
+    for tick in 0..15 * TICKS_PER_S {
+        if expected.outcome().is_some() {
+            break;
+        }
+        expected.step_players(&script(tick));
+        if tick % 11 == 0 {
+            let snapshot = rewound.save();
+            for _ in 0..MAX_PREDICTION {
+                rewound.step_players(&[InputActions::FIRE, InputActions::DOWN]);
+            }
+            rewound.load(&snapshot);
+        }
+        rewound.step_players(&script(tick));
+        assert_eq!(rewound.checksum(), expected.checksum(), "step {}", tick);
+    }
+    assert!(expected.score() > 0);
+    assert_eq!(rewound.observe(), expected.observe());
+}
+
+#[test]
+fn restored_entities_keep_their_references_when_ids_are_taken() {
+    let mut app = App::new();
+    app.add_plugin(RollbackPlugin)
+        .rollback_mapped_component::<HitPlayer>();
+    let player = app.world.spawn(Health::new(3.)).id();
+    app.world.spawn(HitPlayer {
+        player,
+        cause: HitCause::Projectile,
+    });
+    let snapshot = save_world(&mut app.world);
+
+    app.world.despawn(player);
+    // an effect outside the run takes the player's id over
+    let effect = app.world.spawn(Transform::default()).id();
+    assert_eq!(effect.index(), player.index());
+    load_world(&mut app.world, &snapshot);
+
+    assert!(app.world.get_entity(effect).is_some());
+    let hit = app.world.query::<&HitPlayer>().single(&app.world);
+    assert!(app.world.get::<Health>(hit.player).is_some());
+}
+
+#[test]
+fn messages_survive_encoding() {
+    let messages = [
+        Message::Hello,
+        Message::Welcome {
+            seed: SEED,
+            settings: settings(),
+        },
+        Message::Inputs {
+            ack: 12,
+            start: 9,
+            actions: vec![InputActions::FIRE, InputActions::UP | InputActions::LEFT],
+            checksum: Some((8, u64::MAX)),
+        },
+        Message::Inputs {
+            ack: 0,
+            start: 3,
+            actions: Vec::new(),
+            checksum: None,
+        },
+        Message::Leave,
+    ];
+    for message in messages {
+        assert_eq!(Message::decode(&message.encode()).unwrap(), message);
+    }
+    assert!(Message::decode(b"BGNP").is_err());
+}
+
+#[test]
+fn peers_meet_over_udp() {
+    let mut host_end = UdpTransport::host(0).unwrap();
+    let address = format!("127.0.0.1:{}", host_end.local_port().unwrap());
+    let mut join_end = UdpTransport::join(&address).unwrap();
+    let mut host = Handshake::host(SEED, settings());
+    let mut join = Handshake::join(Instant::now());
+
+    let started = Instant::now();
+    let (mut host_start, mut join_start) = (None, None);
+    while host_start.is_none() || join_start.is_none() {
+        assert!(
+            started.elapsed() < Duration::from_secs(5),
+            "peers never met"
+        );
+        let now = Instant::now();
+        if host_start.is_none() {
+            host_start = host.poll(&mut host_end, now).unwrap();
+        }
+        if join_start.is_none() {
+            join_start = join.poll(&mut join_end, now).unwrap();
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    let (host_start, join_start) = (host_start.unwrap(), join_start.unwrap());
+    assert_eq!(host_start.player, PlayerId(0));
+    assert_eq!(join_start.player, PlayerId(1));
+    assert_eq!((join_start.seed, join_start.settings), (SEED, settings()));
+}
+
+#[test]
+fn hosts_only_take_a_hello_as_their_peer() {
+    let mut host_end = UdpTransport::host(0).unwrap();
+    let address = format!("127.0.0.1:{}", host_end.local_port().unwrap());
+    let stranger = UdpSocket::bind("127.0.0.1:0").unwrap();
+    stranger.send_to(b"not a packet", &address).unwrap();
+    stranger
+        .send_to(&Message::Leave.encode(), &address)
+        .unwrap();
+    let mut join_end = UdpTransport::join(&address).unwrap();
+    join_end.send(&Message::Hello.encode()).unwrap();
+
+    let started = Instant::now();
+    let packet = loop {
+        if let Some(packet) = host_end.recv().unwrap() {
+            break packet;
+        }
+        assert!(started.elapsed() < Duration::from_secs(5), "no hello");
+        std::thread::sleep(Duration::from_millis(5));
+    };
+    assert_eq!(Message::decode(&packet).unwrap(), Message::Hello);
+    // answers go to the joiner, not the stranger
+    host_end.send(&Message::Leave.encode()).unwrap();
+    let started = Instant::now();
+    while join_end.recv().unwrap().is_none() {
+        assert!(started.elapsed() < Duration::from_secs(5), "no answer");
+        std::thread::sleep(Duration::from_millis(5));
+    }
+}
+
+#[test]
+fn peers_play_the_same_run_over_a_lossy_link() {
+    let network = LoopbackNetwork::new(
+        LinkConditions {
+            latency: Duration::from_millis(60),
+            jitter: Duration::from_millis(30),
+            loss: 0.1,
+        },
+        SEED,
+    );
+    let (host, joiner) = connect(&network);
+    let mut peers = [host, joiner];
+    let checksums = play(&network, &mut peers, 12 * TICKS_PER_S);
+
+    for peer in &peers {
+        let session = peer.net_session().unwrap().session();
+        assert_eq!(session.desync(), None);
+        assert!(session.rollbacks() > 0);
+        assert!(session.confirmed_tick() > 10 * TICKS_PER_S);
+    }
+    // what the peers agreed on is what the recorded run plays back to
+    let confirmed = peers
+        .iter()
+        .map(|peer| peer.net_session().unwrap().session().confirmed_tick())
+        .min()
+        .unwrap();
+    let mut replay = peers[0].app_mut().world.resource::<LastRun>().0.clone();
+    replay.seed = SEED;
+    replay.inputs.truncate(confirmed as usize * 2);
+    let mut offline = Simulation::with_options(LaunchOptions {
+        replay: Some(replay),
+        ..Default::default()
+    });
+    let mut compared = 0;
+    while offline.tick() < confirmed {
+        let tick = offline.tick();
+        for peer in &checksums {
+            if let Some(checksum) = peer.get(&tick) {
+                assert_eq!(*checksum, offline.checksum(), "step {}", tick);
+                compared += 1;
+            }
+        }
+        offline.step(InputActions::empty());
+    }
+    assert!(compared > confirmed);
+    assert!(offline.score() > 0);
+}
+
+#[test]
+fn a_tampered_state_is_reported_as_a_desync() {
+    let network = LoopbackNetwork::new(
+        LinkConditions {
+            latency: Duration::from_millis(20),
+            ..Default::default()
+        },
+        SEED,
+    );
+    let (host, joiner) = connect(&network);
+    let mut peers = [host, joiner];
+    play(&network, &mut peers, TICKS_PER_S);
+    assert!(peers
+        .iter()
+        .all(|peer| peer.net_session().unwrap().session().desync().is_none()));
+
+    let tampered = peers[1].tick();
+    **peers[1].app_mut().world.resource_mut::<Score>() += 1000;
+    play(&network, &mut peers, TICKS_PER_S);
+
+    for peer in &peers {
+        let desync = peer.net_session().unwrap().session().desync();
+        assert!(desync.unwrap() >= tampered);
+    }
+}
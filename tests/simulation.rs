@@ -0,0 +1,334 @@
+use bevy::prelude::*;
+use bevy_game::{
+    build_game_app,
+    plugins::game::{
+        director_plugin::Director, game_mode_plugin::RunOutcome, game_plugin::SimulationStage,
+        player_plugin::Player,
+    },
+    resources::{
+        launch_options::LaunchOptions,
+        run_settings::{Difficulty, GameMode, Modifiers, RunSettings},
+    },
+    AppState, Bot, GameConfig, InputActions, Simulation, Subsystems,
+};
+
+const SEED: u64 = 42;
+/// Fixed simulation steps per second.
+const TICKS_PER_S: u64 = 60;
+
+#[test]
+fn run_starts_playing_with_a_player() {
+    let mut sim = Simulation::new(SEED);
+    let observation = sim.observe();
+
+    assert_eq!(sim.state(), AppState::Playing);
+    assert!(observation.player.is_some());
+    assert_eq!(observation.lives, 1);
+    assert_eq!(observation.score, 0);
+}
+
+#[test]
+fn clock_advances_one_tick_per_step() {
+    let mut sim = Simulation::new(SEED);
+    let start = sim.tick();
+    sim.run(30, InputActions::empty());
+
+    assert_eq!(sim.tick(), start + 30);
+}
+
+#[test]
+fn enemies_spawn_over_time() {
+    let mut sim = Simulation::new(SEED);
+    assert!(sim.observe().enemies.is_empty());
+
+    sim.run(3 * TICKS_PER_S, InputActions::empty());
+
+    assert!(!sim.observe().enemies.is_empty());
+}
+
+#[test]
+fn firing_spawns_player_projectiles() {
+    let mut sim = Simulation::new(SEED);
+    sim.run(10, InputActions::FIRE);
+
+    assert!(!sim.observe().player_projectiles.is_empty());
+}
+
+#[test]
+fn player_moves_with_input() {
+    let mut sim = Simulation::new(SEED);
+    let start = sim.observe().player.unwrap().position;
+    sim.run(20, InputActions::RIGHT);
+    let player = sim.observe().player.unwrap();
+
+    assert!(player.position.x > start.x);
+    assert!(player.velocity.x > 0.);
+}
+
+#[test]
+fn shooting_an_enemy_scores() {
+    let mut sim = Simulation::new(SEED);
+    let player = sim.observe().player.unwrap().position;
+    assert!(sim.spawn_enemy("drifter", Vec2::new(player.x, 0.)));
+
+    sim.run(2 * TICKS_PER_S, InputActions::FIRE);
+
+    assert!(sim.score() > 0);
+}
+
+#[test]
+fn unknown_archetype_is_not_spawned() {
+    let mut sim = Simulation::new(SEED);
+
+    assert!(!sim.spawn_enemy("no_such_enemy", Vec2::ZERO));
+}
+
+#[test]
+fn colliding_with_an_enemy_ends_the_run() {
+    let mut sim = Simulation::new(SEED);
+    let player = sim.observe().player.unwrap().position;
+    sim.spawn_enemy("drifter", player);
+    // the hit is registered on one step and resolved on the next
+    sim.run(2, InputActions::empty());
+
+    assert_eq!(sim.lives(), 0);
+    assert!(sim.observe().player.is_none());
+    assert_eq!(sim.death_cause().as_deref(), Some("collision:drifter"));
+    assert_eq!(sim.state(), AppState::Playing);
+
+    sim.run(3 * TICKS_PER_S, InputActions::empty());
+
+    assert_eq!(sim.state(), AppState::GameOver);
+}
+
+#[test]
+fn a_kamikaze_ramming_the_player_scores_nothing() {
+    let mut sim = Simulation::new(SEED);
+    let player = sim.observe().player.unwrap().position;
+    sim.spawn_enemy("kamikaze", player);
+    sim.run(2, InputActions::empty());
+
+    assert_eq!(sim.death_cause().as_deref(), Some("collision:kamikaze"));
+    assert_eq!(sim.score(), 0);
+}
+
+#[test]
+fn pause_key_stops_the_clock() {
+    let mut sim = Simulation::new(SEED);
+    sim.press_key(KeyCode::Escape);
+    sim.step(InputActions::empty());
+    assert_eq!(sim.state(), AppState::Paused);
+
+    let paused_at = sim.tick();
+    sim.run(30, InputActions::empty());
+    assert_eq!(sim.tick(), paused_at);
+
+    sim.press_key(KeyCode::Escape);
+    sim.run(2, InputActions::empty());
+    assert_eq!(sim.state(), AppState::Playing);
+    assert!(sim.tick() > paused_at);
+}
+
+#[test]
+fn same_seed_and_inputs_give_the_same_run() {
+    let script = |tick: u64| match (tick / 40) % 3 {
+        0 => InputActions::FIRE | InputActions::LEFT,
+        1 => InputActions::FIRE | InputActions::RIGHT,
+        _ => InputActions::UP,
+    };
+    let mut runs = [Simulation::new(SEED), Simulation::new(SEED)];
+    for sim in runs.iter_mut() {
+        for tick in 0..5 * TICKS_PER_S {
+            sim.step(script(tick));
+        }
+    }
+    let [first, second] = &mut runs;
+
+    assert_eq!(first.observe(), second.observe());
+}
+
+#[test]
+fn bot_shoots_down_enemies() {
+    let mut sim = Simulation::new(SEED);
+    let mut bot = Bot::default();
+    while sim.tick() < 20 * TICKS_PER_S && sim.death_cause().is_none() {
+        let observation = sim.observe();
+        sim.step(bot.act(&observation));
+    }
+
+    assert!(sim.score() > 0);
+}
+
+#[test]
+fn composed_app_follows_the_config_and_runs_injected_systems() {
+    #[derive(Resource, Default)]
+    struct Updates(u32);
+    #[derive(Resource, Default)]
+    struct SimulationUpdates(u32);
+
+    let config = GameConfig::headless().with_sprite_scale(1.);
+    let mut app = build_game_app(config);
+    app.init_resource::<Updates>()
+        .init_resource::<SimulationUpdates>()
+        .add_system(|mut updates: ResMut<Updates>| updates.0 += 1)
+        // unordered against the simulation, which only warns about it
+        .add_system_to_stage(SimulationStage, |mut updates: ResMut<SimulationUpdates>| {
+            updates.0 += 1
+        });
+    app.update();
+    app.update();
+
+    assert_eq!(app.world.resource::<Updates>().0, 2);
+    assert_eq!(app.world.resource::<SimulationUpdates>().0, 2);
+    let player = app
+        .world
+        .query_filtered::<&Transform, With<Player>>()
+        .single(&app.world);
+    assert_eq!(player.scale.truncate(), Vec2::ONE);
+}
+
+#[test]
+fn disabled_pause_menu_ignores_the_pause_key() {
+    let mut app = build_game_app(GameConfig::headless().without(Subsystems::PAUSE_MENU));
+    app.update();
+    app.world
+        .resource_mut::<Input<KeyCode>>()
+        .press(KeyCode::Escape);
+    app.update();
+
+    assert_eq!(
+        *app.world.resource::<State<AppState>>().current(),
+        AppState::Playing
+    );
+}
+
+#[test]
+fn easy_runs_start_with_extra_lives() {
+    let settings = RunSettings {
+        difficulty: Difficulty::Easy,
+        ..Default::default()
+    };
+    let mut sim = Simulation::with_settings(SEED, settings);
+
+    assert_eq!(sim.lives(), 3);
+}
+
+#[test]
+fn director_only_moves_the_pressure_of_adaptive_runs() {
+    let pressure_after_bot_run = |adaptive| {
+        let settings = RunSettings {
+            adaptive,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_settings(SEED, settings);
+        let mut bot = Bot::default();
+        for _ in 0..20 * TICKS_PER_S {
+            let observation = sim.observe();
+            sim.step(bot.act(&observation));
+        }
+        sim.app_mut().world.resource::<Director>().pressure
+    };
+
+    assert_eq!(pressure_after_bot_run(false), 1.);
+    assert_ne!(pressure_after_bot_run(true), 1.);
+}
+
+#[test]
+fn hardcore_runs_get_a_single_life_whatever_the_difficulty() {
+    let settings = RunSettings {
+        mode: GameMode::Hardcore,
+        difficulty: Difficulty::Easy,
+        ..Default::default()
+    };
+    let mut sim = Simulation::with_settings(SEED, settings);
+
+    assert_eq!(sim.lives(), 1);
+}
+
+#[test]
+fn time_attack_ends_when_the_time_is_up() {
+    let mut options = LaunchOptions {
+        seed: Some(SEED),
+        run: RunSettings {
+            mode: GameMode::TimeAttack,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    options.tuning.base.initial_enemies = 0;
+    options.tuning.base.modes.time_attack_s = 2.;
+    let mut sim = Simulation::with_options(options);
+    sim.run(TICKS_PER_S, InputActions::empty());
+
+    assert_eq!(sim.outcome(), None);
+
+    sim.run(TICKS_PER_S + 1, InputActions::empty());
+
+    assert_eq!(sim.outcome(), Some(RunOutcome::TimeUp));
+    assert_eq!(sim.death_cause(), None);
+
+    sim.run(3 * TICKS_PER_S, InputActions::empty());
+
+    assert_eq!(sim.state(), AppState::GameOver);
+}
+
+#[test]
+fn wave_survival_is_won_by_clearing_its_waves() {
+    let mut options = LaunchOptions {
+        seed: Some(SEED),
+        run: RunSettings {
+            mode: GameMode::WaveSurvival,
+            difficulty: Difficulty::Easy,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    options.tuning.base.modes.survival_waves = 1;
+    let mut sim = Simulation::with_options(options);
+    let mut bot = Bot::default();
+    sim.run(TICKS_PER_S, InputActions::empty());
+
+    // nothing roams outside of the waves
+    assert!(sim.observe().enemies.is_empty());
+
+    while sim.outcome().is_none() && sim.tick() < 120 * TICKS_PER_S {
+        let observation = sim.observe();
+        sim.step(bot.act(&observation));
+    }
+
+    assert_eq!(sim.outcome(), Some(RunOutcome::Victory));
+}
+
+#[test]
+fn mirrored_controls_swap_left_and_right() {
+    let settings = RunSettings {
+        modifiers: Modifiers::MIRRORED_CONTROLS,
+        ..Default::default()
+    };
+    let mut sim = Simulation::with_settings(SEED, settings);
+    let start = sim.observe().player.unwrap().position;
+    sim.run(20, InputActions::RIGHT);
+
+    assert!(sim.observe().player.unwrap().position.x < start.x);
+}
+
+#[test]
+fn double_enemy_speed_doubles_how_far_enemies_fly() {
+    let tank_travel = |modifiers| {
+        let settings = RunSettings {
+            modifiers,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_settings(SEED, settings);
+        let start = Vec2::new(0., 100.);
+        sim.spawn_enemy("tank", start);
+        sim.run(90, InputActions::empty());
+        sim.observe().enemies[0].position.distance(start)
+    };
+
+    let normal = tank_travel(Modifiers::empty());
+    let doubled = tank_travel(Modifiers::DOUBLE_ENEMY_SPEED);
+
+    assert!(normal > 0.);
+    assert!((doubled - 2. * normal).abs() < 0.01);
+}
@@ -0,0 +1,37 @@
+use bevy_game::resources::{
+    game_tuning::{GameTuning, TuningPresets, TUNING_PATH},
+    run_settings::Difficulty,
+};
+
+#[test]
+fn shipped_tuning_matches_the_defaults() {
+    let presets = TuningPresets::load(TUNING_PATH).expect("shipped tuning is valid");
+
+    // values missing from a tuning file fall back to these
+    assert_eq!(presets.base, GameTuning::default());
+}
+
+#[test]
+fn missing_values_default_and_difficulties_overlay_the_base() {
+    let presets = TuningPresets::parse(
+        "(base: (initial_enemies: 4), difficulties: { Hard: (initial_enemies: Some(6)) })",
+    )
+    .unwrap();
+
+    assert_eq!(presets.tuning(Difficulty::Normal).initial_enemies, 4);
+    assert_eq!(presets.tuning(Difficulty::Hard).initial_enemies, 6);
+    assert_eq!(
+        presets.tuning(Difficulty::Hard).base_speed,
+        presets.base.base_speed
+    );
+}
+
+#[test]
+fn invalid_tuning_is_rejected() {
+    assert!(TuningPresets::parse("(base: (base_speed: 0.0))").is_err());
+    assert!(TuningPresets::parse("(base: (base_sped: 200.0))").is_err());
+    assert!(TuningPresets::parse(
+        "(difficulties: { Insane: (enemy_respawn_delay_s: Some(-1.0)) })"
+    )
+    .is_err());
+}